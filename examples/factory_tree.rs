@@ -0,0 +1,200 @@
+use gtk::prelude::{BoxExt, ButtonExt, GtkWindowExt, OrientableExt, WidgetExt};
+use relm4::factory::{
+    FactoryComponent, FactorySender, FactoryTree, TreeFactoryComponent, TreeIndex,
+};
+use relm4::{gtk, ComponentParts, ComponentSender, RelmApp, RelmWidgetExt, SimpleComponent};
+
+#[derive(Debug)]
+struct Comment {
+    text: String,
+    expanded: bool,
+}
+
+#[derive(Debug)]
+enum CommentMsg {
+    Toggle,
+}
+
+#[derive(Debug)]
+enum CommentOutput {
+    Reply,
+    Toggled(bool),
+    Remove,
+}
+
+#[relm4::factory]
+impl FactoryComponent for Comment {
+    type Init = String;
+    type Input = CommentMsg;
+    type Output = CommentOutput;
+    type CommandOutput = ();
+    type ParentInput = AppMsg;
+    type ParentWidget = gtk::Box;
+    type Index = TreeIndex;
+
+    view! {
+        root = gtk::Box {
+            set_orientation: gtk::Orientation::Vertical,
+            set_spacing: 5,
+
+            gtk::Box {
+                set_orientation: gtk::Orientation::Horizontal,
+                set_spacing: 10,
+
+                gtk::Button {
+                    #[watch]
+                    set_label: if self.expanded { "-" } else { "+" },
+                    connect_clicked => CommentMsg::Toggle,
+                },
+
+                gtk::Label {
+                    set_label: &self.text,
+                },
+
+                gtk::Button {
+                    set_label: "Reply",
+                    connect_clicked[sender] => move |_| {
+                        sender.output(CommentOutput::Reply)
+                    }
+                },
+
+                gtk::Button {
+                    set_label: "Remove",
+                    connect_clicked[sender] => move |_| {
+                        sender.output(CommentOutput::Remove)
+                    }
+                },
+            }
+        }
+    }
+
+    fn init_model(text: Self::Init, _index: &TreeIndex, _sender: FactorySender<Self>) -> Self {
+        Self {
+            text,
+            expanded: true,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Input, sender: FactorySender<Self>) {
+        match msg {
+            CommentMsg::Toggle => {
+                self.expanded = !self.expanded;
+                sender.output(CommentOutput::Toggled(self.expanded));
+            }
+        }
+    }
+}
+
+impl TreeFactoryComponent for Comment {
+    fn init_children_container(&self, root: &Self::Root) -> gtk::Box {
+        let replies = gtk::Box::new(gtk::Orientation::Vertical, 5);
+        replies.set_margin_start(30);
+        root.append(&replies);
+        replies
+    }
+
+    fn forward_with_index(output: Self::Output, index: &TreeIndex) -> Option<AppMsg> {
+        Some(match output {
+            CommentOutput::Reply => AppMsg::Reply(index.clone()),
+            CommentOutput::Toggled(expanded) => AppMsg::SetExpanded(index.clone(), expanded),
+            CommentOutput::Remove => AppMsg::Remove(index.clone()),
+        })
+    }
+}
+
+struct App {
+    created_comments: usize,
+    comments: FactoryTree<Comment>,
+}
+
+#[derive(Debug)]
+enum AppMsg {
+    Comment,
+    Reply(TreeIndex),
+    SetExpanded(TreeIndex, bool),
+    Remove(TreeIndex),
+}
+
+#[relm4::component]
+impl SimpleComponent for App {
+    type Init = ();
+    type Input = AppMsg;
+    type Output = ();
+
+    view! {
+        gtk::Window {
+            set_title: Some("Factory tree example"),
+            set_default_size: (400, 300),
+
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                set_spacing: 5,
+                set_margin_all: 5,
+
+                gtk::Button {
+                    set_label: "New comment",
+                    connect_clicked => AppMsg::Comment,
+                },
+
+                gtk::ScrolledWindow {
+                    set_vexpand: true,
+
+                    #[local_ref]
+                    comment_box -> gtk::Box {
+                        set_orientation: gtk::Orientation::Vertical,
+                        set_spacing: 5,
+                    }
+                }
+            }
+        }
+    }
+
+    fn init(
+        _: Self::Init,
+        root: &Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = App {
+            created_comments: 0,
+            comments: FactoryTree::new(gtk::Box::default(), sender.input_sender()),
+        };
+
+        let comment_box = model.comments.widget();
+        let widgets = view_output!();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, msg: Self::Input, _sender: ComponentSender<Self>) {
+        // Ignore messages that were queued before their comment was removed.
+        if let AppMsg::Reply(index) | AppMsg::SetExpanded(index, _) | AppMsg::Remove(index) = &msg {
+            if index.is_removed() {
+                return;
+            }
+        }
+
+        match msg {
+            AppMsg::Comment => {
+                self.created_comments += 1;
+                let text = format!("Comment {}", self.created_comments);
+                self.comments.push(&[], text);
+            }
+            AppMsg::Reply(index) => {
+                self.created_comments += 1;
+                let text = format!("Reply {}", self.created_comments);
+                self.comments.push(&index.current_path(), text);
+            }
+            AppMsg::SetExpanded(index, expanded) => {
+                self.comments.set_expanded(&index.current_path(), expanded);
+            }
+            AppMsg::Remove(index) => {
+                self.comments.remove(&index.current_path());
+            }
+        }
+    }
+}
+
+fn main() {
+    let app = RelmApp::new("relm4.example.factory_tree");
+    app.run::<App>(());
+}
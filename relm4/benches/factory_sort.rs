@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use relm4::factory::{DynamicIndex, FactoryComponent, FactorySender, FactoryVecDeque};
+use relm4::gtk;
+
+const ROWS: usize = 10_000;
+
+#[derive(Debug)]
+struct Row {
+    value: usize,
+}
+
+impl FactoryComponent for Row {
+    type Init = usize;
+    type Input = ();
+    type Output = ();
+    type CommandOutput = ();
+    type ParentInput = ();
+    type ParentWidget = gtk::Box;
+    type Root = gtk::Label;
+    type Widgets = ();
+    type Index = DynamicIndex;
+
+    fn init_model(value: Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
+        Self { value }
+    }
+
+    fn init_root(&self) -> Self::Root {
+        gtk::Label::new(Some(&self.value.to_string()))
+    }
+
+    fn init_widgets(
+        &mut self,
+        _index: &DynamicIndex,
+        _root: &Self::Root,
+        _returned_widget: &gtk::Widget,
+        _sender: FactorySender<Self>,
+    ) -> Self::Widgets {
+    }
+}
+
+/// Creates a factory with the rows `0..ROWS` in a shuffled order.
+fn shuffled_factory() -> FactoryVecDeque<Row> {
+    let (sender, _) = relm4::channel();
+    let mut factory = FactoryVecDeque::new(gtk::Box::default(), &sender);
+
+    let mut guard = factory.guard();
+    for index in 0..ROWS {
+        // 7919 is coprime with `ROWS`, so this is a permutation.
+        guard.push_back(index * 7919 % ROWS);
+    }
+    guard.drop();
+
+    factory
+}
+
+/// Sorts the rows and renders the result.
+fn sort(factory: &mut FactoryVecDeque<Row>) {
+    factory.guard().sort_by_key(|row| row.value);
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default()
+        .warm_up_time(Duration::from_millis(100))
+        .sample_size(10);
+    targets = benchmark
+}
+criterion_main!(benches);
+
+fn benchmark(c: &mut Criterion) {
+    gtk::init().unwrap();
+
+    c.bench_function("factory_sort_10k", |b| {
+        b.iter_batched_ref(shuffled_factory, sort, BatchSize::LargeInput);
+    });
+}
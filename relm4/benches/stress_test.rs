@@ -3,8 +3,14 @@ use std::time::Duration;
 use criterion::{criterion_group, criterion_main, Criterion};
 use gtk::gio::ApplicationFlags;
 use gtk::glib::clone;
-use gtk::prelude::{ApplicationExt, BoxExt, ButtonExt, GtkWindowExt};
-use relm4::{gtk, ComponentParts, ComponentSender, RelmApp, RelmWidgetExt, SimpleComponent};
+use gtk::prelude::{
+    ApplicationExt, ApplicationExtManual, BoxExt, ButtonExt, GtkApplicationExt, GtkWindowExt,
+    ObjectExt, WidgetExt,
+};
+use relm4::{
+    gtk, Component, ComponentController, ComponentParts, ComponentSender, RelmApp, RelmWidgetExt,
+    SimpleComponent,
+};
 
 // Iteration count that appear to be reasonable.
 // Constant delays like GTK's runtime are negligible at this number.
@@ -100,7 +106,7 @@ criterion_group! {
     config = Criterion::default()
         .warm_up_time(Duration::from_millis(100))
         .sample_size(20);
-    targets = benchmark
+    targets = benchmark, benchmark_batched
 }
 criterion_main!(benches);
 
@@ -118,3 +124,30 @@ fn benchmark(c: &mut Criterion) {
         })
     });
 }
+
+fn benchmark_batched(c: &mut Criterion) {
+    c.bench_function("stress_test_batched", move |b| {
+        let application = gtk::Application::new(
+            Some("relm4.bench.stress_test_batched"),
+            ApplicationFlags::FLAGS_NONE,
+        );
+
+        b.iter(move || {
+            // `RelmApp` always uses the default builder, so launch the component manually.
+            let handler = application.connect_activate(|application| {
+                let mut controller = AppModel::builder()
+                    .batch_view_updates()
+                    .launch(application.clone())
+                    .detach();
+                let window = controller.widget().clone();
+                controller.detach_runtime();
+
+                application.add_window(&window);
+                window.set_visible(true);
+            });
+
+            application.run_with_args::<&str>(&[]);
+            application.disconnect(handler);
+        })
+    });
+}
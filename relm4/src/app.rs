@@ -1,12 +1,35 @@
-use gtk::glib;
-use gtk::prelude::{ApplicationExt, ApplicationExtManual, Cast, GtkApplicationExt, IsA, WidgetExt};
-use std::fmt::Debug;
+use gtk::prelude::{
+    ApplicationCommandLineExt, ApplicationExt, ApplicationExtManual, Cast, IsA, WidgetExt,
+};
+use gtk::{gio, glib};
+use std::fmt::{self, Debug};
 
 use crate::component::{AsyncComponent, AsyncComponentBuilder, AsyncComponentController};
-use crate::runtime_util::shutdown_all;
-use crate::{Component, ComponentBuilder, ComponentController, MessageBroker, RUNTIME};
+use crate::runtime_util::{shutdown_all, wait_for_shutdown};
+use crate::{
+    windows, Component, ComponentBuilder, ComponentController, MessageBroker, Sender, RUNTIME,
+};
 
-use std::cell::Cell;
+use futures::future::LocalBoxFuture;
+use futures::{Future, FutureExt};
+use std::cell::{Cell, RefCell};
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Duration;
+
+type ActivateHandler<M> = Box<dyn Fn(&gtk::Application, &Sender<M>)>;
+type OpenHandler<M> = Box<dyn Fn(&gtk::Application, &Sender<M>, &[gio::File], &str)>;
+type CommandLineHandler<M> =
+    Box<dyn Fn(&gtk::Application, &Sender<M>, &gio::ApplicationCommandLine) -> i32>;
+type LaunchRoot<M> = Box<dyn FnOnce(&gtk::Application) -> Sender<M>>;
+type BeforeShutdownHandler<M> = Box<dyn Fn(Sender<M>) -> LocalBoxFuture<'static, bool>>;
+pub(crate) type BeforeShutdown = Rc<dyn Fn() -> LocalBoxFuture<'static, bool>>;
+
+thread_local! {
+    static BEFORE_SHUTDOWN: RefCell<Option<BeforeShutdown>> = RefCell::default();
+    static CHECKING_SHUTDOWN: Cell<bool> = Cell::new(false);
+}
 
 /// An app that runs the main application.
 #[derive(Debug)]
@@ -16,6 +39,180 @@ pub struct RelmApp<M: Debug + 'static> {
     app: gtk::Application,
     broker: Option<&'static MessageBroker<M>>,
     args: Option<Vec<String>>,
+    grace_period: Duration,
+    handlers: AppHandlers<M>,
+}
+
+/// Returns `true` if a hook was registered with [`RelmApp::before_shutdown`].
+pub(crate) fn has_before_shutdown() -> bool {
+    BEFORE_SHUTDOWN.with(|cell| cell.borrow().is_some())
+}
+
+/// Installs the hook registered with [`RelmApp::before_shutdown`].
+pub(crate) fn set_before_shutdown(hook: BeforeShutdown) {
+    BEFORE_SHUTDOWN.with(|cell| cell.replace(Some(hook)));
+}
+
+/// Drops the hook registered with [`RelmApp::before_shutdown`]
+/// together with the sender of the main component it holds.
+pub(crate) fn clear_before_shutdown() {
+    BEFORE_SHUTDOWN.with(|cell| cell.take());
+    CHECKING_SHUTDOWN.with(|checking| checking.set(false));
+}
+
+/// Calls `quit` once the hook registered with [`RelmApp::before_shutdown`]
+/// allowed quitting.
+///
+/// While the hook is running, further requests are ignored.
+pub(crate) fn quit_after_check<F: FnOnce() + 'static>(quit: F) {
+    let hook = BEFORE_SHUTDOWN.with(|cell| cell.borrow().clone());
+    match hook {
+        Some(hook) => {
+            if CHECKING_SHUTDOWN.with(|checking| checking.replace(true)) {
+                return;
+            }
+
+            crate::spawn_local(async move {
+                let allowed = hook().await;
+                CHECKING_SHUTDOWN.with(|checking| checking.set(false));
+                if allowed {
+                    quit();
+                }
+            });
+        }
+        None => quit(),
+    }
+}
+
+/// Quits the application unless the hook registered with
+/// [`RelmApp::before_shutdown`] vetoes.
+///
+/// Use this instead of [`ApplicationExt::quit`] for "Quit" actions,
+/// so users get a chance to save their changes.
+pub fn request_quit() {
+    quit_after_check(|| crate::main_application().quit());
+}
+
+/// A command line passed to the application.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandLine {
+    /// The arguments, including the name of the executable.
+    pub arguments: Vec<OsString>,
+    /// The working directory of the invocation.
+    pub cwd: Option<PathBuf>,
+    /// Whether the command line was forwarded from another instance of the application.
+    pub is_remote: bool,
+}
+
+/// Handlers for the signals of the application that are
+/// emitted after the main window was launched.
+struct AppHandlers<M> {
+    activate: Option<ActivateHandler<M>>,
+    open: Option<OpenHandler<M>>,
+    command_line: Option<CommandLineHandler<M>>,
+    before_shutdown: Option<BeforeShutdownHandler<M>>,
+}
+
+impl<M> Default for AppHandlers<M> {
+    fn default() -> Self {
+        Self {
+            activate: None,
+            open: None,
+            command_line: None,
+            before_shutdown: None,
+        }
+    }
+}
+
+impl<M> Debug for AppHandlers<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AppHandlers")
+            .field("activate", &self.activate.is_some())
+            .field("open", &self.open.is_some())
+            .field("command_line", &self.command_line.is_some())
+            .field("before_shutdown", &self.before_shutdown.is_some())
+            .finish()
+    }
+}
+
+/// Launches the main window once and keeps the sender of its component.
+struct RootLauncher<M> {
+    launch: RefCell<Option<LaunchRoot<M>>>,
+    sender: RefCell<Option<Sender<M>>>,
+}
+
+impl<M: 'static> RootLauncher<M> {
+    /// Returns the sender of the main component and whether
+    /// the component was launched by this call.
+    fn get_or_launch(&self, app: &gtk::Application) -> (Sender<M>, bool) {
+        let launch = self.launch.borrow_mut().take();
+        if let Some(launch) = launch {
+            let sender = launch(app);
+            *self.sender.borrow_mut() = Some(sender.clone());
+            (sender, true)
+        } else {
+            let sender = self.sender.borrow().clone();
+            (sender.expect("Main component wasn't launched"), false)
+        }
+    }
+}
+
+impl<M: 'static> AppHandlers<M> {
+    /// Connects the signals of the application so that the main window
+    /// is launched on the first signal and the handlers are called afterwards.
+    fn connect(self, app: &gtk::Application, launch: LaunchRoot<M>) {
+        let Self {
+            activate,
+            open,
+            command_line,
+            before_shutdown,
+        } = self;
+
+        let launch: LaunchRoot<M> = if let Some(before_shutdown) = before_shutdown {
+            Box::new(move |app| {
+                let sender = launch(app);
+                let hook_sender = sender.clone();
+                set_before_shutdown(Rc::new(move || before_shutdown(hook_sender.clone())));
+                sender
+            })
+        } else {
+            launch
+        };
+
+        let root = Rc::new(RootLauncher {
+            launch: RefCell::new(Some(launch)),
+            sender: RefCell::default(),
+        });
+
+        {
+            let root = root.clone();
+            app.connect_activate(move |app| {
+                let (sender, launched) = root.get_or_launch(app);
+                if !launched {
+                    if let Some(activate) = &activate {
+                        activate(app, &sender);
+                    } else {
+                        windows::present_latest();
+                    }
+                }
+            });
+        }
+
+        if let Some(open) = open {
+            let root = root.clone();
+            app.connect_open(move |app, files, hint| {
+                let (sender, _) = root.get_or_launch(app);
+                open(app, &sender, files, hint);
+            });
+        }
+
+        if let Some(command_line) = command_line {
+            app.connect_command_line(move |app, command_line_data| {
+                let (sender, _) = root.get_or_launch(app);
+                command_line(app, &sender, command_line_data)
+            });
+        }
+    }
 }
 
 impl<M: Debug + 'static> RelmApp<M> {
@@ -36,6 +233,8 @@ impl<M: Debug + 'static> RelmApp<M> {
             app,
             broker: None,
             args: None,
+            grace_period: Duration::ZERO,
+            handlers: AppHandlers::default(),
         }
     }
 
@@ -48,6 +247,8 @@ impl<M: Debug + 'static> RelmApp<M> {
             app,
             broker: None,
             args: None,
+            grace_period: Duration::ZERO,
+            handlers: AppHandlers::default(),
         }
     }
 
@@ -65,18 +266,157 @@ impl<M: Debug + 'static> RelmApp<M> {
         self
     }
 
+    /// Handle activations of the application after the main window was launched,
+    /// for example when the user starts the application a second time.
+    ///
+    /// The handler receives the input sender of the main component.
+    /// Use [`windows::open`] to open additional windows from here.
+    /// By default, the most recently opened window is presented.
+    #[must_use]
+    pub fn on_activate<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&gtk::Application, &Sender<M>) + 'static,
+    {
+        self.handlers.activate = Some(Box::new(handler));
+        self
+    }
+
+    /// Handle requests to open files.
+    ///
+    /// This sets [`gio::ApplicationFlags::HANDLES_OPEN`] on the application.
+    /// The main window is launched before the handler is called.
+    #[must_use]
+    pub fn on_open<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&gtk::Application, &Sender<M>, &[gio::File], &str) + 'static,
+    {
+        self.app
+            .set_flags(self.app.flags() | gio::ApplicationFlags::HANDLES_OPEN);
+        self.handlers.open = Some(Box::new(handler));
+        self
+    }
+
+    /// Handle command lines, including those of remote instances.
+    /// The returned value is used as exit status.
+    ///
+    /// This sets [`gio::ApplicationFlags::HANDLES_COMMAND_LINE`] on the application.
+    /// The main window is launched before the handler is called.
+    #[must_use]
+    pub fn on_command_line<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&gtk::Application, &Sender<M>, &gio::ApplicationCommandLine) -> i32 + 'static,
+    {
+        self.app
+            .set_flags(self.app.flags() | gio::ApplicationFlags::HANDLES_COMMAND_LINE);
+        self.handlers.command_line = Some(Box::new(handler));
+        self
+    }
+
+    /// Ask before the application quits, for example to show
+    /// a dialog if there are unsaved changes.
+    ///
+    /// The hook receives the input sender of the main component.
+    /// If the returned future resolves to `false`, the application keeps running.
+    ///
+    /// The hook is called when the last window is closed or
+    /// when [`request_quit`](crate::request_quit) is called.
+    /// It's not called if the application is quit through [`ApplicationExt::quit`].
+    #[must_use]
+    pub fn before_shutdown<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(Sender<M>) -> Fut + 'static,
+        Fut: Future<Output = bool> + 'static,
+    {
+        self.handlers.before_shutdown = Some(Box::new(move |sender| hook(sender).boxed_local()));
+        self
+    }
+
+    /// Give commands time to finish after the application was closed.
+    ///
+    /// After the application quits, all components are shut down.
+    /// Commands that were registered with [`on_shutdown`](crate::shutdown::AttachedShutdown::on_shutdown)
+    /// can use this time to finish, for example to flush data to disk.
+    /// Once all of them completed or the grace period elapsed, [`run`](Self::run) returns.
+    ///
+    /// The default grace period is zero.
+    #[must_use]
+    pub fn shutdown_grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Send files that the application is requested to open
+    /// as input message to the main component.
+    ///
+    /// This also works if a second instance of the application is started with files,
+    /// because GIO forwards them to the primary instance.
+    /// See [`on_open`](Self::on_open) for details.
+    #[must_use]
+    pub fn open_to_input(self) -> Self
+    where
+        M: From<Vec<gio::File>>,
+    {
+        self.map_open(|files, _hint| M::from(files))
+    }
+
+    /// Convert files that the application is requested to open
+    /// and the hint into an input message of the main component.
+    ///
+    /// This also works if a second instance of the application is started with files,
+    /// because GIO forwards them to the primary instance.
+    /// See [`on_open`](Self::on_open) for details.
+    #[must_use]
+    pub fn map_open<F>(self, map: F) -> Self
+    where
+        F: Fn(Vec<gio::File>, String) -> M + 'static,
+    {
+        self.on_open(move |_, sender, files, hint| {
+            sender.emit(map(files.to_vec(), hint.to_owned()));
+        })
+    }
+
+    /// Convert command lines into input messages of the main component.
+    ///
+    /// Command lines of remote instances are forwarded by GIO to the primary instance
+    /// and can be recognized with [`CommandLine::is_remote`].
+    /// If `map` returns [`None`], no message is sent.
+    /// The exit status is always `0`, use [`on_command_line`](Self::on_command_line)
+    /// for more control.
+    #[must_use]
+    pub fn map_command_line<F>(self, map: F) -> Self
+    where
+        F: Fn(CommandLine) -> Option<M> + 'static,
+    {
+        self.on_command_line(move |_, sender, command_line| {
+            let command_line = CommandLine {
+                arguments: command_line.arguments(),
+                cwd: command_line.cwd(),
+                is_remote: command_line.is_remote(),
+            };
+            if let Some(message) = map(command_line) {
+                sender.emit(message);
+            }
+            0
+        })
+    }
+
     /// Runs the application, returns once the application is closed.
     pub fn run<C>(self, payload: C::Init)
     where
         C: Component<Input = M>,
         C::Root: IsA<gtk::Window> + WidgetExt,
     {
-        let Self { app, broker, args } = self;
-
-        let payload = Cell::new(Some(payload));
+        let Self {
+            app,
+            broker,
+            args,
+            grace_period,
+            handlers,
+        } = self;
 
-        app.connect_activate(move |app| {
-            if let Some(payload) = payload.take() {
+        handlers.connect(
+            &app,
+            Box::new(move |app| {
                 assert!(
                     app.is_registered(),
                     "App should be already registered when activated"
@@ -93,13 +433,16 @@ impl<M: Debug + 'static> RelmApp<M> {
 
                 let mut controller = connector.detach();
                 let window = controller.widget().clone();
+                let sender = controller.sender().clone();
 
                 controller.detach_runtime();
 
-                app.add_window(window.as_ref());
+                windows::track(window.as_ref(), Box::new(()));
                 window.set_visible(true);
-            }
-        });
+
+                sender
+            }),
+        );
 
         let _guard = RUNTIME.enter();
         if let Some(args) = args {
@@ -109,7 +452,9 @@ impl<M: Debug + 'static> RelmApp<M> {
         }
 
         // Make sure everything is shut down
+        clear_before_shutdown();
         shutdown_all();
+        wait_for_shutdown(grace_period);
         glib::MainContext::ref_thread_default().iteration(true);
     }
 
@@ -132,12 +477,17 @@ impl<M: Debug + 'static> RelmApp<M> {
         C: AsyncComponent<Input = M>,
         C::Root: IsA<gtk::Window> + WidgetExt,
     {
-        let Self { app, broker, args } = self;
-
-        let payload = Cell::new(Some(payload));
+        let Self {
+            app,
+            broker,
+            args,
+            grace_period,
+            handlers,
+        } = self;
 
-        app.connect_activate(move |app| {
-            if let Some(payload) = payload.take() {
+        handlers.connect(
+            &app,
+            Box::new(move |app| {
                 assert!(
                     app.is_registered(),
                     "App should be already registered when activated"
@@ -154,13 +504,16 @@ impl<M: Debug + 'static> RelmApp<M> {
 
                 let mut controller = connector.detach();
                 let window = controller.widget().clone();
+                let sender = controller.sender().clone();
 
                 controller.detach_runtime();
 
-                app.add_window(window.as_ref());
+                windows::track(window.as_ref(), Box::new(()));
                 window.set_visible(true);
-            }
-        });
+
+                sender
+            }),
+        );
 
         let _guard = RUNTIME.enter();
         if let Some(args) = args {
@@ -170,7 +523,9 @@ impl<M: Debug + 'static> RelmApp<M> {
         }
 
         // Make sure everything is shut down
+        clear_before_shutdown();
         shutdown_all();
+        wait_for_shutdown(grace_period);
         glib::MainContext::ref_thread_default().iteration(true);
     }
 
@@ -187,3 +542,61 @@ impl<M: Debug + 'static> RelmApp<M> {
         self.with_args(args).run_async::<C>(payload)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use futures::FutureExt;
+    use gtk::glib;
+
+    use super::{
+        clear_before_shutdown, has_before_shutdown, quit_after_check, set_before_shutdown,
+    };
+
+    fn settle() {
+        let context = glib::MainContext::default();
+        while context.iteration(false) {}
+    }
+
+    #[gtk::test]
+    fn quit_without_hook() {
+        let quit = Rc::new(Cell::new(false));
+        let quit_ = quit.clone();
+        quit_after_check(move || quit_.set(true));
+        assert!(quit.get());
+    }
+
+    #[gtk::test]
+    fn hook_decides_and_ignores_repeated_requests() {
+        let allow = Rc::new(Cell::new(false));
+        let asked = Rc::new(Cell::new(0));
+        let quits = Rc::new(Cell::new(0));
+
+        let (allow_, asked_) = (allow.clone(), asked.clone());
+        set_before_shutdown(Rc::new(move || {
+            asked_.set(asked_.get() + 1);
+            let allow = allow_.get();
+            async move { allow }.boxed_local()
+        }));
+
+        for _ in 0..2 {
+            let quits = quits.clone();
+            quit_after_check(move || quits.set(quits.get() + 1));
+        }
+        settle();
+        assert_eq!(asked.get(), 1);
+        assert_eq!(quits.get(), 0);
+
+        allow.set(true);
+        let quits_ = quits.clone();
+        quit_after_check(move || quits_.set(quits_.get() + 1));
+        settle();
+        assert_eq!(asked.get(), 2);
+        assert_eq!(quits.get(), 1);
+
+        clear_before_shutdown();
+        assert!(!has_before_shutdown());
+    }
+}
@@ -0,0 +1,214 @@
+//! A typed publish/subscribe event bus.
+//!
+//! Unlike a [`MessageBroker`](crate::MessageBroker), which delivers messages to exactly
+//! one component, the bus delivers every published message to all subscribers of its type.
+//! The type of a message is its topic, so newtypes can be used to separate topics with
+//! the same data.
+//!
+//! Components subscribe with [`ComponentBuilder::subscribe`](crate::ComponentBuilder::subscribe)
+//! and are unsubscribed automatically when they shut down.
+//! Messages can be published from any thread.
+//!
+//! ```
+//! use relm4::bus;
+//!
+//! #[derive(Debug, Clone)]
+//! struct DarkMode(bool);
+//!
+//! let (sender, receiver) = relm4::channel();
+//! let subscription = bus::subscribe(&sender, |DarkMode(enabled)| enabled);
+//!
+//! bus::publish(DarkMode(true));
+//! assert_eq!(receiver.recv_sync(), Some(true));
+//!
+//! // Dropping the subscription unsubscribes.
+//! drop(subscription);
+//! assert_eq!(bus::subscribers::<DarkMode>(), 0);
+//! ```
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use once_cell::sync::Lazy;
+
+use crate::Sender;
+
+/// Delivers a message to a subscriber and returns `false` if the subscriber is gone.
+type Deliver<T> = Arc<dyn Fn(T) -> bool + Send + Sync>;
+
+/// The subscribers of a topic.
+type Topic<T> = Vec<(u64, Deliver<T>)>;
+
+/// The topics of the bus by the type of their messages.
+static TOPICS: Lazy<Mutex<HashMap<TypeId, Box<dyn Any + Send>>>> = Lazy::new(Mutex::default);
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+fn with_topic<T: 'static, R>(func: impl FnOnce(&mut Topic<T>) -> R) -> R {
+    let mut topics = TOPICS.lock().unwrap_or_else(PoisonError::into_inner);
+    let topic = topics
+        .entry(TypeId::of::<T>())
+        .or_insert_with(|| Box::new(Topic::<T>::new()));
+    func(
+        topic
+            .downcast_mut()
+            .expect("Topics are stored by their type"),
+    )
+}
+
+/// Publishes a message to all subscribers of its type.
+///
+/// Subscribers whose receivers were dropped are removed.
+pub fn publish<T: Clone + 'static>(message: T) {
+    // Don't hold the lock while sending, a subscriber might block or publish itself.
+    let subscribers: Vec<(u64, Deliver<T>)> = with_topic(|topic: &mut Topic<T>| topic.clone());
+
+    let mut closed = Vec::new();
+    for (id, deliver) in subscribers {
+        if !deliver(message.clone()) {
+            closed.push(id);
+        }
+    }
+
+    if !closed.is_empty() {
+        with_topic(|topic: &mut Topic<T>| topic.retain(|(id, _)| !closed.contains(id)));
+    }
+}
+
+/// Forwards all messages of type `T` to `sender` after transforming them with `map`.
+///
+/// The subscription ends when the returned [`Subscription`] is dropped.
+pub fn subscribe<T, M, F>(sender: &Sender<M>, map: F) -> Subscription
+where
+    T: 'static,
+    M: Send + 'static,
+    F: Fn(T) -> M + Send + Sync + 'static,
+{
+    let sender = sender.clone();
+    let deliver: Deliver<T> = Arc::new(move |message| sender.send(map(message)).is_ok());
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    with_topic(|topic: &mut Topic<T>| topic.push((id, deliver)));
+
+    Subscription {
+        id,
+        unsubscribe: Some(unsubscribe::<T>),
+    }
+}
+
+/// Returns the number of subscribers of messages of type `T`.
+#[must_use]
+pub fn subscribers<T: 'static>() -> usize {
+    with_topic(|topic: &mut Topic<T>| topic.len())
+}
+
+fn unsubscribe<T: 'static>(id: u64) {
+    with_topic(|topic: &mut Topic<T>| topic.retain(|(other, _)| *other != id));
+}
+
+/// Unsubscribes from a topic of the bus when dropped.
+#[must_use = "Dropping the subscription unsubscribes immediately"]
+pub struct Subscription {
+    id: u64,
+    unsubscribe: Option<fn(u64)>,
+}
+
+impl Subscription {
+    /// Keeps the subscription until the application quits.
+    pub fn detach(mut self) {
+        self.unsubscribe = None;
+    }
+}
+
+impl fmt::Debug for Subscription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscription")
+            .field("id", &self.id)
+            .field("detached", &self.unsubscribe.is_none())
+            .finish()
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(unsubscribe) = self.unsubscribe.take() {
+            unsubscribe(self.id);
+        }
+    }
+}
+
+type Subscribe<Input> = Box<dyn FnOnce(&Sender<Input>) -> Subscription>;
+
+/// Subscriptions of a component builder that are created when the component is launched.
+pub(crate) struct Subscriptions<Input> {
+    pending: Vec<Subscribe<Input>>,
+}
+
+impl<Input> Default for Subscriptions<Input> {
+    fn default() -> Self {
+        Self {
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl<Input> fmt::Debug for Subscriptions<Input> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscriptions")
+            .field("pending", &self.pending.len())
+            .finish()
+    }
+}
+
+impl<Input: Send + 'static> Subscriptions<Input> {
+    pub(crate) fn add<T, F>(&mut self, map: F)
+    where
+        T: 'static,
+        F: Fn(T) -> Input + Send + Sync + 'static,
+    {
+        self.pending
+            .push(Box::new(move |sender| subscribe(sender, map)));
+    }
+}
+
+impl<Input> Subscriptions<Input> {
+    /// Subscribes `sender` to all topics.
+    pub(crate) fn subscribe(self, sender: &Sender<Input>) -> Vec<Subscription> {
+        self.pending
+            .into_iter()
+            .map(|subscribe| subscribe(sender))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{publish, subscribe, subscribers};
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Event(u8);
+
+    #[test]
+    fn publish_to_all() {
+        let (first, first_receiver) = crate::channel();
+        let (second, second_receiver) = crate::channel();
+        let first_subscription = subscribe(&first, |Event(value)| value);
+        let _second_subscription = subscribe(&second, |event: Event| event);
+
+        publish(Event(1));
+        assert_eq!(first_receiver.recv_sync(), Some(1));
+        assert_eq!(second_receiver.recv_sync(), Some(Event(1)));
+
+        drop(first_subscription);
+        drop(second_receiver);
+        assert_eq!(subscribers::<Event>(), 1);
+
+        // Closed receivers are removed when publishing.
+        publish(Event(2));
+        assert_eq!(subscribers::<Event>(), 0);
+        assert!(first_receiver.0.try_recv().is_err());
+    }
+}
@@ -0,0 +1,219 @@
+use std::time::Duration;
+
+use tokio::time::{self, Instant};
+
+use super::{Receiver, Sender};
+
+impl<T> Sender<T>
+where
+    T: Send + 'static,
+{
+    /// Returns a sender that forwards a message to this sender
+    /// only after no new message was sent for `delay`.
+    ///
+    /// Only the latest message of a burst is forwarded, which is useful
+    /// for search fields or other inputs that change rapidly.
+    ///
+    /// The messages are handled on a background task that
+    /// stops once the returned sender and all its clones are dropped.
+    #[must_use]
+    pub fn debounce(&self, delay: Duration) -> Sender<T> {
+        self.adapt(move |receiver, target| debounce(receiver, target, delay))
+    }
+
+    /// Returns a sender that forwards at most one message to this sender
+    /// per `interval`.
+    ///
+    /// The first message is forwarded immediately.
+    /// Messages sent during the interval are replaced by newer ones and
+    /// the latest message is forwarded at the end of the interval.
+    ///
+    /// The messages are handled on a background task that
+    /// stops once the returned sender and all its clones are dropped.
+    #[must_use]
+    pub fn throttle(&self, interval: Duration) -> Sender<T> {
+        self.adapt(move |receiver, target| throttle(receiver, target, interval))
+    }
+
+    /// Returns a sender that collects messages for `window` after the first
+    /// message of a burst arrived and forwards them to this sender afterwards.
+    ///
+    /// If `same` returns `true` for a collected message and a newer one,
+    /// the collected message is dropped, so only the latest message of each
+    /// kind is forwarded. The remaining messages keep their order.
+    ///
+    /// The messages are handled on a background task that
+    /// stops once the returned sender and all its clones are dropped.
+    #[must_use]
+    pub fn coalesce<F>(&self, window: Duration, same: F) -> Sender<T>
+    where
+        F: Fn(&T, &T) -> bool + Send + 'static,
+    {
+        self.adapt(move |receiver, target| coalesce(receiver, target, window, same))
+    }
+
+    /// Spawns `task` on the runtime with the receiver of a new channel
+    /// and a clone of this sender.
+    fn adapt<F, Fut>(&self, task: F) -> Sender<T>
+    where
+        F: FnOnce(Receiver<T>, Sender<T>) -> Fut,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let (sender, receiver) = super::channel();
+        crate::spawn(task(receiver, self.clone()));
+        sender
+    }
+}
+
+async fn debounce<T>(receiver: Receiver<T>, target: Sender<T>, delay: Duration) {
+    while let Some(mut latest) = receiver.recv().await {
+        // Wait until the burst is over. If the sender was dropped, forward immediately.
+        while let Ok(Some(message)) = time::timeout(delay, receiver.recv()).await {
+            latest = message;
+        }
+        if target.send(latest).is_err() {
+            break;
+        }
+    }
+}
+
+async fn throttle<T>(receiver: Receiver<T>, target: Sender<T>, interval: Duration) {
+    'outer: while let Some(message) = receiver.recv().await {
+        if target.send(message).is_err() {
+            break;
+        }
+
+        let mut deadline = Instant::now() + interval;
+        let mut latest = None;
+        loop {
+            match time::timeout_at(deadline, receiver.recv()).await {
+                Ok(Some(message)) => latest = Some(message),
+                Ok(None) => {
+                    if let Some(message) = latest {
+                        time::sleep_until(deadline).await;
+                        target.send(message).ok();
+                    }
+                    break 'outer;
+                }
+                Err(_) => match latest.take() {
+                    Some(message) => {
+                        if target.send(message).is_err() {
+                            break 'outer;
+                        }
+                        deadline += interval;
+                    }
+                    None => break,
+                },
+            }
+        }
+    }
+}
+
+async fn coalesce<T, F>(receiver: Receiver<T>, target: Sender<T>, window: Duration, same: F)
+where
+    F: Fn(&T, &T) -> bool,
+{
+    while let Some(message) = receiver.recv().await {
+        let deadline = Instant::now() + window;
+        let mut batch = vec![message];
+
+        while let Ok(Some(message)) = time::timeout_at(deadline, receiver.recv()).await {
+            batch.retain(|collected| !same(collected, &message));
+            batch.push(message);
+        }
+
+        for message in batch {
+            if target.send(message).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use tokio::time::{self, Instant};
+
+    use crate::Receiver;
+
+    const TICK: Duration = Duration::from_millis(50);
+
+    // The tests run on a paused clock, which only advances when all tasks are idle,
+    // so the timing of the forwarded messages is deterministic.
+
+    /// Returns the next forwarded message together with the time it took to arrive.
+    async fn next<T>(receiver: &Receiver<T>) -> Option<(T, Duration)> {
+        let start = Instant::now();
+        let message = receiver.recv().await?;
+        Some((message, start.elapsed()))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn debounce() {
+        let (input, receiver) = crate::channel();
+        let (target, output) = crate::channel();
+        tokio::spawn(super::debounce(receiver, target, TICK));
+
+        for i in 0..3 {
+            input.send(i).unwrap();
+        }
+        time::sleep(TICK / 2).await;
+        input.send(3).unwrap();
+
+        // The burst ends one delay after the last message.
+        assert_eq!(next(&output).await, Some((3, TICK)));
+
+        input.send(4).unwrap();
+        drop(input);
+        assert_eq!(next(&output).await, Some((4, Duration::ZERO)));
+        assert_eq!(next(&output).await, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn throttle() {
+        let (input, receiver) = crate::channel();
+        let (target, output) = crate::channel();
+        tokio::spawn(super::throttle(receiver, target, TICK));
+
+        for i in 0..3 {
+            input.send(i).unwrap();
+        }
+
+        assert_eq!(next(&output).await, Some((0, Duration::ZERO)));
+        assert_eq!(next(&output).await, Some((2, TICK)));
+
+        // The interval is over, so the next message is forwarded immediately.
+        time::sleep(TICK * 2).await;
+        input.send(3).unwrap();
+        input.send(4).unwrap();
+        drop(input);
+        assert_eq!(next(&output).await, Some((3, Duration::ZERO)));
+        assert_eq!(next(&output).await, Some((4, TICK)));
+        assert_eq!(next(&output).await, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn coalesce() {
+        let (input, receiver) = crate::channel();
+        let (target, output) = crate::channel();
+        tokio::spawn(super::coalesce(
+            receiver,
+            target,
+            TICK,
+            |a: &(u8, u8), b| a.0 == b.0,
+        ));
+
+        for message in [(1, 0), (2, 0), (1, 1), (3, 0), (2, 1)] {
+            input.send(message).unwrap();
+        }
+
+        assert_eq!(next(&output).await, Some(((1, 1), TICK)));
+        assert_eq!(next(&output).await, Some(((3, 0), Duration::ZERO)));
+        assert_eq!(next(&output).await, Some(((2, 1), Duration::ZERO)));
+
+        drop(input);
+        assert_eq!(next(&output).await, None);
+    }
+}
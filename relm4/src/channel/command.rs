@@ -0,0 +1,222 @@
+//! Commands that can be canceled individually or by key.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use futures::future::{AbortHandle, Abortable};
+use futures::{Stream, StreamExt};
+
+use crate::Sender;
+
+/// Handle to a command that can abort it.
+///
+/// Dropping the handle doesn't abort the command.
+#[derive(Debug, Clone)]
+pub struct CommandHandle {
+    abort: AbortHandle,
+    finished: Arc<AtomicBool>,
+}
+
+impl CommandHandle {
+    /// Aborts the command by dropping its future.
+    ///
+    /// Messages the command already sent are still handled.
+    pub fn abort(&self) {
+        self.abort.abort();
+    }
+
+    /// Returns `true` if the command was aborted.
+    #[must_use]
+    pub fn is_aborted(&self) -> bool {
+        self.abort.is_aborted()
+    }
+
+    /// Returns `true` if the command completed or was aborted.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire) || self.is_aborted()
+    }
+}
+
+/// Wraps `future` so it can be aborted with the returned handle.
+pub(super) fn cancellable<F>(future: F) -> (impl Future<Output = ()>, CommandHandle)
+where
+    F: Future<Output = ()>,
+{
+    let (abort, registration) = AbortHandle::new_pair();
+    let finished = Arc::new(AtomicBool::new(false));
+
+    let task_finished = Arc::clone(&finished);
+    let future = async move {
+        Abortable::new(future, registration).await.ok();
+        task_finished.store(true, Ordering::Release);
+    };
+
+    (future, CommandHandle { abort, finished })
+}
+
+/// Sends the items of `stream` to `sender` after transforming them with `map`
+/// until the stream ends or the receiver is dropped.
+pub(super) async fn forward_stream<S, F, T>(stream: S, sender: Sender<T>, mut map: F)
+where
+    S: Stream,
+    F: FnMut(S::Item) -> T,
+{
+    futures::pin_mut!(stream);
+    while let Some(item) = stream.next().await {
+        if sender.send(map(item)).is_err() {
+            break;
+        }
+    }
+}
+
+/// The running commands of a component that were started with a key.
+#[derive(Debug, Default)]
+pub(super) struct KeyedCommands {
+    running: Mutex<HashMap<Cow<'static, str>, (u64, AbortHandle)>>,
+    next_id: AtomicU64,
+}
+
+impl KeyedCommands {
+    /// Spawns `future` on the runtime.
+    ///
+    /// If a key is given, a running command with the same key is aborted.
+    pub(super) fn spawn<F>(
+        self: &Arc<Self>,
+        key: Option<Cow<'static, str>>,
+        future: F,
+    ) -> CommandHandle
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let commands = Arc::clone(self);
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (future, handle) = cancellable(future);
+
+        if let Some(key) = &key {
+            let previous = self
+                .running
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert(key.clone(), (id, handle.abort.clone()));
+            if let Some((_, previous)) = previous {
+                previous.abort();
+            }
+        }
+
+        crate::spawn(async move {
+            future.await;
+            if let Some(key) = key {
+                commands.remove(&key, id);
+            }
+        });
+
+        handle
+    }
+
+    /// Removes the command with `id` unless it was replaced by a newer command.
+    fn remove(&self, key: &str, id: u64) {
+        let mut running = self.running.lock().unwrap_or_else(PoisonError::into_inner);
+        if running.get(key).map_or(false, |(other, _)| *other == id) {
+            running.remove(key);
+        }
+    }
+
+    /// Aborts all running commands.
+    pub(super) fn cancel_all(&self) {
+        let running =
+            std::mem::take(&mut *self.running.lock().unwrap_or_else(PoisonError::into_inner));
+        for (_, abort) in running.into_values() {
+            abort.abort();
+        }
+    }
+
+    /// Aborts the command with `key` and returns `true` if it was running.
+    pub(super) fn cancel(&self, key: &str) -> bool {
+        let command = self
+            .running
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(key);
+        command.map(|(_, abort)| abort.abort()).is_some()
+    }
+
+    pub(super) fn is_running(&self, key: &str) -> bool {
+        self.running
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains_key(key)
+    }
+
+    pub(super) fn keys(&self) -> Vec<Cow<'static, str>> {
+        self.running
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .keys()
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::{cancellable, forward_stream, KeyedCommands};
+
+    fn wait_until(condition: impl Fn() -> bool) {
+        for _ in 0..100 {
+            if condition() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("Condition wasn't met in time");
+    }
+
+    #[test]
+    fn latest_wins() {
+        let commands = Arc::new(KeyedCommands::default());
+        let (sender, receiver) = crate::channel();
+
+        let first = commands.spawn(Some("search".into()), futures::future::pending());
+        assert!(commands.is_running("search"));
+
+        let second = commands.spawn(Some("search".into()), async move {
+            sender.send(2).unwrap();
+        });
+        assert!(first.is_aborted());
+        assert_eq!(receiver.recv_sync(), Some(2));
+
+        wait_until(|| second.is_finished() && !commands.is_running("search"));
+        assert!(commands.keys().is_empty());
+    }
+
+    #[test]
+    fn cancel() {
+        let commands = Arc::new(KeyedCommands::default());
+        let handle = commands.spawn(Some("load".into()), futures::future::pending());
+
+        assert_eq!(commands.keys(), ["load"]);
+        assert!(commands.cancel("load"));
+        assert!(handle.is_finished());
+        assert!(!commands.cancel("load"));
+    }
+
+    #[test]
+    fn stream() {
+        let (sender, receiver) = crate::channel();
+        let stream = futures::stream::iter([1, 2]);
+        let (future, handle) = cancellable(forward_stream(stream, sender, |n| n * 10));
+
+        futures::executor::block_on(future);
+        assert!(handle.is_finished());
+        assert_eq!(receiver.recv_sync(), Some(10));
+        assert_eq!(receiver.recv_sync(), Some(20));
+        assert_eq!(receiver.recv_sync(), None);
+    }
+}
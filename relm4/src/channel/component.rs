@@ -3,13 +3,22 @@
 
 //! Contains various flavors of channels to send messages between components and workers.
 
+use std::borrow::Cow;
 use std::fmt::Debug;
 use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
+use futures::Stream;
+
+use super::command::{self, KeyedCommands};
+use super::timer;
+use crate::component::async_worker::AsyncWorker;
 use crate::component::AsyncComponent;
 use crate::factory::{AsyncFactoryComponent, FactoryComponent};
-use crate::{Component, Sender, ShutdownReceiver};
+use crate::metrics::CommandGuard;
+use crate::{Canceled, CommandHandle, Component, Responder, Sender, ShutdownReceiver, TimerHandle};
 
 // Contains senders used by components and factories internally.
 #[derive(Debug)]
@@ -25,6 +34,12 @@ where
     /// Emits command outputs.
     command: Sender<CommandOutput>,
     shutdown: ShutdownReceiver,
+    /// The type name of the component, used for its [`metrics`](crate::metrics).
+    type_name: &'static str,
+    /// Commands that can be canceled by key.
+    keyed: Arc<KeyedCommands>,
+    /// Whether the component was stopped after a panic.
+    stopped: AtomicBool,
 }
 
 impl<Input, Output, CommandOutput> ComponentSenderInner<Input, Output, CommandOutput>
@@ -62,8 +77,25 @@ where
     /// Emit an input to the component.
     fn input(&self, message: Input) {
         // Input messages should always be safe to send
-        // because the runtime keeps the receiver alive.
-        self.input.send(message).expect("The runtime of the component was shutdown. Maybe you accidentally dropped a controller?");
+        // because the runtime keeps the receiver alive,
+        // unless it was stopped after a panic.
+        if let Err(message) = self.input.send(message) {
+            if self.stopped.load(Ordering::Acquire) {
+                tracing::warn!(
+                    "Component {} was stopped after a panic, dropping input {message:?}",
+                    self.type_name
+                );
+            } else {
+                panic!("The runtime of the component was shutdown. Maybe you accidentally dropped a controller? {message:?}");
+            }
+        }
+    }
+
+    /// Drops further inputs instead of panicking and
+    /// cancels the keyed commands, because the component was stopped after a panic.
+    fn stop(&self) {
+        self.stopped.store(true, Ordering::Release);
+        self.keyed.cancel_all();
     }
 
     /// This is not public because factories can unwrap the result
@@ -82,11 +114,34 @@ where
     {
         let recipient = self.shutdown.clone();
         let sender = self.command.clone();
+        let in_flight = CommandGuard::new(self.type_name);
         crate::spawn(async move {
             cmd(sender, recipient).await;
+            drop(in_flight);
         });
     }
 
+    /// Spawns an asynchronous command that can be aborted with the returned handle.
+    ///
+    /// If a key is given, a running command with the same key is aborted.
+    fn cancellable_command<Cmd, Fut>(
+        &self,
+        key: Option<Cow<'static, str>>,
+        cmd: Cmd,
+    ) -> CommandHandle
+    where
+        Cmd: FnOnce(Sender<CommandOutput>, ShutdownReceiver) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        let recipient = self.shutdown.clone();
+        let sender = self.command.clone();
+        let in_flight = CommandGuard::new(self.type_name);
+        self.keyed.spawn(key, async move {
+            cmd(sender, recipient).await;
+            drop(in_flight);
+        })
+    }
+
     /// Spawns a synchronous command.
     ///
     /// This is particularly useful for CPU-intensive background jobs that
@@ -99,7 +154,11 @@ where
         Cmd: FnOnce(Sender<CommandOutput>) + Send + 'static,
     {
         let sender = self.command.clone();
-        crate::spawn_blocking(move || cmd(sender));
+        let in_flight = CommandGuard::new(self.type_name);
+        crate::spawn_blocking(move || {
+            cmd(sender);
+            drop(in_flight);
+        });
     }
 
     /// Spawns a future that will be dropped as soon as the factory component is shut down.
@@ -150,6 +209,9 @@ macro_rules! sender_impl {
                         output,
                         command,
                         shutdown,
+                        type_name: std::any::type_name::<C>(),
+                        keyed: Arc::default(),
+                        stopped: AtomicBool::new(false),
                     }),
                 }
             }
@@ -186,6 +248,20 @@ macro_rules! sender_impl {
                 self.shared.input(message);
             }
 
+            /// Sends a request to the component itself and waits for the reply.
+            ///
+            /// This is useful for commands that need data from the model.
+            /// The request is canceled if the component shuts down before it replies.
+            pub async fn request<R, F>(&self, message: F) -> Result<R, Canceled>
+            where
+                F: FnOnce(Responder<R>) -> C::Input,
+            {
+                self.shared
+                    .input
+                    .request_until_shutdown(self.shared.shutdown.clone(), message)
+                    .await
+            }
+
             /// Spawns an asynchronous command.
             /// You can bind the the command to the lifetime of the component
             /// by using a [`ShutdownReceiver`].
@@ -197,6 +273,62 @@ macro_rules! sender_impl {
                 self.shared.command(cmd)
             }
 
+            /// Spawns an asynchronous command that can be aborted with the returned handle.
+            pub fn cancellable_command<Cmd, Fut>(&self, cmd: Cmd) -> CommandHandle
+            where
+                Cmd: FnOnce(Sender<C::CommandOutput>, ShutdownReceiver) -> Fut + Send + 'static,
+                Fut: Future<Output = ()> + Send,
+            {
+                self.shared.cancellable_command(None, cmd)
+            }
+
+            /// Spawns an asynchronous command under `key`.
+            ///
+            /// A running command with the same key is aborted, so only the latest
+            /// command for each key sends its results, for example for search queries.
+            pub fn keyed_command<K, Cmd, Fut>(&self, key: K, cmd: Cmd) -> CommandHandle
+            where
+                K: Into<Cow<'static, str>>,
+                Cmd: FnOnce(Sender<C::CommandOutput>, ShutdownReceiver) -> Fut + Send + 'static,
+                Fut: Future<Output = ()> + Send,
+            {
+                self.shared.cancellable_command(Some(key.into()), cmd)
+            }
+
+            /// Spawns a future under `key` that will be dropped as soon as the component is shut down.
+            ///
+            /// Essentially, this is a simpler version of [`Self::keyed_command()`].
+            pub fn keyed_oneshot_command<K, Fut>(&self, key: K, future: Fut) -> CommandHandle
+            where
+                K: Into<Cow<'static, str>>,
+                Fut: Future<Output = C::CommandOutput> + Send + 'static,
+            {
+                self.keyed_command(key, move |out, shutdown| {
+                    shutdown
+                        .register(async move { out.send(future.await) })
+                        .drop_on_shutdown()
+                })
+            }
+
+            /// Aborts the command with `key`.
+            ///
+            /// Returns `true` if the command was still running.
+            pub fn cancel_command(&self, key: &str) -> bool {
+                self.shared.keyed.cancel(key)
+            }
+
+            /// Returns `true` if a command with `key` is running.
+            #[must_use]
+            pub fn is_command_running(&self, key: &str) -> bool {
+                self.shared.keyed.is_running(key)
+            }
+
+            /// Returns the keys of all running keyed commands.
+            #[must_use]
+            pub fn running_commands(&self) -> Vec<Cow<'static, str>> {
+                self.shared.keyed.keys()
+            }
+
             /// Spawns a synchronous command.
             ///
             /// This is particularly useful for CPU-intensive background jobs that
@@ -230,6 +362,137 @@ macro_rules! sender_impl {
             {
                 self.shared.spawn_oneshot_command(cmd)
             }
+
+            /// Forwards the items of `stream` as input messages after transforming them with `map`.
+            ///
+            /// The stream is polled on the runtime of Relm4 until it ends,
+            /// the returned handle is aborted or the component shuts down.
+            pub fn subscribe_stream<S, F>(&self, stream: S, map: F) -> CommandHandle
+            where
+                S: Stream + Send + 'static,
+                F: FnMut(S::Item) -> C::Input + Send + 'static,
+                C::Input: Send,
+            {
+                let forward = command::forward_stream(stream, self.input_sender().clone(), map);
+                let (future, handle) = command::cancellable(
+                    self.shared
+                        .shutdown
+                        .clone()
+                        .register(forward)
+                        .drop_on_shutdown(),
+                );
+                crate::spawn(future);
+                handle
+            }
+
+            /// Forwards the output of `future` as an input message after transforming it with `map`.
+            ///
+            /// The future is polled on the runtime of Relm4 until it completes,
+            /// the returned handle is aborted or the component shuts down.
+            pub fn subscribe_future<Fut, F>(&self, future: Fut, map: F) -> CommandHandle
+            where
+                Fut: Future + Send + 'static,
+                F: FnOnce(Fut::Output) -> C::Input + Send + 'static,
+                C::Input: Send,
+            {
+                let mut map = Some(map);
+                self.subscribe_stream(futures::stream::once(future), move |output| {
+                    let map = map.take().expect("Futures complete only once");
+                    map(output)
+                })
+            }
+
+            /// Forwards the items of `stream` as input messages after transforming them with `map`.
+            ///
+            /// Unlike [`Self::subscribe_stream()`], the stream is polled on the main context of the
+            /// current thread, so it doesn't need to be [`Send`].
+            pub fn subscribe_local_stream<S, F>(&self, stream: S, map: F) -> CommandHandle
+            where
+                S: Stream + 'static,
+                F: FnMut(S::Item) -> C::Input + 'static,
+            {
+                let forward = command::forward_stream(stream, self.input_sender().clone(), map);
+                let (future, handle) = command::cancellable(
+                    self.shared
+                        .shutdown
+                        .clone()
+                        .register(forward)
+                        .drop_on_shutdown(),
+                );
+                crate::spawn_local(future);
+                handle
+            }
+
+            /// Sends the input returned by `message` every `period`.
+            ///
+            /// The timer runs on the main context of the current thread, so `message` doesn't need
+            /// to be [`Send`]. It stops when it's canceled or the component shuts down.
+            pub fn interval<F>(&self, period: Duration, message: F) -> TimerHandle
+            where
+                F: FnMut() -> C::Input + 'static,
+            {
+                timer::start(
+                    self.input_sender().clone(),
+                    self.shared.shutdown.clone(),
+                    period,
+                    true,
+                    message,
+                )
+            }
+
+            /// Sends `message` once after `delay`.
+            ///
+            /// The timer runs on the main context of the current thread, so `message` doesn't need
+            /// to be [`Send`]. It's dropped when the timer is canceled or the component shuts down.
+            /// Use [`TimerHandle::reset`] to delay the message while it's pending.
+            /// Once the message was sent, resetting the timer has no effect.
+            pub fn timeout(&self, delay: Duration, message: C::Input) -> TimerHandle {
+                let mut message = Some(message);
+                timer::start(
+                    self.input_sender().clone(),
+                    self.shared.shutdown.clone(),
+                    delay,
+                    false,
+                    move || message.take().expect("Timeouts fire only once"),
+                )
+            }
+
+            /// Returns a sender that emits an input to the component
+            /// only after no new message was sent for `delay`.
+            ///
+            /// See [`Sender::debounce`] for details.
+            #[must_use]
+            pub fn debounce_input(&self, delay: Duration) -> Sender<C::Input>
+            where
+                C::Input: Send,
+            {
+                self.input_sender().debounce(delay)
+            }
+
+            /// Returns a sender that emits at most one input to the component
+            /// per `interval`.
+            ///
+            /// See [`Sender::throttle`] for details.
+            #[must_use]
+            pub fn throttle_input(&self, interval: Duration) -> Sender<C::Input>
+            where
+                C::Input: Send,
+            {
+                self.input_sender().throttle(interval)
+            }
+
+            /// Returns a sender that collects inputs for `window` and
+            /// only emits the latest input for which `same` returns `true`.
+            ///
+            /// See [`Sender::coalesce`] for details.
+            #[must_use]
+            pub fn coalesce_input<F>(&self, window: Duration, same: F) -> Sender<C::Input>
+            where
+                C::Input: Send,
+                F: Fn(&C::Input, &C::Input) -> bool + Send + 'static,
+            {
+                self.input_sender().coalesce(window, same)
+            }
         }
 
         impl<C: $trait> Clone for $name<C> {
@@ -242,7 +505,26 @@ macro_rules! sender_impl {
     };
 }
 
+/// Adds the methods that the runtime uses to recover a component from panics.
+macro_rules! recovery_impl {
+    ($name:ident, $trait:ident) => {
+        impl<C: $trait> $name<C> {
+            /// Cancels the keyed commands of a model that is replaced after a panic.
+            pub(crate) fn cancel_commands(&self) {
+                self.shared.keyed.cancel_all();
+            }
+
+            /// Drops further inputs instead of panicking, because the
+            /// component was stopped after a panic.
+            pub(crate) fn stop(&self) {
+                self.shared.stop();
+            }
+        }
+    };
+}
+
 sender_impl!(ComponentSender, Component);
+recovery_impl!(ComponentSender, Component);
 
 impl<C: Component> ComponentSender<C> {
     /// Emit an output to the component.
@@ -257,6 +539,7 @@ impl<C: Component> ComponentSender<C> {
 }
 
 sender_impl!(AsyncComponentSender, AsyncComponent);
+recovery_impl!(AsyncComponentSender, AsyncComponent);
 
 impl<C: AsyncComponent> AsyncComponentSender<C> {
     /// Emit an output to the component.
@@ -287,3 +570,18 @@ impl<C: AsyncFactoryComponent> AsyncFactorySender<C> {
         self.shared.output(message).unwrap()
     }
 }
+
+sender_impl!(AsyncWorkerSender, AsyncWorker);
+recovery_impl!(AsyncWorkerSender, AsyncWorker);
+
+impl<C: AsyncWorker> AsyncWorkerSender<C> {
+    /// Emit an output to the worker.
+    ///
+    /// Returns [`Err`] if all receivers were dropped,
+    /// for example by [`detach`].
+    ///
+    /// [`detach`]: crate::component::async_worker::AsyncWorkerHandle::detach
+    pub fn output(&self, message: C::Output) -> Result<(), C::Output> {
+        self.shared.output(message)
+    }
+}
@@ -1,30 +1,46 @@
+mod adapters;
+mod command;
 mod component;
+mod policy;
+mod request;
 /// Cancellation mechanism used by Relm4.
 pub mod shutdown;
+mod timer;
 
-pub use component::{AsyncComponentSender, AsyncFactorySender, ComponentSender, FactorySender};
+pub use command::CommandHandle;
+pub use component::{
+    AsyncComponentSender, AsyncFactorySender, AsyncWorkerSender, ComponentSender, FactorySender,
+};
+pub use policy::{bounded_channel, ChannelOptions, OverflowPolicy};
+pub use request::{Canceled, Responder};
+pub use timer::TimerHandle;
+
+pub(crate) use policy::ReceiverToken;
 
 // Copyright 2022 System76 <info@system76.com>
 // SPDX-License-Identifier: MIT or Apache-2.0
 
 use std::fmt;
+use std::sync::Arc;
 
 use flume::r#async::RecvStream;
 
+use policy::SendPolicy;
+
 /// Create an unbounded channel to send messages
 /// between different parts of you application.
 #[must_use]
 pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
     let (tx, rx) = flume::unbounded();
-    (Sender(tx), Receiver(rx))
+    (Sender(tx, None), Receiver(rx, ReceiverToken::default()))
 }
 
 /// A Relm4 sender sends messages to a component or worker.
-pub struct Sender<T>(pub(crate) flume::Sender<T>);
+pub struct Sender<T>(pub(crate) flume::Sender<T>, Option<Arc<SendPolicy<T>>>);
 
 impl<T> From<flume::Sender<T>> for Sender<T> {
     fn from(sender: flume::Sender<T>) -> Self {
-        Self(sender)
+        Self(sender, None)
     }
 }
 
@@ -43,25 +59,37 @@ impl<T> Sender<T> {
     ///
     /// If all receivers where dropped, [`Err`] is returned
     /// with the content of the message.
+    ///
+    /// If the channel is bounded and full, the message is
+    /// handled according to its [`OverflowPolicy`].
     pub fn send(&self, message: T) -> Result<(), T> {
-        self.0.send(message).map_err(|e| e.into_inner())
+        if let Some(policy) = &self.1 {
+            policy.send(&self.0, message)
+        } else {
+            self.0.send(message).map_err(|e| e.into_inner())
+        }
     }
 }
 
 impl<T> Clone for Sender<T> {
     fn clone(&self) -> Self {
-        Self(self.0.clone())
+        Self(self.0.clone(), self.1.clone())
     }
 }
 
 impl<T> fmt::Debug for Sender<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_tuple("Sender").field(&self.0).finish()
+        let mut f = f.debug_tuple("Sender");
+        f.field(&self.0);
+        if let Some(policy) = &self.1 {
+            f.field(policy);
+        }
+        f.finish()
     }
 }
 
 /// A Relm4 receiver receives messages from a component or worker.
-pub struct Receiver<T>(pub(crate) flume::Receiver<T>);
+pub struct Receiver<T>(pub(crate) flume::Receiver<T>, pub(crate) ReceiverToken);
 
 impl<T> Receiver<T> {
     /// Receives a message from a component or worker.
@@ -79,6 +107,10 @@ impl<T> Receiver<T> {
         self.0.recv().ok()
     }
 
+    /// Converts the receiver into a stream.
+    ///
+    /// The stream doesn't count as a receiver for bounded channels that discard
+    /// queued messages, so only use it for channels without a policy.
     #[must_use]
     pub(crate) fn into_stream(self) -> RecvStream<'static, T> {
         self.0.into_stream()
@@ -0,0 +1,478 @@
+use std::fmt;
+use std::sync::{Arc, Weak};
+
+use flume::TrySendError;
+
+use super::{Receiver, Sender};
+
+type SameKey<T> = dyn Fn(&T, &T) -> bool + Send + Sync;
+type Prioritizer<T> = dyn Fn(&T) -> bool + Send + Sync;
+
+/// Defines what happens when a message is sent
+/// to a bounded channel that is already full.
+pub enum OverflowPolicy<T> {
+    /// Block the sending thread until there is space in the channel.
+    ///
+    /// Only use this policy for workers and channels that are drained by another thread.
+    /// Components and factories run on the main thread, where any blocked sender,
+    /// including widget signal handlers, stops the loop that drains the channel.
+    /// Launching them with this policy panics.
+    Block,
+    /// Discard the oldest queued message to make room for the new one.
+    DropOldest,
+    /// Discard the new message.
+    DropNewest,
+    /// Replace queued messages that have the same key as the new message.
+    ///
+    /// If no queued message has the same key, the oldest message is discarded.
+    /// Create this policy with [`OverflowPolicy::coalesce_by_key`].
+    CoalesceByKey(Arc<SameKey<T>>),
+}
+
+impl<T> OverflowPolicy<T> {
+    /// Replace queued messages that have the same key as the new message.
+    ///
+    /// ```
+    /// # use relm4::OverflowPolicy;
+    /// #[derive(Debug)]
+    /// enum Msg {
+    ///     Progress(u32, f64),
+    ///     Finished(u32),
+    /// }
+    ///
+    /// // Only keep the latest progress update for each download.
+    /// let policy = OverflowPolicy::coalesce_by_key(|msg: &Msg| match msg {
+    ///     Msg::Progress(id, _) => Some(*id),
+    ///     Msg::Finished(_) => None,
+    /// });
+    /// ```
+    ///
+    /// Messages with the key [`None`] are never coalesced.
+    pub fn coalesce_by_key<K, F>(key: F) -> Self
+    where
+        K: PartialEq,
+        F: Fn(&T) -> Option<K> + Send + Sync + 'static,
+    {
+        Self::CoalesceByKey(Arc::new(move |first, second| match key(first) {
+            Some(first) => key(second).as_ref() == Some(&first),
+            None => false,
+        }))
+    }
+}
+
+impl<T> Clone for OverflowPolicy<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Block => Self::Block,
+            Self::DropOldest => Self::DropOldest,
+            Self::DropNewest => Self::DropNewest,
+            Self::CoalesceByKey(key) => Self::CoalesceByKey(key.clone()),
+        }
+    }
+}
+
+impl<T> fmt::Debug for OverflowPolicy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Block => f.write_str("Block"),
+            Self::DropOldest => f.write_str("DropOldest"),
+            Self::DropNewest => f.write_str("DropNewest"),
+            Self::CoalesceByKey(_) => f.write_str("CoalesceByKey(<key fn>)"),
+        }
+    }
+}
+
+/// Create a bounded channel to send messages
+/// between different parts of you application.
+///
+/// Once `capacity` messages are queued, new messages are
+/// handled according to the [`OverflowPolicy`].
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+#[must_use]
+pub fn bounded_channel<T>(capacity: usize, policy: OverflowPolicy<T>) -> (Sender<T>, Receiver<T>) {
+    let (sender, receiver, _) = ChannelOptions {
+        bound: Some((capacity, policy)),
+        priority: None,
+    }
+    .channel();
+    (sender, receiver)
+}
+
+/// Options for the input channel of a component.
+///
+/// By default, the channel is unbounded and all messages
+/// are handled in the order in which they were sent.
+pub struct ChannelOptions<T> {
+    pub(crate) bound: Option<(usize, OverflowPolicy<T>)>,
+    pub(crate) priority: Option<Arc<Prioritizer<T>>>,
+}
+
+impl<T> Default for ChannelOptions<T> {
+    fn default() -> Self {
+        Self {
+            bound: None,
+            priority: None,
+        }
+    }
+}
+
+impl<T> fmt::Debug for ChannelOptions<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChannelOptions")
+            .field("bound", &self.bound)
+            .field("priority", &self.priority.is_some())
+            .finish()
+    }
+}
+
+impl<T> ChannelOptions<T> {
+    /// Limit the number of messages that can be queued.
+    ///
+    /// Once `capacity` messages are waiting to be processed,
+    /// new messages are handled according to the [`OverflowPolicy`].
+    ///
+    /// # Panics
+    ///
+    /// Creating the channel panics if `capacity` is zero.
+    #[must_use]
+    pub fn bounded(mut self, capacity: usize, policy: OverflowPolicy<T>) -> Self {
+        self.bound = Some((capacity, policy));
+        self
+    }
+
+    /// Handle messages for which `is_priority` returns `true`
+    /// before all other queued messages.
+    ///
+    /// Priority messages are never dropped by an [`OverflowPolicy`].
+    #[must_use]
+    pub fn prioritize<F>(mut self, is_priority: F) -> Self
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        self.priority = Some(Arc::new(is_priority));
+        self
+    }
+
+    /// Creates the channel of a component that is drained on the main thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the channel uses [`OverflowPolicy::Block`], since
+    /// senders on the main thread would block the loop that drains the channel.
+    pub(crate) fn main_thread_channel(self) -> (Sender<T>, Receiver<T>, Option<Receiver<T>>) {
+        assert!(
+            !matches!(self.bound, Some((_, OverflowPolicy::Block))),
+            "OverflowPolicy::Block would dead-lock components on the main thread, use it only for workers"
+        );
+        self.channel()
+    }
+
+    /// Creates a channel and an additional receiver for
+    /// priority messages if a prioritizer was set.
+    pub(crate) fn channel(self) -> (Sender<T>, Receiver<T>, Option<Receiver<T>>) {
+        let Self { bound, priority } = self;
+
+        if bound.is_none() && priority.is_none() {
+            let (sender, receiver) = super::channel();
+            return (sender, receiver, None);
+        }
+
+        let (sender, receiver, overflow) = match bound {
+            Some((capacity, policy)) => {
+                assert!(
+                    capacity > 0,
+                    "Bounded channels need a capacity of at least 1"
+                );
+                let (sender, receiver) = flume::bounded(capacity);
+                let (overflow, token) = match policy {
+                    OverflowPolicy::Block => (Overflow::Block, ReceiverToken::default()),
+                    OverflowPolicy::DropNewest => (Overflow::DropNewest, ReceiverToken::default()),
+                    OverflowPolicy::DropOldest => {
+                        let (queue, token) = Queue::new(&receiver);
+                        (Overflow::DropOldest(queue), token)
+                    }
+                    OverflowPolicy::CoalesceByKey(same_key) => {
+                        let (queue, token) = Queue::new(&receiver);
+                        (Overflow::CoalesceByKey(same_key, queue), token)
+                    }
+                };
+                (sender, Receiver(receiver, token), overflow)
+            }
+            None => {
+                let (sender, receiver) = flume::unbounded();
+                (
+                    sender,
+                    Receiver(receiver, ReceiverToken::default()),
+                    Overflow::Block,
+                )
+            }
+        };
+
+        let (priority, priority_receiver) = match priority {
+            Some(is_priority) => {
+                let (priority_sender, priority_receiver) = flume::unbounded();
+                (
+                    Some((is_priority, priority_sender)),
+                    Some(Receiver(priority_receiver, ReceiverToken::default())),
+                )
+            }
+            None => (None, None),
+        };
+
+        let policy = SendPolicy { overflow, priority };
+
+        (
+            Sender(sender, Some(Arc::new(policy))),
+            receiver,
+            priority_receiver,
+        )
+    }
+}
+
+/// Keeps track of whether a [`Receiver`] of a channel is still alive.
+///
+/// Policies that discard queued messages hold a receiver of their own,
+/// so the channel itself never reports that all receivers were dropped.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ReceiverToken(Option<Arc<()>>);
+
+/// Access to the queue of a channel that is used to discard queued messages.
+struct Queue<T> {
+    receiver: flume::Receiver<T>,
+    receivers: Weak<()>,
+}
+
+impl<T> Queue<T> {
+    fn new(receiver: &flume::Receiver<T>) -> (Self, ReceiverToken) {
+        let token = Arc::new(());
+        let queue = Self {
+            receiver: receiver.clone(),
+            receivers: Arc::downgrade(&token),
+        };
+        (queue, ReceiverToken(Some(token)))
+    }
+
+    /// Returns true if all receivers except for the one of the queue were dropped.
+    fn is_disconnected(&self) -> bool {
+        self.receivers.strong_count() == 0
+    }
+
+    /// Sends a message and discards the oldest messages while the channel is full.
+    fn send_dropping_oldest(&self, sender: &flume::Sender<T>, mut message: T) -> Result<(), T> {
+        loop {
+            match sender.try_send(message) {
+                Err(TrySendError::Full(returned)) => {
+                    tracing::debug!("Channel is full, dropping the oldest message");
+                    self.receiver.try_recv().ok();
+                    message = returned;
+                }
+                Err(TrySendError::Disconnected(message)) => return Err(message),
+                Ok(()) => return Ok(()),
+            }
+        }
+    }
+}
+
+enum Overflow<T> {
+    Block,
+    DropNewest,
+    DropOldest(Queue<T>),
+    CoalesceByKey(Arc<SameKey<T>>, Queue<T>),
+}
+
+/// Decides how a message is sent through a channel.
+pub(crate) struct SendPolicy<T> {
+    overflow: Overflow<T>,
+    priority: Option<(Arc<Prioritizer<T>>, flume::Sender<T>)>,
+}
+
+impl<T> SendPolicy<T> {
+    pub(crate) fn send(&self, sender: &flume::Sender<T>, message: T) -> Result<(), T> {
+        if let Some((is_priority, priority_sender)) = &self.priority {
+            if is_priority(&message) {
+                return priority_sender.send(message).map_err(|e| e.into_inner());
+            }
+        }
+
+        match &self.overflow {
+            Overflow::Block => sender.send(message).map_err(|e| e.into_inner()),
+            Overflow::DropNewest => match sender.try_send(message) {
+                Err(TrySendError::Disconnected(message)) => Err(message),
+                Err(TrySendError::Full(_)) => {
+                    tracing::debug!("Channel is full, dropping the newest message");
+                    Ok(())
+                }
+                Ok(()) => Ok(()),
+            },
+            Overflow::DropOldest(queue) => {
+                if queue.is_disconnected() {
+                    return Err(message);
+                }
+                queue.send_dropping_oldest(sender, message)
+            }
+            Overflow::CoalesceByKey(same_key, queue) => {
+                if queue.is_disconnected() {
+                    return Err(message);
+                }
+                match sender.try_send(message) {
+                    Err(TrySendError::Full(message)) => {
+                        let mut queued: Vec<T> = queue.receiver.drain().collect();
+                        let len = queued.len();
+
+                        queued.retain(|queued| !same_key(queued, &message));
+                        if queued.len() == len && !queued.is_empty() {
+                            tracing::debug!("Channel is full, dropping the oldest message");
+                            queued.remove(0);
+                        }
+
+                        // Other senders might have filled the channel in the meantime,
+                        // so fall back to discarding the oldest messages.
+                        for queued in queued.into_iter().chain(Some(message)) {
+                            queue.send_dropping_oldest(sender, queued)?;
+                        }
+                        Ok(())
+                    }
+                    Err(TrySendError::Disconnected(message)) => Err(message),
+                    Ok(()) => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for SendPolicy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let overflow = match &self.overflow {
+            Overflow::Block => "Block",
+            Overflow::DropNewest => "DropNewest",
+            Overflow::DropOldest(_) => "DropOldest",
+            Overflow::CoalesceByKey(..) => "CoalesceByKey",
+        };
+
+        f.debug_struct("SendPolicy")
+            .field("overflow", &overflow)
+            .field("priority", &self.priority.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{bounded_channel, ChannelOptions, OverflowPolicy};
+
+    fn queued<T>(receiver: &crate::Receiver<T>) -> Vec<T> {
+        receiver.0.drain().collect()
+    }
+
+    #[test]
+    fn drop_newest() {
+        let (sender, receiver) = bounded_channel(2, OverflowPolicy::DropNewest);
+        for i in 0..4 {
+            sender.send(i).unwrap();
+        }
+        assert_eq!(queued(&receiver), vec![0, 1]);
+    }
+
+    #[test]
+    fn drop_oldest() {
+        let (sender, receiver) = bounded_channel(2, OverflowPolicy::DropOldest);
+        for i in 0..4 {
+            sender.send(i).unwrap();
+        }
+        assert_eq!(queued(&receiver), vec![2, 3]);
+    }
+
+    #[test]
+    fn coalesce_by_key() {
+        let policy = OverflowPolicy::coalesce_by_key(|msg: &(u8, u8)| Some(msg.0));
+        let (sender, receiver) = bounded_channel(3, policy);
+        for msg in [(1, 0), (2, 0), (1, 1), (1, 2), (3, 0), (4, 0)] {
+            sender.send(msg).unwrap();
+        }
+        assert_eq!(queued(&receiver), vec![(1, 2), (3, 0), (4, 0)]);
+    }
+
+    #[test]
+    fn priority() {
+        let options = ChannelOptions {
+            bound: None,
+            priority: Some(std::sync::Arc::new(|msg: &u8| *msg > 10)),
+        };
+        let (sender, receiver, priority_receiver) = options.channel();
+        for msg in [1, 20, 2, 30] {
+            sender.send(msg).unwrap();
+        }
+        assert_eq!(queued(&receiver), vec![1, 2]);
+        assert_eq!(queued(&priority_receiver.unwrap()), vec![20, 30]);
+    }
+
+    #[test]
+    #[should_panic(expected = "OverflowPolicy::Block")]
+    fn block_rejected_on_main_thread() {
+        let options = ChannelOptions::default().bounded(1, OverflowPolicy::<u8>::Block);
+        let _channel = options.main_thread_channel();
+    }
+
+    #[test]
+    fn block_waits_for_receiver() {
+        let (sender, receiver) = bounded_channel(1, OverflowPolicy::Block);
+        sender.send(1).unwrap();
+
+        let thread = std::thread::spawn(move || sender.send(2));
+        assert_eq!(receiver.recv_sync(), Some(1));
+        assert_eq!(thread.join().unwrap(), Ok(()));
+        assert_eq!(queued(&receiver), vec![2]);
+    }
+
+    #[test]
+    fn block_reports_disconnect() {
+        let (sender, receiver) = bounded_channel(1, OverflowPolicy::Block);
+        drop(receiver);
+        assert_eq!(sender.send(1), Err(1));
+    }
+
+    #[test]
+    fn dropping_policies_report_disconnect() {
+        let coalesce = OverflowPolicy::coalesce_by_key(|msg: &u8| Some(*msg));
+        for policy in [OverflowPolicy::DropOldest, coalesce] {
+            let (sender, receiver) = bounded_channel(1, policy);
+            sender.send(1).unwrap();
+            sender.send(2).unwrap();
+            drop(receiver);
+            assert_eq!(sender.send(3), Err(3));
+        }
+    }
+
+    #[test]
+    fn coalesce_concurrent_senders() {
+        const SENDERS: u8 = 4;
+        const MESSAGES: u16 = 2000;
+
+        let policy = OverflowPolicy::coalesce_by_key(|msg: &(u8, u16)| Some(msg.0));
+        let (sender, receiver) = bounded_channel(3, policy);
+
+        let threads: Vec<_> = (0..SENDERS)
+            .map(|key| {
+                let sender = sender.clone();
+                std::thread::spawn(move || {
+                    for value in 0..MESSAGES {
+                        sender.send((key, value)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        // Messages that were re-queued while coalescing weren't lost,
+        // so the channel is still full.
+        sender.send((SENDERS, 0)).unwrap();
+        let queued = queued(&receiver);
+        assert_eq!(queued.len(), 3);
+        assert_eq!(queued.last(), Some(&(SENDERS, 0)));
+    }
+}
@@ -0,0 +1,135 @@
+//! One-shot replies to messages sent with [`Sender::request`].
+
+use std::error::Error;
+use std::fmt;
+
+use futures::future::{self, Either};
+use tokio::sync::oneshot;
+
+use crate::{Sender, ShutdownReceiver};
+
+/// Replies to a request that was sent with [`Sender::request`].
+///
+/// Include it in an input message and call [`respond`](Responder::respond)
+/// from the update function or a command.
+/// If it's dropped without replying, the requester receives [`Canceled`].
+pub struct Responder<T> {
+    sender: oneshot::Sender<T>,
+}
+
+impl<T> Responder<T> {
+    /// Sends the reply to the requester.
+    ///
+    /// Returns the reply as error if the requester isn't waiting anymore.
+    pub fn respond(self, reply: T) -> Result<(), T> {
+        self.sender.send(reply)
+    }
+
+    /// Returns `true` if the requester isn't waiting for the reply anymore.
+    #[must_use]
+    pub fn is_canceled(&self) -> bool {
+        self.sender.is_closed()
+    }
+}
+
+impl<T> fmt::Debug for Responder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Responder")
+            .field("canceled", &self.is_canceled())
+            .finish()
+    }
+}
+
+/// The error of a request that won't be answered.
+///
+/// Either the [`Responder`] was dropped without replying or the
+/// receiving component was shut down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Canceled;
+
+impl fmt::Display for Canceled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("The request was canceled")
+    }
+}
+
+impl Error for Canceled {}
+
+impl<T> Sender<T> {
+    /// Sends a request and waits for the reply.
+    ///
+    /// `message` creates the message from the [`Responder`] that the
+    /// receiver uses to reply.
+    pub async fn request<R, F>(&self, message: F) -> Result<R, Canceled>
+    where
+        F: FnOnce(Responder<R>) -> T,
+    {
+        let (sender, receiver) = oneshot::channel();
+        self.send(message(Responder { sender }))
+            .map_err(|_| Canceled)?;
+        receiver.await.map_err(|_| Canceled)
+    }
+
+    /// Sends a request and waits for the reply
+    /// until `shutdown` receives a shutdown signal.
+    pub(crate) async fn request_until_shutdown<R, F>(
+        &self,
+        shutdown: ShutdownReceiver,
+        message: F,
+    ) -> Result<R, Canceled>
+    where
+        F: FnOnce(Responder<R>) -> T,
+    {
+        let reply = Box::pin(self.request(message));
+        let shutdown = Box::pin(shutdown.wait());
+
+        // Replies that are already available win over the shutdown.
+        match future::select(reply, shutdown).await {
+            Either::Left((reply, _)) => reply,
+            Either::Right(((), _)) => Err(Canceled),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Canceled, Responder};
+    use crate::shutdown;
+
+    #[derive(Debug)]
+    enum Input {
+        Double(u32, Responder<u32>),
+        Ignore(Responder<u32>),
+    }
+
+    #[test]
+    fn request() {
+        let (sender, receiver) = crate::channel();
+        std::thread::spawn(move || {
+            while let Some(message) = receiver.recv_sync() {
+                match message {
+                    Input::Double(value, responder) => responder.respond(value * 2).unwrap(),
+                    Input::Ignore(_) => {}
+                }
+            }
+        });
+
+        let reply = futures::executor::block_on(sender.request(|r| Input::Double(21, r)));
+        assert_eq!(reply, Ok(42));
+
+        let reply = futures::executor::block_on(sender.request(Input::Ignore));
+        assert_eq!(reply, Err(Canceled));
+    }
+
+    #[test]
+    fn cancel_on_shutdown() {
+        let (sender, _receiver) = crate::channel();
+        let (shutdown_sender, shutdown_receiver) = shutdown::channel();
+        shutdown_sender.shutdown();
+
+        let reply = futures::executor::block_on(
+            sender.request_until_shutdown(shutdown_receiver, Input::Ignore),
+        );
+        assert_eq!(reply, Err(Canceled));
+    }
+}
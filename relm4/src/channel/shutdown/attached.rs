@@ -1,7 +1,7 @@
 // Copyright 2022 System76 <info@system76.com>
 // SPDX-License-Identifier: MIT or Apache-2.0
 
-use super::ShutdownReceiver;
+use super::{PendingGuard, ShutdownReceiver};
 use futures::future::Either;
 use std::future::Future;
 
@@ -17,10 +17,15 @@ where
     F: Future<Output = Out>,
 {
     /// Creates a future which will resolve to this on shutdown.
+    ///
+    /// When the application quits, [`RelmApp`](crate::RelmApp) waits for
+    /// the `shutdown` future to complete during its
+    /// [grace period](crate::RelmApp::shutdown_grace_period).
     pub async fn on_shutdown<S>(self, shutdown: S) -> Out
     where
         S: Future<Output = Out>,
     {
+        let _pending = PendingGuard::new();
         match self.wait().await {
             Either::Left(_) => shutdown.await,
             Either::Right(out) => out,
@@ -5,6 +5,7 @@ mod attached;
 mod receiver;
 mod sender;
 
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::sync::broadcast;
 
 pub use attached::AttachedShutdown;
@@ -20,3 +21,28 @@ pub fn channel() -> (ShutdownSender, ShutdownReceiver) {
     let (sender, receiver) = broadcast::channel(1);
     (ShutdownSender { sender }, ShutdownReceiver { receiver })
 }
+
+/// Number of [`AttachedShutdown::on_shutdown`] futures that haven't completed yet.
+static PENDING: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the number of [`AttachedShutdown::on_shutdown`] futures that haven't completed yet.
+pub(crate) fn pending() -> usize {
+    PENDING.load(Ordering::Acquire)
+}
+
+/// Counts a pending shutdown handler until dropped.
+#[derive(Debug)]
+struct PendingGuard;
+
+impl PendingGuard {
+    fn new() -> Self {
+        PENDING.fetch_add(1, Ordering::AcqRel);
+        Self
+    }
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        PENDING.fetch_sub(1, Ordering::AcqRel);
+    }
+}
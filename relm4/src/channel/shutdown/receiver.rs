@@ -27,6 +27,18 @@ impl ShutdownReceiver {
         let _ = self.receiver.recv().await;
     }
 
+    /// Combines multiple receivers into one that receives
+    /// the shutdown signal once all of them received it.
+    pub(crate) fn combine<I: IntoIterator<Item = Self>>(iter: I) -> Self {
+        let (sender, receiver) = super::channel();
+        let waits: Vec<_> = iter.into_iter().map(Self::wait).collect();
+        crate::spawn(async move {
+            futures::future::join_all(waits).await;
+            sender.shutdown();
+        });
+        receiver
+    }
+
     #[cfg(test)]
     pub(crate) fn try_recv(&mut self) -> Result<(), TryRecvError> {
         self.receiver.try_recv()
@@ -40,3 +52,21 @@ impl Clone for ShutdownReceiver {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::ShutdownReceiver;
+
+    #[test]
+    fn combine_waits_for_all() {
+        let (first, first_receiver) = super::super::channel();
+        let (second, second_receiver) = super::super::channel();
+        let mut combined = ShutdownReceiver::combine([first_receiver, second_receiver]);
+
+        first.shutdown();
+        assert!(combined.try_recv().is_err());
+
+        second.shutdown();
+        crate::RUNTIME.block_on(combined.wait());
+    }
+}
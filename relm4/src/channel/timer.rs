@@ -0,0 +1,249 @@
+//! Timers that send input messages to components.
+
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use futures::future::{AbortHandle, Abortable};
+use gtk::glib;
+use gtk::prelude::{ObjectExt, WidgetExt};
+use tokio::sync::oneshot;
+
+use crate::{Sender, ShutdownReceiver};
+
+/// Handle to a timer that was started with `interval()` or `timeout()`
+/// on a component sender.
+///
+/// Dropping the handle doesn't cancel the timer.
+pub struct TimerHandle {
+    abort: AbortHandle,
+    state: Rc<TimerState>,
+}
+
+struct TimerState {
+    period: Duration,
+    deadline: Cell<Instant>,
+    pause_widget: RefCell<Option<glib::WeakRef<gtk::Widget>>>,
+}
+
+impl TimerHandle {
+    /// Stops the timer.
+    pub fn cancel(&self) {
+        self.abort.abort();
+    }
+
+    /// Returns `true` if the timer was canceled.
+    #[must_use]
+    pub fn is_canceled(&self) -> bool {
+        self.abort.is_aborted()
+    }
+
+    /// Starts counting down the period of the timer again.
+    ///
+    /// This is useful for timeouts that should only fire after a period of inactivity.
+    /// Timeouts that already fired aren't started again.
+    pub fn reset(&self) {
+        self.state.deadline.set(Instant::now() + self.state.period);
+    }
+
+    /// Pauses the timer while `widget` isn't mapped, usually the root widget of the component.
+    ///
+    /// Intervals skip their ticks while paused and timeouts that expired
+    /// while paused fire once the widget is mapped again.
+    #[must_use]
+    pub fn pause_when_unmapped(self, widget: &impl AsRef<gtk::Widget>) -> Self {
+        *self.state.pause_widget.borrow_mut() = Some(widget.as_ref().downgrade());
+        self
+    }
+}
+
+impl fmt::Debug for TimerHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TimerHandle")
+            .field("period", &self.state.period)
+            .field("canceled", &self.is_canceled())
+            .finish()
+    }
+}
+
+/// Starts a timer on the main context of the current thread
+/// that sends the messages of `message` to `input`.
+///
+/// If `repeat` is `false`, the timer stops after the first message.
+pub(super) fn start<T, F>(
+    input: Sender<T>,
+    shutdown: ShutdownReceiver,
+    period: Duration,
+    repeat: bool,
+    mut message: F,
+) -> TimerHandle
+where
+    T: 'static,
+    F: FnMut() -> T + 'static,
+{
+    let (abort, registration) = AbortHandle::new_pair();
+    let state = Rc::new(TimerState {
+        period,
+        deadline: Cell::new(Instant::now() + period),
+        pause_widget: RefCell::default(),
+    });
+
+    let timer_state = Rc::clone(&state);
+    let timer = async move {
+        loop {
+            // The deadline moves if the timer is reset while waiting.
+            let now = Instant::now();
+            let deadline = timer_state.deadline.get();
+            if deadline > now {
+                glib::timeout_future(deadline - now).await;
+                continue;
+            }
+
+            let widget = timer_state
+                .pause_widget
+                .borrow()
+                .as_ref()
+                .and_then(glib::WeakRef::upgrade);
+            if let Some(widget) = widget {
+                if !widget.is_mapped() {
+                    mapped(&widget).await;
+                    if repeat {
+                        timer_state.deadline.set(Instant::now() + period);
+                        continue;
+                    }
+                }
+            }
+
+            if input.send(message()).is_err() || !repeat {
+                break;
+            }
+            timer_state.deadline.set(Instant::now() + period);
+        }
+    };
+
+    crate::spawn_local(
+        shutdown
+            .register(Abortable::new(timer, registration))
+            .drop_on_shutdown(),
+    );
+
+    TimerHandle { abort, state }
+}
+
+/// Waits until `widget` is mapped.
+async fn mapped(widget: &gtk::Widget) {
+    let (sender, receiver) = oneshot::channel();
+    let sender = RefCell::new(Some(sender));
+    let _handler = MapHandler {
+        widget,
+        id: Some(widget.connect_map(move |_| {
+            if let Some(sender) = sender.take() {
+                sender.send(()).ok();
+            }
+        })),
+    };
+
+    receiver.await.ok();
+}
+
+/// Disconnects the map handler, also if the timer is canceled while waiting.
+struct MapHandler<'a> {
+    widget: &'a gtk::Widget,
+    id: Option<glib::SignalHandlerId>,
+}
+
+impl Drop for MapHandler<'_> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            self.widget.disconnect(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use gtk::glib;
+    use gtk::prelude::GtkWindowExt;
+
+    use super::start;
+    use crate::{shutdown, Receiver};
+
+    /// Runs the main context until `condition` returns `true` or `timeout` expired.
+    fn run_until(timeout: Duration, mut condition: impl FnMut() -> bool) -> bool {
+        let context = glib::MainContext::default();
+        let start = Instant::now();
+        loop {
+            while context.iteration(false) {}
+            if condition() {
+                return true;
+            } else if start.elapsed() >= timeout {
+                return false;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Runs the main context for `duration` and returns the received messages.
+    fn received_within(receiver: &Receiver<u8>, duration: Duration) -> Vec<u8> {
+        run_until(duration, || false);
+        receiver.0.try_iter().collect()
+    }
+
+    #[gtk::test]
+    fn reset_delays_timeout() {
+        let (sender, receiver) = crate::channel();
+        let (_shutdown, shutdown) = shutdown::channel();
+        let handle = start(sender, shutdown, Duration::from_millis(100), false, || 1);
+
+        assert!(received_within(&receiver, Duration::from_millis(60)).is_empty());
+        handle.reset();
+        assert!(received_within(&receiver, Duration::from_millis(60)).is_empty());
+
+        let mut received = Vec::new();
+        assert!(run_until(Duration::from_secs(5), || {
+            received.extend(receiver.0.try_iter());
+            !received.is_empty()
+        }));
+        assert_eq!(received, vec![1]);
+        assert!(received_within(&receiver, Duration::from_millis(150)).is_empty());
+    }
+
+    #[gtk::test]
+    fn cancel_stops_interval() {
+        let (sender, receiver) = crate::channel();
+        let (_shutdown, shutdown) = shutdown::channel();
+        let handle = start(sender, shutdown, Duration::from_millis(10), true, || 1);
+
+        assert!(run_until(Duration::from_secs(5), || !receiver.0.is_empty()));
+        assert!(!handle.is_canceled());
+
+        handle.cancel();
+        assert!(handle.is_canceled());
+        // Messages that were sent before canceling are still queued.
+        received_within(&receiver, Duration::ZERO);
+        assert!(received_within(&receiver, Duration::from_millis(50)).is_empty());
+    }
+
+    #[gtk::test]
+    fn pause_until_mapped() {
+        let (sender, receiver) = crate::channel();
+        let (_shutdown, shutdown) = shutdown::channel();
+        let widget = gtk::Box::default();
+        let _handle = start(sender, shutdown, Duration::from_millis(10), false, || 1)
+            .pause_when_unmapped(&widget);
+
+        // The timeout expires while the widget isn't mapped.
+        assert!(received_within(&receiver, Duration::from_millis(50)).is_empty());
+
+        let window = gtk::Window::new();
+        window.set_child(Some(&widget));
+        window.present();
+
+        assert!(run_until(Duration::from_secs(5), || !receiver.0.is_empty()));
+        assert_eq!(receiver.0.try_iter().collect::<Vec<_>>(), vec![1]);
+        window.destroy();
+    }
+}
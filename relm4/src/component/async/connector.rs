@@ -3,7 +3,7 @@
 // SPDX-License-Identifier: MIT or Apache-2.0
 
 use super::{AsyncComponent, AsyncComponentController, AsyncController};
-use crate::{Receiver, Sender, ShutdownOnDrop};
+use crate::{Receiver, Sender, ShutdownOnDrop, ShutdownReceiver};
 use std::fmt::{self, Debug};
 
 /// Contains the post-launch input sender and output receivers with the root widget.
@@ -21,6 +21,9 @@ pub struct AsyncConnector<C: AsyncComponent> {
 
     /// Type used to destroy the async component when it's dropped.
     pub(super) shutdown_on_drop: ShutdownOnDrop,
+
+    /// Receives a signal when the runtime of the component shuts down.
+    pub(super) shutdown: ShutdownReceiver,
 }
 
 impl<C: AsyncComponent> AsyncConnector<C> {
@@ -35,6 +38,7 @@ impl<C: AsyncComponent> AsyncConnector<C> {
             sender,
             receiver,
             shutdown_on_drop,
+            shutdown,
         } = self;
 
         crate::spawn_local(receiver.forward(sender_.clone(), transform));
@@ -43,6 +47,7 @@ impl<C: AsyncComponent> AsyncConnector<C> {
             widget,
             sender,
             shutdown_on_drop,
+            shutdown,
         }
     }
 
@@ -56,6 +61,7 @@ impl<C: AsyncComponent> AsyncConnector<C> {
             sender,
             receiver,
             shutdown_on_drop,
+            shutdown,
         } = self;
 
         let mut sender_ = sender.clone();
@@ -69,6 +75,7 @@ impl<C: AsyncComponent> AsyncConnector<C> {
             widget,
             sender,
             shutdown_on_drop,
+            shutdown,
         }
     }
 
@@ -78,6 +85,7 @@ impl<C: AsyncComponent> AsyncConnector<C> {
             widget,
             sender,
             shutdown_on_drop,
+            shutdown,
             ..
         } = self;
 
@@ -85,6 +93,7 @@ impl<C: AsyncComponent> AsyncConnector<C> {
             widget,
             sender,
             shutdown_on_drop,
+            shutdown,
         }
     }
 }
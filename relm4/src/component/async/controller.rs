@@ -4,7 +4,7 @@
 
 use std::fmt::{self, Debug};
 
-use crate::{Sender, ShutdownOnDrop};
+use crate::{Canceled, Responder, Sender, ShutdownOnDrop, ShutdownReceiver};
 
 use super::AsyncComponent;
 
@@ -38,6 +38,25 @@ pub struct AsyncController<C: AsyncComponent> {
 
     /// Type used to destroy the async component when it's dropped.
     pub(super) shutdown_on_drop: ShutdownOnDrop,
+
+    /// Receives a signal when the runtime of the component shuts down.
+    pub(super) shutdown: ShutdownReceiver,
+}
+
+impl<C: AsyncComponent> AsyncController<C> {
+    /// Sends a request to the component and waits for the reply.
+    ///
+    /// `message` creates the input message from the [`Responder`] that the
+    /// component uses to reply.
+    /// The request is canceled if the component shuts down before it replies.
+    pub async fn request<R, F>(&self, message: F) -> Result<R, Canceled>
+    where
+        F: FnOnce(Responder<R>) -> C::Input,
+    {
+        self.sender
+            .request_until_shutdown(self.shutdown.clone(), message)
+            .await
+    }
 }
 
 impl<C: AsyncComponent> AsyncComponentController<C> for AsyncController<C> {
@@ -132,6 +132,22 @@ pub trait AsyncComponent: Sized + 'static {
         self.update_view(widgets, sender);
     }
 
+    /// Decides whether an input message can be replaced by a newer one.
+    ///
+    /// Before an input is passed to [`update_with_view`](Self::update_with_view),
+    /// the runtime looks at the messages that are already queued.
+    /// As long as this method returns `true` for the current and the next message,
+    /// the current message is discarded in favor of the next one.
+    /// This collapses bursts of messages into a single update.
+    ///
+    /// The runtime only calls this method for components that are launched with
+    /// [`AsyncComponentBuilder::coalesce_inputs`](crate::component::AsyncComponentBuilder::coalesce_inputs).
+    /// The default implementation never merges messages.
+    #[allow(unused)]
+    fn coalesce(older: &Self::Input, newer: &Self::Input) -> bool {
+        false
+    }
+
     /// Last method called before a component is shut down.
     ///
     /// This method is guaranteed to be called even when the entire application is shut down.
@@ -199,6 +215,14 @@ pub trait SimpleAsyncComponent: Sized + 'static {
     #[allow(unused)]
     fn update_view(&self, widgets: &mut Self::Widgets, sender: AsyncComponentSender<Self>) {}
 
+    /// Decides whether an input message can be replaced by a newer one.
+    ///
+    /// See [`AsyncComponent::coalesce`] for details.
+    #[allow(unused)]
+    fn coalesce(older: &Self::Input, newer: &Self::Input) -> bool {
+        false
+    }
+
     /// Last method called before a component is shut down.
     ///
     /// This method is guaranteed to be called even when the entire application is shut down.
@@ -248,6 +272,10 @@ where
         C::update_view(self, widgets, sender);
     }
 
+    fn coalesce(older: &Self::Input, newer: &Self::Input) -> bool {
+        C::coalesce(older, newer)
+    }
+
     fn shutdown(&mut self, widgets: &mut Self::Widgets, output: Sender<Self::Output>) {
         self.shutdown(widgets, output);
     }
@@ -0,0 +1,640 @@
+use std::any;
+use std::fmt::{Debug, Display};
+use std::time::{Duration, Instant};
+
+use futures::FutureExt;
+use tracing::{info_span, Instrument};
+
+use crate::bus::Subscriptions;
+use crate::channel::{AsyncWorkerSender, ChannelOptions, OverflowPolicy};
+use crate::component::panic::{PanicHandler, PanicOptions, Recovery};
+use crate::component::Crash;
+use crate::inspector::{self, InspectOptions};
+use crate::{
+    Canceled, GuardedReceiver, Receiver, Responder, RuntimeSenders, Sender, ShutdownOnDrop,
+    ShutdownReceiver,
+};
+
+/// Receives inputs and outputs in the background like a [`Worker`](crate::Worker),
+/// but handles them asynchronously on the runtime of Relm4.
+///
+/// Launch it with [`AsyncWorkerBuilder::detach_async_worker`] to run it as a task
+/// instead of a dedicated thread, which is ideal for network-bound work.
+/// Updates are awaited one after another and a running update is
+/// canceled when the worker is shut down.
+#[async_trait::async_trait]
+pub trait AsyncWorker: Sized + Send + 'static {
+    /// The initial parameters that will be used to build the worker state.
+    type Init: 'static + Send;
+    /// The type of inputs that this worker shall receive.
+    type Input: 'static + Send + Debug;
+    /// The type of outputs that this worker shall send.
+    type Output: 'static + Send + Debug;
+    /// The type of the outputs of commands that this worker spawns.
+    type CommandOutput: 'static + Send + Debug;
+
+    /// Create a builder for this worker.
+    #[must_use]
+    fn builder() -> AsyncWorkerBuilder<Self> {
+        AsyncWorkerBuilder::default()
+    }
+
+    /// Defines the initial state of the worker.
+    async fn init(init: Self::Init, sender: AsyncWorkerSender<Self>) -> Self;
+
+    /// Defines how inputs will be processed.
+    async fn update(&mut self, message: Self::Input, sender: AsyncWorkerSender<Self>);
+
+    /// Defines how the worker should respond to command updates.
+    #[allow(unused)]
+    async fn update_cmd(&mut self, message: Self::CommandOutput, sender: AsyncWorkerSender<Self>) {}
+
+    /// Last method called before the worker is shut down.
+    #[allow(unused)]
+    fn shutdown(&mut self, output: Sender<Self::Output>) {}
+
+    /// An identifier for the worker used for debug logging.
+    ///
+    /// The default implementation of this method uses the address of the worker, but
+    /// implementations are free to provide more meaningful identifiers.
+    fn id(&self) -> String {
+        format!("{:p}", &self)
+    }
+}
+
+/// An [`AsyncWorker`] that is ready to be launched.
+#[derive(Debug)]
+pub struct AsyncWorkerBuilder<W: AsyncWorker> {
+    channel: ChannelOptions<W::Input>,
+    inspect: InspectOptions<W, W::Input>,
+    panic: PanicOptions<W::Init>,
+    bus: Subscriptions<W::Input>,
+}
+
+impl<W: AsyncWorker> Default for AsyncWorkerBuilder<W> {
+    fn default() -> Self {
+        Self {
+            channel: ChannelOptions::default(),
+            inspect: InspectOptions::default(),
+            panic: PanicOptions::default(),
+            bus: Subscriptions::default(),
+        }
+    }
+}
+
+impl<W: AsyncWorker> AsyncWorkerBuilder<W> {
+    /// Limit the number of input messages that can be queued.
+    ///
+    /// See [`ComponentBuilder::bounded`](crate::ComponentBuilder::bounded) for details.
+    #[must_use]
+    pub fn bounded(mut self, capacity: usize, policy: OverflowPolicy<W::Input>) -> Self {
+        self.channel = self.channel.bounded(capacity, policy);
+        self
+    }
+
+    /// Handle input messages for which `is_priority` returns `true`
+    /// before all other queued messages.
+    ///
+    /// See [`ComponentBuilder::prioritize`](crate::ComponentBuilder::prioritize) for details.
+    #[must_use]
+    pub fn prioritize<F>(mut self, is_priority: F) -> Self
+    where
+        F: Fn(&W::Input) -> bool + Send + Sync + 'static,
+    {
+        self.channel = self.channel.prioritize(is_priority);
+        self
+    }
+
+    /// Subscribe the worker to messages of type `T` on the [`bus`](crate::bus).
+    ///
+    /// Published messages are turned into input messages by `map`.
+    /// The subscription ends when the worker shuts down.
+    #[must_use]
+    pub fn subscribe<T, F>(mut self, map: F) -> Self
+    where
+        T: 'static,
+        F: Fn(T) -> W::Input + Send + Sync + 'static,
+    {
+        self.bus.add(map);
+        self
+    }
+
+    /// Show the [`Debug`] output of the worker in the [`inspector`](crate::inspector).
+    #[must_use]
+    pub fn inspect_model(mut self) -> Self
+    where
+        W: Debug,
+    {
+        self.inspect.debug_model();
+        self
+    }
+
+    /// Allow the [`inspector`](crate::inspector) to send input messages
+    /// that are parsed from text by `parse`.
+    #[must_use]
+    pub fn inspect_input<F, E>(mut self, parse: F) -> Self
+    where
+        F: Fn(&str) -> Result<W::Input, E> + 'static,
+        E: Display,
+    {
+        self.inspect.parse_input(parse);
+        self
+    }
+
+    /// Catch panics while the worker handles a message,
+    /// log them and drop the message that caused the panic.
+    ///
+    /// See [`ComponentBuilder::catch_panics`](crate::ComponentBuilder::catch_panics) for details.
+    #[must_use]
+    pub fn catch_panics(mut self) -> Self {
+        self.panic.log_and_drop();
+        self
+    }
+
+    /// Catch panics while the worker handles a message and
+    /// initialize the worker again with a copy of its payload.
+    ///
+    /// The task waits for the backoff before the worker is initialized again.
+    /// See [`ComponentBuilder::restart_on_panic`](crate::ComponentBuilder::restart_on_panic)
+    /// for details.
+    #[must_use]
+    pub fn restart_on_panic(mut self, max_restarts: usize, backoff: Duration) -> Self
+    where
+        W::Init: Clone,
+    {
+        self.panic.restart(max_restarts, backoff);
+        self
+    }
+
+    /// Forward a [`Crash`] to `sender` whenever a panic was caught,
+    /// for example to notify the parent component.
+    #[must_use]
+    pub fn forward_crashes<X: 'static, F: (Fn(Crash) -> X) + 'static>(
+        mut self,
+        sender: &Sender<X>,
+        transform: F,
+    ) -> Self {
+        let sender = sender.clone();
+        self.panic.notify(move |crash| {
+            if sender.send(transform(crash)).is_err() {
+                tracing::error!("Couldn't forward crash of worker");
+            }
+        });
+        self
+    }
+
+    /// Starts the worker as a task on the runtime of Relm4.
+    ///
+    /// Unlike [`detach_worker`](crate::ComponentBuilder::detach_worker), this doesn't
+    /// block a thread for each worker.
+    pub fn detach_async_worker(self, payload: W::Init) -> AsyncWorkerHandle<W> {
+        let Self {
+            channel,
+            inspect,
+            panic,
+            bus,
+        } = self;
+
+        let (input_sender, input_receiver, priority_receiver) = channel.channel();
+
+        let RuntimeSenders {
+            output_sender,
+            output_receiver,
+            cmd_sender,
+            cmd_receiver,
+            shutdown_notifier,
+            shutdown_recipient,
+            shutdown_on_drop,
+            mut shutdown_event,
+            registration,
+        } = RuntimeSenders::<W::Output, W::CommandOutput>::new(any::type_name::<W>());
+
+        let debug_model = inspect.install(&registration, input_sender.clone());
+        let mut panics = panic.handler(any::type_name::<W>(), &payload);
+        let subscriptions = bus.subscribe(&input_sender);
+
+        // Encapsulates the senders used by worker methods.
+        let worker_sender = AsyncWorkerSender::new(
+            input_sender.clone(),
+            output_sender,
+            cmd_sender,
+            shutdown_recipient.clone(),
+        );
+
+        let component_id = registration.id();
+        crate::spawn(inspector::scope(component_id, async move {
+            // Unsubscribes from the bus when the runtime stops.
+            let _subscriptions = subscriptions;
+
+            let mut model = W::init(payload, worker_sender.clone()).await;
+            registration.set_name(model.id());
+            registration.snapshot(&model, debug_model);
+
+            let mut cmd = GuardedReceiver::new(cmd_receiver);
+            let mut input = GuardedReceiver::with_priority(input_receiver, priority_receiver);
+
+            loop {
+                futures::select!(
+                    message = input => {
+                        registration.counter().input(worker_sender.input_sender().0.len());
+                        let started = Instant::now();
+                        let span = info_span!(
+                            "update",
+                            input=?message,
+                            component=any::type_name::<W>(),
+                            id=model.id(),
+                        );
+                        let update = panics.guard_future(
+                            model.update(message, worker_sender.clone()).instrument(span),
+                        );
+
+                        // Cancels the update if the worker is shut down meanwhile.
+                        let result = futures::select!(
+                            result = update.fuse() => Some(result),
+                            _ = shutdown_event => None,
+                        );
+
+                        let keep_running = match result {
+                            Some(Ok(())) => {
+                                registration.updated(started, &model, debug_model);
+                                true
+                            }
+                            Some(Err(payload)) => {
+                                recover(payload, &mut panics, &mut model, &worker_sender).await
+                            }
+                            None => {
+                                model.shutdown(worker_sender.output_sender().clone());
+                                false
+                            }
+                        };
+                        if !keep_running {
+                            shutdown_notifier.shutdown();
+                            return;
+                        }
+                    }
+
+                    // Handles responses from a command.
+                    message = cmd => {
+                        registration.counter().command();
+                        let started = Instant::now();
+                        let span = info_span!(
+                            "update_cmd",
+                            cmd_output=?message,
+                            component=any::type_name::<W>(),
+                            id=model.id(),
+                        );
+                        let update = panics.guard_future(
+                            model.update_cmd(message, worker_sender.clone()).instrument(span),
+                        );
+
+                        // Cancels the update if the worker is shut down meanwhile.
+                        let result = futures::select!(
+                            result = update.fuse() => Some(result),
+                            _ = shutdown_event => None,
+                        );
+
+                        let keep_running = match result {
+                            Some(Ok(())) => {
+                                registration.updated(started, &model, debug_model);
+                                true
+                            }
+                            Some(Err(payload)) => {
+                                recover(payload, &mut panics, &mut model, &worker_sender).await
+                            }
+                            None => {
+                                model.shutdown(worker_sender.output_sender().clone());
+                                false
+                            }
+                        };
+                        if !keep_running {
+                            shutdown_notifier.shutdown();
+                            return;
+                        }
+                    }
+
+                    // Triggered when the worker is destroyed
+                    _ = shutdown_event => {
+                        model.shutdown(worker_sender.output_sender().clone());
+                        shutdown_notifier.shutdown();
+                        return;
+                    }
+                );
+            }
+        }));
+
+        // Give back a type for controlling the worker.
+        AsyncWorkerHandle {
+            sender: input_sender,
+            receiver: output_receiver,
+            shutdown_on_drop,
+            shutdown: shutdown_recipient,
+        }
+    }
+}
+
+/// Recovers from a panic that was caught while the worker handled a message.
+///
+/// Before the worker is restarted, the previous model is shut down and its keyed
+/// commands are canceled. Panics while the worker is initialized again are
+/// handled like panics in updates.
+///
+/// Returns `false` if the worker should shut down,
+/// after its [`shutdown`](AsyncWorker::shutdown) method was called.
+async fn recover<W: AsyncWorker>(
+    mut payload: Box<dyn any::Any + Send>,
+    panics: &mut PanicHandler<W::Init>,
+    model: &mut W,
+    sender: &AsyncWorkerSender<W>,
+) -> bool {
+    let mut shut_down = false;
+    loop {
+        let restart = match panics.recover(payload) {
+            Recovery::Continue => return true,
+            Recovery::Restart(init, delay) => Some((init, delay)),
+            Recovery::Stop => None,
+        };
+
+        if !shut_down {
+            let output = sender.output_sender().clone();
+            panics.guard(|| model.shutdown(output)).ok();
+            shut_down = true;
+        }
+
+        let Some((init, delay)) = restart else {
+            sender.stop();
+            return false;
+        };
+
+        sender.cancel_commands();
+        tokio::time::sleep(delay).await;
+
+        // The task is scoped to the worker, so it's already the current component.
+        match panics.guard_future(W::init(init, sender.clone())).await {
+            Ok(restarted) => {
+                *model = restarted;
+                return true;
+            }
+            Err(panic) => payload = panic,
+        }
+    }
+}
+
+/// Handle to an async worker task in the background.
+///
+/// It offers the same API as [`WorkerHandle`](crate::WorkerHandle).
+/// It's a separate type because [`WorkerHandle`](crate::WorkerHandle) is bound to
+/// [`Component`](crate::Component), which an [`AsyncWorker`] doesn't implement
+/// because its methods are async.
+#[derive(Debug)]
+pub struct AsyncWorkerHandle<W: AsyncWorker> {
+    // Sends inputs to the worker.
+    sender: Sender<W::Input>,
+    // Where the worker will send its outputs to.
+    receiver: Receiver<W::Output>,
+    // Shutdown the worker when this is dropped
+    shutdown_on_drop: ShutdownOnDrop,
+    // Receives a signal when the worker shuts down
+    shutdown: ShutdownReceiver,
+}
+
+impl<W: AsyncWorker> AsyncWorkerHandle<W> {
+    /// Given a mutable closure, captures the receiver for handling.
+    pub fn connect_receiver<F: FnMut(&mut Sender<W::Input>, W::Output) + 'static>(
+        self,
+        mut func: F,
+    ) -> AsyncWorkerController<W> {
+        let Self {
+            sender,
+            receiver,
+            shutdown_on_drop,
+            shutdown,
+        } = self;
+
+        let mut sender_ = sender.clone();
+        crate::spawn_local(async move {
+            while let Some(event) = receiver.recv().await {
+                func(&mut sender_, event);
+            }
+        });
+
+        AsyncWorkerController {
+            sender,
+            shutdown_on_drop,
+            shutdown,
+        }
+    }
+
+    /// Forwards output events to the designated sender.
+    pub fn forward<X: 'static, F: (Fn(W::Output) -> X) + 'static>(
+        self,
+        sender: &Sender<X>,
+        transform: F,
+    ) -> AsyncWorkerController<W> {
+        let Self {
+            sender: own_sender,
+            receiver,
+            shutdown_on_drop,
+            shutdown,
+        } = self;
+
+        crate::spawn_local(receiver.forward(sender.clone(), transform));
+        AsyncWorkerController {
+            sender: own_sender,
+            shutdown_on_drop,
+            shutdown,
+        }
+    }
+
+    /// Ignore outputs from the worker and take the handle.
+    #[must_use]
+    pub fn detach(self) -> AsyncWorkerController<W> {
+        let Self {
+            sender,
+            shutdown_on_drop,
+            shutdown,
+            ..
+        } = self;
+
+        AsyncWorkerController {
+            sender,
+            shutdown_on_drop,
+            shutdown,
+        }
+    }
+}
+
+/// Sends inputs to an async worker. On drop, shuts down the worker.
+///
+/// The counterpart of [`WorkerController`](crate::WorkerController) for an [`AsyncWorker`].
+#[derive(Debug)]
+pub struct AsyncWorkerController<W: AsyncWorker> {
+    // Sends inputs to the worker.
+    sender: Sender<W::Input>,
+    // Shutdown the worker when this is dropped
+    shutdown_on_drop: ShutdownOnDrop,
+    // Receives a signal when the worker shuts down
+    shutdown: ShutdownReceiver,
+}
+
+impl<W: AsyncWorker> AsyncWorkerController<W> {
+    /// Emits an input to the worker.
+    pub fn emit(&self, event: W::Input) {
+        self.sender.send(event).unwrap();
+    }
+
+    /// Provides access to the worker's sender.
+    #[must_use]
+    pub const fn sender(&self) -> &Sender<W::Input> {
+        &self.sender
+    }
+
+    /// Sends a request to the worker and waits for the reply.
+    ///
+    /// `message` creates the input message from the [`Responder`] that the
+    /// worker uses to reply.
+    /// The request is canceled if the worker shuts down before it replies.
+    pub async fn request<R, F>(&self, message: F) -> Result<R, Canceled>
+    where
+        F: FnOnce(Responder<R>) -> W::Input,
+    {
+        self.sender
+            .request_until_shutdown(self.shutdown.clone(), message)
+            .await
+    }
+
+    /// Dropping this type will usually stop the runtime of the worker.
+    /// With this method you can give the runtime a static lifetime.
+    /// In other words, dropping the [`AsyncWorkerController`] will not stop
+    /// the runtime anymore, it will run until the app is closed.
+    pub fn detach_runtime(&mut self) {
+        self.shutdown_on_drop.deactivate();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::AsyncWorker;
+    use crate::channel::AsyncWorkerSender;
+    use crate::Sender;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum Event {
+        Init,
+        Added(u32),
+        Started,
+        Canceled,
+        Shutdown,
+    }
+
+    #[derive(Debug)]
+    enum Input {
+        Add(u32),
+        Hang,
+        Panic,
+    }
+
+    /// Logs that a hanging update was canceled when it's dropped.
+    struct CancelGuard(flume::Sender<Event>);
+
+    impl Drop for CancelGuard {
+        fn drop(&mut self) {
+            self.0.send(Event::Canceled).ok();
+        }
+    }
+
+    struct Counter {
+        total: u32,
+        log: flume::Sender<Event>,
+    }
+
+    #[async_trait::async_trait]
+    impl AsyncWorker for Counter {
+        type Init = flume::Sender<Event>;
+        type Input = Input;
+        type Output = u32;
+        type CommandOutput = ();
+
+        async fn init(log: Self::Init, _sender: AsyncWorkerSender<Self>) -> Self {
+            log.send(Event::Init).unwrap();
+            Self { total: 0, log }
+        }
+
+        async fn update(&mut self, message: Self::Input, sender: AsyncWorkerSender<Self>) {
+            match message {
+                Input::Add(value) => {
+                    self.total += value;
+                    self.log.send(Event::Added(self.total)).unwrap();
+                    sender.output(self.total).ok();
+                }
+                Input::Hang => {
+                    let _guard = CancelGuard(self.log.clone());
+                    self.log.send(Event::Started).unwrap();
+                    futures::future::pending::<()>().await;
+                }
+                Input::Panic => panic!("Test panic"),
+            }
+        }
+
+        fn shutdown(&mut self, _output: Sender<Self::Output>) {
+            self.log.send(Event::Shutdown).unwrap();
+        }
+    }
+
+    fn next(events: &flume::Receiver<Event>) -> Event {
+        events.recv_timeout(TIMEOUT).unwrap()
+    }
+
+    #[test]
+    fn outputs_and_detach() {
+        let (log, events) = flume::unbounded();
+        let handle = Counter::builder().detach_async_worker(log);
+
+        handle.sender.send(Input::Add(2)).unwrap();
+        assert_eq!(handle.receiver.0.recv_timeout(TIMEOUT), Ok(2));
+
+        let controller = handle.detach();
+        controller.emit(Input::Add(3));
+        assert_eq!(next(&events), Event::Init);
+        assert_eq!(next(&events), Event::Added(2));
+        assert_eq!(next(&events), Event::Added(5));
+    }
+
+    #[test]
+    fn shutdown_cancels_update() {
+        let (log, events) = flume::unbounded();
+        let controller = Counter::builder().detach_async_worker(log).detach();
+
+        controller.emit(Input::Hang);
+        assert_eq!(next(&events), Event::Init);
+        assert_eq!(next(&events), Event::Started);
+
+        drop(controller);
+        assert_eq!(next(&events), Event::Canceled);
+        assert_eq!(next(&events), Event::Shutdown);
+    }
+
+    #[test]
+    fn restart_after_panic() {
+        let (log, events) = flume::unbounded();
+        let handle = Counter::builder()
+            .restart_on_panic(1, Duration::ZERO)
+            .detach_async_worker(log);
+
+        handle.sender.send(Input::Add(2)).unwrap();
+        handle.sender.send(Input::Panic).unwrap();
+        handle.sender.send(Input::Add(3)).unwrap();
+
+        // The restarted worker starts counting from zero again.
+        assert_eq!(next(&events), Event::Init);
+        assert_eq!(next(&events), Event::Added(2));
+        assert_eq!(next(&events), Event::Shutdown);
+        assert_eq!(next(&events), Event::Init);
+        assert_eq!(next(&events), Event::Added(3));
+        assert_eq!(handle.receiver.0.recv_timeout(TIMEOUT), Ok(2));
+        assert_eq!(handle.receiver.0.recv_timeout(TIMEOUT), Ok(3));
+    }
+}
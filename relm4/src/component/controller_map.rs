@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::hash::Hash;
+use std::rc::Rc;
+
+use crate::{
+    Component, ComponentBuilder, ComponentController, Controller, RelmContainerExt, RelmRemoveExt,
+    Sender,
+};
+
+/// A collection of child components that are identified by a key.
+///
+/// Unlike factories, this works with regular [`Component`]s.
+/// The root widgets of the children are attached to a container
+/// and their outputs are forwarded together with their key.
+/// Removing a child detaches its root widget and shuts it down.
+///
+/// ```no_run
+/// # use relm4::prelude::*;
+/// # use relm4::ControllerMap;
+/// # struct Download;
+/// # impl SimpleComponent for Download {
+/// #     type Init = String;
+/// #     type Input = ();
+/// #     type Output = bool;
+/// #     type Root = gtk::Box;
+/// #     type Widgets = ();
+/// #     fn init_root() -> gtk::Box { gtk::Box::default() }
+/// #     fn init(_: String, _: &gtk::Box, _: ComponentSender<Self>) -> ComponentParts<Self> {
+/// #         ComponentParts { model: Download, widgets: () }
+/// #     }
+/// # }
+/// #[derive(Debug)]
+/// enum Msg {
+///     Finished(u32, bool),
+/// }
+///
+/// # let container = gtk::Box::default();
+/// # let (sender, _) = relm4::channel::<Msg>();
+/// let mut downloads = ControllerMap::<u32, Download>::new(&container, &sender, |id, success| {
+///     Msg::Finished(id, success)
+/// });
+/// downloads.insert(1, "https://relm4.org".to_owned());
+/// downloads.remove(&1);
+/// ```
+pub struct ControllerMap<K, C: Component> {
+    controllers: HashMap<K, Controller<C>>,
+    attach: Box<dyn Fn(&C::Root)>,
+    detach: Box<dyn Fn(&C::Root)>,
+    forward: Rc<dyn Fn(K, C::Output)>,
+}
+
+impl<K, C> ControllerMap<K, C>
+where
+    K: Clone + Eq + Hash + 'static,
+    C: Component,
+    C::Root: AsRef<gtk::Widget>,
+{
+    /// Creates an empty map that attaches the root widgets of its children to `container`
+    /// and forwards their outputs to `sender` after transforming them with `transform`.
+    pub fn new<W, M, F>(container: &W, sender: &Sender<M>, transform: F) -> Self
+    where
+        W: RelmContainerExt + RelmRemoveExt + Clone + 'static,
+        C::Root: AsRef<W::Child>,
+        M: 'static,
+        F: Fn(K, C::Output) -> M + 'static,
+    {
+        let attach_container = container.clone();
+        let detach_container = container.clone();
+        let sender = sender.clone();
+
+        Self {
+            controllers: HashMap::new(),
+            attach: Box::new(move |root| attach_container.container_add(root)),
+            detach: Box::new(move |root| detach_container.container_remove(root)),
+            forward: Rc::new(move |key, output| sender.emit(transform(key, output))),
+        }
+    }
+
+    /// Launches a child with `init` and inserts it under `key`.
+    ///
+    /// If a child with the same key exists, it's removed first.
+    pub fn insert(&mut self, key: K, init: C::Init) -> &Controller<C> {
+        self.insert_with(key, C::builder(), init)
+    }
+
+    /// Launches a child from a configured `builder` and inserts it under `key`.
+    ///
+    /// If a child with the same key exists, it's removed first.
+    pub fn insert_with(
+        &mut self,
+        key: K,
+        builder: ComponentBuilder<C>,
+        init: C::Init,
+    ) -> &Controller<C> {
+        self.remove(&key);
+
+        let forward = Rc::clone(&self.forward);
+        let output_key = key.clone();
+        let controller = builder
+            .launch(init)
+            .connect_receiver(move |_, output| forward(output_key.clone(), output));
+        (self.attach)(controller.widget());
+
+        self.controllers.entry(key).or_insert(controller)
+    }
+
+    /// Removes the child with `key`, detaches its root widget and shuts it down.
+    ///
+    /// Returns `true` if the child existed.
+    pub fn remove(&mut self, key: &K) -> bool {
+        match self.controllers.remove(key) {
+            Some(controller) => {
+                (self.detach)(controller.widget());
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<K, C> ControllerMap<K, C>
+where
+    K: Eq + Hash,
+    C: Component,
+{
+    /// Returns the controller of the child with `key`.
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<&Controller<C>> {
+        self.controllers.get(key)
+    }
+
+    /// Sends an input message to the child with `key`.
+    ///
+    /// Returns the message as error if there is no such child.
+    pub fn send(&self, key: &K, message: C::Input) -> Result<(), C::Input> {
+        match self.controllers.get(key) {
+            Some(controller) => controller.sender().send(message),
+            None => Err(message),
+        }
+    }
+
+    /// Sends a clone of `message` to every child.
+    pub fn broadcast(&self, message: C::Input)
+    where
+        C::Input: Clone,
+    {
+        for controller in self.controllers.values() {
+            controller.emit(message.clone());
+        }
+    }
+
+    /// Returns `true` if a child with `key` exists.
+    #[must_use]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.controllers.contains_key(key)
+    }
+
+    /// Returns the number of children.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.controllers.len()
+    }
+
+    /// Returns `true` if there are no children.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.controllers.is_empty()
+    }
+
+    /// Iterates over the keys of all children in arbitrary order.
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.controllers.keys()
+    }
+
+    /// Iterates over all children in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &Controller<C>)> {
+        self.controllers.iter()
+    }
+}
+
+impl<K, C: Component> ControllerMap<K, C> {
+    /// Removes all children.
+    pub fn clear(&mut self) {
+        for (_, controller) in self.controllers.drain() {
+            (self.detach)(controller.widget());
+        }
+    }
+}
+
+impl<K, C: Component> Drop for ControllerMap<K, C> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<K: Debug, C: Component> Debug for ControllerMap<K, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ControllerMap")
+            .field("keys", &self.controllers.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::time::Duration;
+
+    use gtk::prelude::WidgetExt;
+
+    use super::ControllerMap;
+    use crate::{
+        gtk, Component, ComponentParts, ComponentSender, Receiver, RelmIterChildrenExt, Sender,
+    };
+
+    thread_local! {
+        static SHUTDOWNS: RefCell<Vec<&'static str>> = RefCell::default();
+    }
+
+    #[derive(Debug)]
+    struct Child(&'static str);
+
+    impl Component for Child {
+        type CommandOutput = ();
+        type Input = u8;
+        /// Echoes the input.
+        type Output = u8;
+        /// The name of the root widget.
+        type Init = &'static str;
+        type Root = gtk::Label;
+        type Widgets = ();
+
+        fn init_root() -> Self::Root {
+            gtk::Label::default()
+        }
+
+        fn init(
+            name: &'static str,
+            root: &Self::Root,
+            _sender: ComponentSender<Self>,
+        ) -> ComponentParts<Self> {
+            root.set_widget_name(name);
+            ComponentParts {
+                model: Self(name),
+                widgets: (),
+            }
+        }
+
+        fn update(&mut self, message: u8, sender: ComponentSender<Self>, _root: &Self::Root) {
+            sender.output(message).unwrap();
+        }
+
+        fn shutdown(&mut self, _widgets: &mut Self::Widgets, _output: Sender<Self::Output>) {
+            SHUTDOWNS.with(|shutdowns| shutdowns.borrow_mut().push(self.0));
+        }
+    }
+
+    fn map() -> (ControllerMap<u32, Child>, gtk::Box, Receiver<(u32, u8)>) {
+        SHUTDOWNS.with(|shutdowns| shutdowns.borrow_mut().clear());
+        let container = gtk::Box::default();
+        let (sender, receiver) = crate::channel();
+        let map = ControllerMap::new(&container, &sender, |key, output| (key, output));
+        (map, container, receiver)
+    }
+
+    fn widget_names(container: &gtk::Box) -> Vec<String> {
+        container
+            .iter_children()
+            .map(|child| child.widget_name().to_string())
+            .collect()
+    }
+
+    /// Runs the main context until `condition` returns `true` or the timeout expired.
+    fn settle_until(mut condition: impl FnMut() -> bool) -> bool {
+        let context = gtk::glib::MainContext::ref_thread_default();
+        for _ in 0..500 {
+            while context.iteration(false) {}
+            if condition() {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    fn outputs(receiver: &Receiver<(u32, u8)>, count: usize) -> Vec<(u32, u8)> {
+        let mut outputs = Vec::new();
+        settle_until(|| {
+            outputs.extend(receiver.0.try_iter());
+            outputs.len() >= count
+        });
+        outputs.sort_unstable();
+        outputs
+    }
+
+    fn shutdowns() -> Vec<&'static str> {
+        SHUTDOWNS.with(|shutdowns| shutdowns.borrow().clone())
+    }
+
+    #[gtk::test]
+    fn insert_attaches_widgets() {
+        let (mut map, container, _receiver) = map();
+        map.insert(1, "one");
+        map.insert_with(2, Child::builder(), "two");
+
+        assert_eq!(map.len(), 2);
+        assert!(map.contains_key(&2));
+        assert_eq!(widget_names(&container), ["one", "two"]);
+
+        // Inserting an existing key replaces the child.
+        map.insert(1, "uno");
+        assert_eq!(map.len(), 2);
+        assert_eq!(widget_names(&container), ["two", "uno"]);
+        assert!(settle_until(|| shutdowns() == ["one"]));
+    }
+
+    #[gtk::test]
+    fn remove_detaches_and_shuts_down() {
+        let (mut map, container, _receiver) = map();
+        map.insert(1, "one");
+        map.insert(2, "two");
+
+        assert!(map.remove(&1));
+        assert!(!map.remove(&1));
+        assert_eq!(map.keys().collect::<Vec<_>>(), [&2]);
+        assert_eq!(widget_names(&container), ["two"]);
+        assert!(settle_until(|| shutdowns() == ["one"]));
+    }
+
+    #[gtk::test]
+    fn send_forwards_outputs_with_key() {
+        let (mut map, _container, receiver) = map();
+        map.insert(1, "one");
+
+        assert_eq!(map.send(&3, 7), Err(7));
+        assert_eq!(map.send(&1, 7), Ok(()));
+        assert_eq!(outputs(&receiver, 1), [(1, 7)]);
+    }
+
+    #[gtk::test]
+    fn broadcast() {
+        let (mut map, _container, receiver) = map();
+        map.insert(1, "one");
+        map.insert(2, "two");
+
+        map.broadcast(5);
+        assert_eq!(outputs(&receiver, 2), [(1, 5), (2, 5)]);
+    }
+}
@@ -10,11 +10,21 @@ mod r#async;
 /// Message broker
 mod message_broker;
 
+/// Child components managed by key.
+mod controller_map;
+
+/// Panic isolation for component runtimes.
+mod panic;
+
+/// Workers that handle their messages asynchronously on the runtime of Relm4.
+pub mod async_worker;
 /// A simpler version of components that does work
 /// in the background.
 pub mod worker;
 
+pub use controller_map::ControllerMap;
 pub use message_broker::MessageBroker;
+pub use panic::Crash;
 
 pub use sync::{
     CommandFuture, Component, ComponentBuilder, ComponentController, ComponentParts,
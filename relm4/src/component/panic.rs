@@ -0,0 +1,420 @@
+use std::any::Any;
+use std::convert::Infallible;
+use std::fmt;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::time::Duration;
+
+use futures::FutureExt;
+
+use crate::Sender;
+
+/// Describes a panic that happened while a component handled a message.
+///
+/// Register a receiver with `forward_crashes()` on the component builder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Crash {
+    /// The type name of the component.
+    pub type_name: &'static str,
+    /// The message of the panic.
+    pub message: String,
+    /// Whether the component was restarted.
+    ///
+    /// If the component wasn't restarted, it either continues with
+    /// its previous model or was shut down because it exceeded its restarts.
+    pub restarted: bool,
+}
+
+/// What the runtime does when an update panics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Policy {
+    /// Continue unwinding, which usually aborts the application.
+    #[default]
+    Propagate,
+    /// Log the panic and drop the message that caused it.
+    LogAndDrop,
+    /// Initialize the component again.
+    Restart {
+        max_restarts: usize,
+        backoff: Duration,
+    },
+}
+
+type Notify = Rc<dyn Fn(Crash)>;
+
+/// Panic handling options of a component builder.
+pub(crate) struct PanicOptions<Init> {
+    policy: Policy,
+    clone_init: Option<fn(&Init) -> Init>,
+    notify: Option<Notify>,
+}
+
+impl<Init> Default for PanicOptions<Init> {
+    fn default() -> Self {
+        Self {
+            policy: Policy::default(),
+            clone_init: None,
+            notify: None,
+        }
+    }
+}
+
+impl<Init> Clone for PanicOptions<Init> {
+    fn clone(&self) -> Self {
+        Self {
+            policy: self.policy,
+            clone_init: self.clone_init,
+            notify: self.notify.clone(),
+        }
+    }
+}
+
+impl<Init> fmt::Debug for PanicOptions<Init> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PanicOptions")
+            .field("policy", &self.policy)
+            .field("notify", &self.notify.is_some())
+            .finish()
+    }
+}
+
+impl<Init> PanicOptions<Init> {
+    pub(crate) fn log_and_drop(&mut self) {
+        self.policy = Policy::LogAndDrop;
+    }
+
+    pub(crate) fn restart(&mut self, max_restarts: usize, backoff: Duration)
+    where
+        Init: Clone,
+    {
+        self.policy = Policy::Restart {
+            max_restarts,
+            backoff,
+        };
+        self.clone_init = Some(Init::clone);
+    }
+
+    pub(crate) fn notify<F: Fn(Crash) + 'static>(&mut self, notify: F) {
+        self.notify = Some(Rc::new(notify));
+    }
+
+    /// Creates the handler that is used by the runtime.
+    ///
+    /// Crashes are forwarded to the thread that launches the component,
+    /// so the handler can be moved to a worker thread.
+    pub(crate) fn handler(self, type_name: &'static str, init: &Init) -> PanicHandler<Init> {
+        let init = self.clone_init.map(|clone| (clone(init), clone));
+        self.build_handler(type_name, init)
+    }
+
+    /// Creates a handler that never keeps a copy of the payload,
+    /// so components with a restart policy are shut down instead.
+    pub(crate) fn handler_without_restarts(
+        self,
+        type_name: &'static str,
+    ) -> PanicHandler<Infallible> {
+        self.build_handler(type_name, None)
+    }
+
+    fn build_handler<I>(
+        self,
+        type_name: &'static str,
+        init: Option<(I, fn(&I) -> I)>,
+    ) -> PanicHandler<I> {
+        let Self { policy, notify, .. } = self;
+
+        let notify = notify.map(|notify| {
+            let (sender, receiver) = crate::channel();
+            crate::spawn_local(async move {
+                while let Some(crash) = receiver.recv().await {
+                    notify(crash);
+                }
+            });
+            sender
+        });
+
+        PanicHandler {
+            type_name,
+            policy,
+            init,
+            restarts: 0,
+            notify,
+        }
+    }
+}
+
+/// How the runtime continues after a panic.
+#[derive(Debug)]
+pub(crate) enum Recovery<Init> {
+    /// Keep the current model.
+    Continue,
+    /// Initialize the component again after the delay.
+    Restart(Init, Duration),
+    /// Shut down the component.
+    Stop,
+}
+
+/// Decides how the runtime of a component recovers from panics.
+pub(crate) struct PanicHandler<Init> {
+    type_name: &'static str,
+    policy: Policy,
+    /// The payload for restarts and the function to clone it.
+    init: Option<(Init, fn(&Init) -> Init)>,
+    restarts: usize,
+    notify: Option<Sender<Crash>>,
+}
+
+impl<Init> fmt::Debug for PanicHandler<Init> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PanicHandler")
+            .field("type_name", &self.type_name)
+            .field("policy", &self.policy)
+            .field("restarts", &self.restarts)
+            .field("notify", &self.notify)
+            .finish()
+    }
+}
+
+impl<Init> PanicHandler<Init> {
+    /// Returns `true` if panics should be caught at all.
+    fn catches(&self) -> bool {
+        self.policy != Policy::Propagate
+    }
+
+    /// Runs `func` and catches panics if the policy isn't to propagate them.
+    pub(crate) fn guard<R, F: FnOnce() -> R>(&self, func: F) -> Result<R, Box<dyn Any + Send>> {
+        if self.catches() {
+            panic::catch_unwind(AssertUnwindSafe(func))
+        } else {
+            Ok(func())
+        }
+    }
+
+    /// Awaits `future` and catches panics if the policy isn't to propagate them.
+    ///
+    /// The returned future doesn't borrow the handler, so it's [`Send`] if `future` is.
+    pub(crate) fn guard_future<F: Future>(
+        &self,
+        future: F,
+    ) -> impl Future<Output = Result<F::Output, Box<dyn Any + Send>>> {
+        let catches = self.catches();
+        async move {
+            if catches {
+                AssertUnwindSafe(future).catch_unwind().await
+            } else {
+                Ok(future.await)
+            }
+        }
+    }
+
+    /// Handles the payload of a caught panic.
+    ///
+    /// If the policy is to propagate panics, unwinding continues.
+    pub(crate) fn recover(&mut self, payload: Box<dyn Any + Send>) -> Recovery<Init> {
+        let message = panic_message(&*payload);
+
+        let recovery = match self.policy {
+            Policy::Propagate => panic::resume_unwind(payload),
+            Policy::LogAndDrop => {
+                tracing::error!(
+                    "Component {} panicked, dropping the message: {message}",
+                    self.type_name
+                );
+                Recovery::Continue
+            }
+            Policy::Restart {
+                max_restarts,
+                backoff,
+            } => match &self.init {
+                Some((init, clone)) if self.restarts < max_restarts => {
+                    let factor = 2_u32.saturating_pow(self.restarts.try_into().unwrap_or(u32::MAX));
+                    let delay = backoff.saturating_mul(factor);
+                    self.restarts += 1;
+                    tracing::error!(
+                        "Component {} panicked, restarting in {delay:?} ({}/{max_restarts}): {message}",
+                        self.type_name,
+                        self.restarts,
+                    );
+                    Recovery::Restart(clone(init), delay)
+                }
+                Some(_) => {
+                    tracing::error!(
+                        "Component {} panicked too often, shutting down: {message}",
+                        self.type_name
+                    );
+                    Recovery::Stop
+                }
+                None => {
+                    tracing::error!(
+                        "Component {} panicked and can't be restarted, shutting down: {message}",
+                        self.type_name
+                    );
+                    Recovery::Stop
+                }
+            },
+        };
+
+        if let Some(notify) = &self.notify {
+            notify
+                .send(Crash {
+                    type_name: self.type_name,
+                    message,
+                    restarted: matches!(recovery, Recovery::Restart(..)),
+                })
+                .ok();
+        }
+
+        recovery
+    }
+}
+
+/// Extracts the message of a panic payload.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_owned()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_owned()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use std::panic;
+    use std::time::Duration;
+
+    use super::{PanicOptions, Recovery};
+    use crate::test::ComponentHarness;
+    use crate::{Component, ComponentParts, ComponentSender, Sender};
+
+    thread_local! {
+        static INITS: Cell<u8> = Cell::new(0);
+    }
+
+    #[derive(Debug)]
+    struct Fragile;
+
+    #[derive(Debug)]
+    enum FragileMsg {
+        Panic,
+        Wait,
+    }
+
+    impl Component for Fragile {
+        type CommandOutput = ();
+        type Input = FragileMsg;
+        type Output = &'static str;
+        /// Whether initializing the component again panics.
+        type Init = bool;
+        type Root = ();
+        /// Keeps a sender like a widget callback would.
+        type Widgets = ComponentSender<Self>;
+
+        fn init_root() -> Self::Root {}
+
+        fn init(
+            fail_restart: bool,
+            _root: &(),
+            sender: ComponentSender<Self>,
+        ) -> ComponentParts<Self> {
+            let inits = INITS.with(|inits| inits.replace(inits.get() + 1)) + 1;
+            assert!(!(fail_restart && inits > 1), "can't restart");
+            ComponentParts {
+                model: Fragile,
+                widgets: sender,
+            }
+        }
+
+        fn update(&mut self, message: FragileMsg, sender: ComponentSender<Self>, _root: &()) {
+            match message {
+                FragileMsg::Panic => panic!("boom"),
+                FragileMsg::Wait => {
+                    sender.keyed_oneshot_command("wait", futures::future::pending());
+                }
+            }
+        }
+
+        fn shutdown(&mut self, _widgets: &mut Self::Widgets, output: Sender<Self::Output>) {
+            output.send("shutdown").ok();
+        }
+    }
+
+    fn launch(fail_restart: bool) -> ComponentHarness<Fragile> {
+        INITS.with(|inits| inits.set(0));
+        let builder = Fragile::builder().restart_on_panic(1, Duration::ZERO);
+        ComponentHarness::launch_with(builder, fail_restart)
+    }
+
+    #[gtk::test]
+    fn restart_shuts_down_previous_model() {
+        let harness = launch(false);
+        harness.send(FragileMsg::Wait);
+        let sender = harness.widgets().clone();
+        assert!(sender.is_command_running("wait"));
+
+        harness.send(FragileMsg::Panic);
+        assert!(harness.settle_until(Duration::from_secs(5), |_| INITS.with(Cell::get) == 2));
+        assert_eq!(harness.take_outputs(), vec!["shutdown"]);
+        assert!(!sender.is_command_running("wait"));
+    }
+
+    #[gtk::test]
+    fn stopped_component_drops_inputs() {
+        let harness = launch(true);
+        let sender = harness.widgets().clone();
+
+        // Initializing the component again panics as well, so it stops.
+        harness.send(FragileMsg::Panic);
+        assert!(harness.settle_until(Duration::from_secs(5), |_| INITS.with(Cell::get) == 2));
+        harness.settle();
+        assert_eq!(harness.take_outputs(), vec!["shutdown"]);
+
+        // Like a widget callback that fires after the component stopped.
+        sender.input(FragileMsg::Wait);
+    }
+
+    #[test]
+    fn restart_with_backoff() {
+        let mut options = PanicOptions::<u8>::default();
+        options.restart(2, Duration::from_millis(10));
+        let mut handler = options.handler("Component", &7);
+
+        for expected in [10, 20] {
+            let payload = panic::catch_unwind(|| panic!("boom")).unwrap_err();
+            match handler.recover(payload) {
+                Recovery::Restart(init, delay) => {
+                    assert_eq!(init, 7);
+                    assert_eq!(delay, Duration::from_millis(expected));
+                }
+                recovery => panic!("unexpected recovery {recovery:?}"),
+            }
+        }
+
+        let payload = panic::catch_unwind(|| panic!("boom")).unwrap_err();
+        assert!(matches!(handler.recover(payload), Recovery::Stop));
+    }
+
+    #[test]
+    fn restart_without_payload() {
+        let mut options = PanicOptions::<u8>::default();
+        options.restart(2, Duration::from_millis(10));
+        let mut handler = options.handler_without_restarts("Component");
+
+        let payload = panic::catch_unwind(|| panic!("boom")).unwrap_err();
+        assert!(matches!(handler.recover(payload), Recovery::Stop));
+    }
+
+    #[test]
+    fn log_and_drop() {
+        let mut options = PanicOptions::<()>::default();
+        options.log_and_drop();
+        let mut handler = options.handler("Component", &());
+
+        let payload = panic::catch_unwind(|| panic!("boom")).unwrap_err();
+        assert!(matches!(handler.recover(payload), Recovery::Continue));
+    }
+}
@@ -4,16 +4,24 @@
 
 use super::super::MessageBroker;
 use super::{Component, ComponentParts, Connector, StateWatcher};
+use crate::bus::Subscriptions;
+use crate::channel::ChannelOptions;
+use crate::component::panic::{PanicHandler, PanicOptions, Recovery};
+use crate::component::Crash;
+use crate::inspector::{self, InspectOptions, Registration};
+use crate::runtime_util::{batch, Queued, RuntimeHooks, ViewUpdate};
 use crate::{
-    late_initialization, ComponentSender, GuardedReceiver, Receiver, RelmContainerExt,
-    RelmWidgetExt, RuntimeSenders, Sender,
+    late_initialization, ComponentSender, GuardedReceiver, OverflowPolicy, Receiver,
+    RelmContainerExt, RelmWidgetExt, RuntimeSenders, Sender,
 };
 use gtk::glib;
 use gtk::prelude::{GtkWindowExt, NativeDialogExt};
 use std::any;
 use std::cell::RefCell;
+use std::fmt::{Debug, Display};
 use std::marker::PhantomData;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 use tokio::sync::oneshot;
 use tracing::info_span;
 
@@ -23,6 +31,13 @@ pub struct ComponentBuilder<C: Component> {
     /// The root widget of the component.
     pub root: C::Root,
     priority: glib::Priority,
+    batch_view_updates: bool,
+    pub(crate) coalesce_inputs: bool,
+    pub(crate) channel: ChannelOptions<C::Input>,
+    pub(crate) inspect: InspectOptions<C, C::Input>,
+    pub(crate) panic: PanicOptions<C::Init>,
+    pub(crate) bus: Subscriptions<C::Input>,
+    pub(crate) hooks: RuntimeHooks<C::Input, C::CommandOutput, C::Output>,
 
     pub(super) component: PhantomData<C>,
 }
@@ -33,6 +48,13 @@ impl<C: Component> Default for ComponentBuilder<C> {
         Self {
             root: C::init_root(),
             priority: glib::Priority::default(),
+            batch_view_updates: false,
+            coalesce_inputs: false,
+            channel: ChannelOptions::default(),
+            inspect: InspectOptions::default(),
+            panic: PanicOptions::default(),
+            bus: Subscriptions::default(),
+            hooks: RuntimeHooks::default(),
             component: PhantomData,
         }
     }
@@ -62,6 +84,159 @@ impl<C: Component> ComponentBuilder<C> {
         self.priority = priority;
         self
     }
+
+    /// Update the view only once for all messages that are handled
+    /// in one iteration of the main loop.
+    ///
+    /// By default, [`update_with_view`](Component::update_with_view) is called for each message.
+    /// With this option, the runtime calls [`update`](Component::update) or
+    /// [`update_cmd`](Component::update_cmd) for all queued messages and
+    /// [`update_view`](Component::update_view) only once afterwards, before the next frame is drawn.
+    ///
+    /// Because [`update_with_view`](Component::update_with_view) and
+    /// [`update_cmd_with_view`](Component::update_cmd_with_view) aren't called in this mode,
+    /// components that override them shouldn't use this option.
+    #[must_use]
+    pub fn batch_view_updates(mut self) -> Self {
+        self.batch_view_updates = true;
+        self
+    }
+
+    /// Merge queued input messages with [`coalesce`](Component::coalesce)
+    /// before they're handled.
+    ///
+    /// By default, the runtime takes one message at a time from the input channel.
+    /// With this option, it looks at the next queued message after each one it takes,
+    /// so a bounded channel can hold one more message than its capacity.
+    #[must_use]
+    pub fn coalesce_inputs(mut self) -> Self {
+        self.coalesce_inputs = true;
+        self
+    }
+
+    /// Limit the number of input messages that can be queued.
+    ///
+    /// Once `capacity` messages are waiting to be processed,
+    /// new messages are handled according to the [`OverflowPolicy`].
+    /// By default, the input channel is unbounded.
+    ///
+    /// This has no effect if the component is launched with
+    /// [`launch_with_broker`](ComponentBuilder::launch_with_broker).
+    ///
+    /// Workers can use [`OverflowPolicy::Block`] to slow down their senders,
+    /// but components run on the main thread, where blocking would dead-lock.
+    ///
+    /// # Panics
+    ///
+    /// Launching the component panics if `capacity` is zero or if it's launched
+    /// on the main thread with [`OverflowPolicy::Block`].
+    #[must_use]
+    pub fn bounded(mut self, capacity: usize, policy: OverflowPolicy<C::Input>) -> Self {
+        self.channel = self.channel.bounded(capacity, policy);
+        self
+    }
+
+    /// Handle input messages for which `is_priority` returns `true`
+    /// before all other queued messages.
+    ///
+    /// Priority messages are never dropped by an [`OverflowPolicy`].
+    ///
+    /// This has no effect if the component is launched with
+    /// [`launch_with_broker`](ComponentBuilder::launch_with_broker).
+    #[must_use]
+    pub fn prioritize<F>(mut self, is_priority: F) -> Self
+    where
+        F: Fn(&C::Input) -> bool + Send + Sync + 'static,
+    {
+        self.channel = self.channel.prioritize(is_priority);
+        self
+    }
+
+    /// Subscribe the component to messages of type `T` on the [`bus`](crate::bus).
+    ///
+    /// Published messages are turned into input messages by `map`.
+    /// The subscription ends when the component shuts down.
+    #[must_use]
+    pub fn subscribe<T, F>(mut self, map: F) -> Self
+    where
+        T: 'static,
+        C::Input: Send,
+        F: Fn(T) -> C::Input + Send + Sync + 'static,
+    {
+        self.bus.add(map);
+        self
+    }
+
+    /// Show the [`Debug`] output of the model in the [`inspector`](crate::inspector).
+    ///
+    /// The model is formatted after every update, so this
+    /// is mostly useful during development.
+    #[must_use]
+    pub fn inspect_model(mut self) -> Self
+    where
+        C: Debug,
+    {
+        self.inspect.debug_model();
+        self
+    }
+
+    /// Allow the [`inspector`](crate::inspector) to send input messages
+    /// that are parsed from text by `parse`.
+    #[must_use]
+    pub fn inspect_input<F, E>(mut self, parse: F) -> Self
+    where
+        F: Fn(&str) -> Result<C::Input, E> + 'static,
+        E: Display,
+    {
+        self.inspect.parse_input(parse);
+        self
+    }
+
+    /// Catch panics while the component handles a message,
+    /// log them and drop the message that caused the panic.
+    ///
+    /// The model keeps the state it had when the panic happened,
+    /// which might be inconsistent.
+    /// By default, panics aren't caught.
+    #[must_use]
+    pub fn catch_panics(mut self) -> Self {
+        self.panic.log_and_drop();
+        self
+    }
+
+    /// Catch panics while the component handles a message and
+    /// initialize the component again with a copy of its payload.
+    ///
+    /// Before each restart, the runtime waits for `backoff`, which doubles
+    /// after every restart. After `max_restarts` restarts, the component is shut down.
+    ///
+    /// [`init`](Component::init) is called again with the same root widget,
+    /// so it shouldn't assume that the root is empty.
+    #[must_use]
+    pub fn restart_on_panic(mut self, max_restarts: usize, backoff: Duration) -> Self
+    where
+        C::Init: Clone,
+    {
+        self.panic.restart(max_restarts, backoff);
+        self
+    }
+
+    /// Forward a [`Crash`] to `sender` whenever a panic was caught,
+    /// for example to notify the parent component.
+    #[must_use]
+    pub fn forward_crashes<X: 'static, F: (Fn(Crash) -> X) + 'static>(
+        mut self,
+        sender: &Sender<X>,
+        transform: F,
+    ) -> Self {
+        let sender = sender.clone();
+        self.panic.notify(move |crash| {
+            if sender.send(transform(crash)).is_err() {
+                tracing::error!("Couldn't forward crash of component");
+            }
+        });
+        self
+    }
 }
 
 impl<C: Component> ComponentBuilder<C>
@@ -136,11 +311,12 @@ where
 
 impl<C: Component> ComponentBuilder<C> {
     /// Starts the component, passing ownership to a future attached to a [gtk::glib::MainContext].
-    pub fn launch(self, payload: C::Init) -> Connector<C> {
+    pub fn launch(mut self, payload: C::Init) -> Connector<C> {
         // Used for all events to be processed by this component's internal service.
-        let (input_sender, input_receiver) = crate::channel::<C::Input>();
+        let (input_sender, input_receiver, priority_receiver) =
+            std::mem::take(&mut self.channel).main_thread_channel();
 
-        self.launch_with_input_channel(payload, input_sender, input_receiver)
+        self.launch_with_input_channel(payload, input_sender, input_receiver, priority_receiver)
     }
 
     /// Similar to [`launch()`](ComponentBuilder::launch) but also initializes a [`MessageBroker`].
@@ -158,6 +334,7 @@ impl<C: Component> ComponentBuilder<C> {
             payload,
             input_sender,
             input_receiver.expect("Message broker launched multiple times"),
+            None,
         )
     }
 
@@ -166,8 +343,19 @@ impl<C: Component> ComponentBuilder<C> {
         payload: C::Init,
         input_sender: Sender<C::Input>,
         input_receiver: Receiver<C::Input>,
+        priority_receiver: Option<Receiver<C::Input>>,
     ) -> Connector<C> {
-        let Self { root, priority, .. } = self;
+        let Self {
+            root,
+            priority,
+            batch_view_updates,
+            coalesce_inputs,
+            inspect,
+            panic,
+            bus,
+            mut hooks,
+            ..
+        } = self;
 
         let RuntimeSenders {
             output_sender,
@@ -178,7 +366,19 @@ impl<C: Component> ComponentBuilder<C> {
             shutdown_recipient,
             shutdown_on_drop,
             mut shutdown_event,
-        } = RuntimeSenders::<C::Output, C::CommandOutput>::new();
+            registration,
+        } = RuntimeSenders::<C::Output, C::CommandOutput>::new(any::type_name::<C>());
+
+        let output_receiver = hooks.observe_output(output_receiver);
+
+        let component_id = registration.id();
+        let counter = registration.counter();
+        let queue = input_sender.clone();
+        hooks.on_input(move |_| counter.input(queue.0.len()));
+        hooks.on_command(move |_| counter.command());
+        let debug_model = inspect.install(&registration, input_sender.clone());
+        let mut panics = panic.handler(any::type_name::<C>(), &payload);
+        let subscriptions = bus.subscribe(&input_sender);
 
         // Gets notifications when a component's model and view is updated externally.
         let (notifier, notifier_receiver) = crate::channel();
@@ -186,24 +386,35 @@ impl<C: Component> ComponentBuilder<C> {
         let (source_id_sender, source_id_receiver) =
             oneshot::channel::<gtk::glib::JoinHandle<()>>();
 
+        let (self_input_sender, cmd_sender) = hooks.sources(&input_sender, cmd_sender);
+
         // Encapsulates the senders used by component methods.
         let component_sender = ComponentSender::new(
-            input_sender.clone(),
+            self_input_sender,
             output_sender.clone(),
             cmd_sender,
-            shutdown_recipient,
+            shutdown_recipient.clone(),
         );
 
         // Constructs the initial model and view with the initial payload.
-        let state = Rc::new(RefCell::new(C::init(
-            payload,
-            &root,
-            component_sender.clone(),
-        )));
+        let state = {
+            let _current = registration.enter();
+            Rc::new(RefCell::new(C::init(
+                payload,
+                &root,
+                component_sender.clone(),
+            )))
+        };
+        {
+            let model = &state.borrow().model;
+            registration.set_name(model.id());
+            registration.snapshot(model, debug_model);
+        }
         let watcher = StateWatcher {
             state,
             notifier,
             shutdown_on_drop,
+            shutdown: shutdown_recipient,
         };
 
         let rt_state = watcher.state.clone();
@@ -212,78 +423,155 @@ impl<C: Component> ComponentBuilder<C> {
         // Spawns the component's service. It will receive both `Self::Input` and
         // `Self::CommandOutput` messages. It will spawn commands as requested by
         // updates, and send `Self::Output` messages externally.
-        let handle = crate::spawn_local_with_priority(priority, async move {
-            let id = source_id_receiver.await.unwrap().into_source_id().unwrap();
-            let mut notifier = GuardedReceiver::new(notifier_receiver);
-            let mut cmd = GuardedReceiver::new(cmd_receiver);
-            let mut input = GuardedReceiver::new(input_receiver);
-            loop {
-                futures::select!(
-                    // Performs the model update, checking if the update requested a command.
-                    // Runs that command asynchronously in the background using tokio.
-                    message = input => {
-                        let ComponentParts {
-                            model,
-                            widgets,
-                        } = &mut *rt_state.borrow_mut();
-
-                        let span = info_span!(
-                            "update_with_view",
-                            input=?message,
-                            component=any::type_name::<C>(),
-                            id=model.id(),
-                        );
-                        let _enter = span.enter();
-
-                        model.update_with_view(widgets, message, component_sender.clone(), &rt_root);
-                    }
-
-                    // Handles responses from a command.
-                    message = cmd => {
-                        let ComponentParts {
-                            model,
-                            widgets,
-                        } = &mut *rt_state.borrow_mut();
-
-                        let span = info_span!(
-                            "update_cmd_with_view",
-                            cmd_output=?message,
-                            component=any::type_name::<C>(),
-                            id=model.id(),
-                        );
-                        let _enter = span.enter();
-
-                        model.update_cmd_with_view(widgets, message, component_sender.clone(), &rt_root);
-                    }
-
-                    // Triggered when the model and view have been updated externally.
-                    _ = notifier => {
-                        let ComponentParts {
-                            model,
-                            widgets,
-                        } = &mut *rt_state.borrow_mut();
-
-                        model.update_view(widgets, component_sender.clone());
-                    }
-
-                    // Triggered when the component is destroyed
-                    _ = shutdown_event => {
-                        let ComponentParts {
-                            model,
-                            widgets,
-                        } = &mut *rt_state.borrow_mut();
-
-                        model.shutdown(widgets, output_sender);
-
-                        shutdown_notifier.shutdown();
-
-                        id.remove();
-
-                        return;
-                    }
-                );
-            }
-        });
+        let handle = crate::spawn_local_with_priority(
+            priority,
+            inspector::scope(component_id, async move {
+                let id = source_id_receiver.await.unwrap().into_source_id().unwrap();
+                // Unsubscribes from the bus when the runtime stops.
+                let _subscriptions = subscriptions;
+                let mut notifier = GuardedReceiver::new(notifier_receiver);
+                let mut cmd = GuardedReceiver::new(cmd_receiver);
+                let mut input = GuardedReceiver::with_priority(input_receiver, priority_receiver);
+                if coalesce_inputs {
+                    input = input.coalesce(C::coalesce);
+                }
+                let mut view_update = ViewUpdate::new(batch_view_updates);
+                loop {
+                    futures::select!(
+                        // Performs the model update, checking if the update requested a command.
+                        // Runs that command asynchronously in the background using tokio.
+                        message = input => {
+                            let started = Instant::now();
+                            let result = panics.guard(|| {
+                                let ComponentParts {
+                                    model,
+                                    widgets,
+                                } = &mut *rt_state.borrow_mut();
+
+                                if view_update.is_batched() {
+                                    for message in batch(Queued::Input(message), &mut input, &mut cmd) {
+                                        update_batched(model, message, &hooks, &component_sender, &rt_root);
+                                    }
+                                    view_update.schedule();
+                                } else {
+                                    hooks.input(&message);
+
+                                    let span = info_span!(
+                                        "update_with_view",
+                                        input=?message,
+                                        component=any::type_name::<C>(),
+                                        id=model.id(),
+                                    );
+                                    let _enter = span.enter();
+
+                                    model.update_with_view(widgets, message, component_sender.clone(), &rt_root);
+                                }
+
+                                registration.updated(started, model, debug_model);
+                            });
+
+                            if let Err(payload) = result {
+                                if !recover(payload, &mut panics, &rt_state, &rt_root, &component_sender, &registration).await {
+                                    shutdown_notifier.shutdown();
+                                    id.remove();
+                                    return;
+                                }
+                            }
+                        }
+
+                        // Handles responses from a command.
+                        message = cmd => {
+                            let started = Instant::now();
+                            let result = panics.guard(|| {
+                                let ComponentParts {
+                                    model,
+                                    widgets,
+                                } = &mut *rt_state.borrow_mut();
+
+                                if view_update.is_batched() {
+                                    for message in batch(Queued::Command(message), &mut input, &mut cmd) {
+                                        update_batched(model, message, &hooks, &component_sender, &rt_root);
+                                    }
+                                    view_update.schedule();
+                                } else {
+                                    hooks.command(&message);
+
+                                    let span = info_span!(
+                                        "update_cmd_with_view",
+                                        cmd_output=?message,
+                                        component=any::type_name::<C>(),
+                                        id=model.id(),
+                                    );
+                                    let _enter = span.enter();
+
+                                    model.update_cmd_with_view(widgets, message, component_sender.clone(), &rt_root);
+                                }
+
+                                registration.updated(started, model, debug_model);
+                            });
+
+                            if let Err(payload) = result {
+                                if !recover(payload, &mut panics, &rt_state, &rt_root, &component_sender, &registration).await {
+                                    shutdown_notifier.shutdown();
+                                    id.remove();
+                                    return;
+                                }
+                            }
+                        }
+
+                        // Updates the view once after a batch of messages was handled.
+                        _ = view_update => {
+                            let started = Instant::now();
+                            let ComponentParts {
+                                model,
+                                widgets,
+                            } = &mut *rt_state.borrow_mut();
+
+                            let span = info_span!(
+                                "update_view",
+                                component=any::type_name::<C>(),
+                                id=model.id(),
+                            );
+                            let _enter = span.enter();
+
+                            model.update_view(widgets, component_sender.clone());
+
+                            registration.viewed(started);
+                        }
+
+                        // Triggered when the model and view have been updated externally.
+                        _ = notifier => {
+                            let started = Instant::now();
+                            let ComponentParts {
+                                model,
+                                widgets,
+                            } = &mut *rt_state.borrow_mut();
+
+                            model.update_view(widgets, component_sender.clone());
+
+                            registration.viewed(started);
+                            registration.snapshot(model, debug_model);
+                        }
+
+                        // Triggered when the component is destroyed
+                        _ = shutdown_event => {
+                            let ComponentParts {
+                                model,
+                                widgets,
+                            } = &mut *rt_state.borrow_mut();
+
+                            model.shutdown(widgets, output_sender);
+
+                            shutdown_notifier.shutdown();
+
+                            id.remove();
+
+                            return;
+                        }
+                    );
+                }
+            }),
+        );
 
         source_id_sender.send(handle).unwrap();
 
@@ -296,3 +584,77 @@ impl<C: Component> ComponentBuilder<C> {
         }
     }
 }
+
+/// Recovers from a panic that was caught while the component handled a message.
+///
+/// Before the component is restarted, the previous model is shut down and its keyed
+/// commands are canceled. Panics while the component is initialized again are
+/// handled like panics in updates.
+///
+/// Returns `false` if the component should shut down,
+/// after its [`shutdown`](Component::shutdown) method was called.
+async fn recover<C: Component>(
+    mut payload: Box<dyn any::Any + Send>,
+    panics: &mut PanicHandler<C::Init>,
+    state: &RefCell<ComponentParts<C>>,
+    root: &C::Root,
+    sender: &ComponentSender<C>,
+    registration: &Registration,
+) -> bool {
+    let mut shut_down = false;
+    loop {
+        let restart = match panics.recover(payload) {
+            Recovery::Continue => return true,
+            Recovery::Restart(init, delay) => Some((init, delay)),
+            Recovery::Stop => None,
+        };
+
+        if !shut_down {
+            let ComponentParts { model, widgets } = &mut *state.borrow_mut();
+            let output = sender.output_sender().clone();
+            panics.guard(|| model.shutdown(widgets, output)).ok();
+            shut_down = true;
+        }
+
+        let Some((init, delay)) = restart else {
+            sender.stop();
+            return false;
+        };
+
+        sender.cancel_commands();
+        glib::timeout_future(delay).await;
+
+        let parts = {
+            let _current = registration.enter();
+            panics.guard(|| C::init(init, root, sender.clone()))
+        };
+        match parts {
+            Ok(parts) => {
+                // Drop the previous model outside of the borrow.
+                let previous = state.replace(parts);
+                drop(previous);
+                return true;
+            }
+            Err(panic) => payload = panic,
+        }
+    }
+}
+
+/// Updates the model with a message of a batch without updating the view.
+fn update_batched<C: Component>(
+    model: &mut C,
+    message: Queued<C::Input, C::CommandOutput>,
+    hooks: &RuntimeHooks<C::Input, C::CommandOutput, C::Output>,
+    sender: &ComponentSender<C>,
+    root: &C::Root,
+) {
+    hooks.queued(&message);
+
+    let span = message.span::<C>(&model.id());
+    let _enter = span.enter();
+
+    match message {
+        Queued::Input(message) => model.update(message, sender.clone(), root),
+        Queued::Command(message) => model.update_cmd(message, sender.clone(), root),
+    }
+}
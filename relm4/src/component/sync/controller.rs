@@ -5,7 +5,7 @@
 use std::cell::Ref;
 use std::fmt::{self, Debug};
 
-use crate::Sender;
+use crate::{Canceled, Responder, Sender};
 
 use super::{Component, StateWatcher};
 
@@ -56,6 +56,22 @@ pub struct Controller<C: Component> {
     pub(super) sender: Sender<C::Input>,
 }
 
+impl<C: Component> Controller<C> {
+    /// Sends a request to the component and waits for the reply.
+    ///
+    /// `message` creates the input message from the [`Responder`] that the
+    /// component uses to reply.
+    /// The request is canceled if the component shuts down before it replies.
+    pub async fn request<R, F>(&self, message: F) -> Result<R, Canceled>
+    where
+        F: FnOnce(Responder<R>) -> C::Input,
+    {
+        self.sender
+            .request_until_shutdown(self.state.shutdown.clone(), message)
+            .await
+    }
+}
+
 impl<C: Component> ComponentController<C> for Controller<C> {
     fn sender(&self) -> &Sender<C::Input> {
         &self.sender
@@ -1,4 +1,4 @@
-use crate::{Component, ComponentParts, ShutdownOnDrop};
+use crate::{Component, ComponentParts, ShutdownOnDrop, ShutdownReceiver};
 
 use std::cell::{Ref, RefCell, RefMut};
 use std::fmt::{self, Debug};
@@ -12,6 +12,8 @@ pub struct StateWatcher<C: Component> {
     pub(super) state: Rc<RefCell<ComponentParts<C>>>,
     pub(super) notifier: crate::Sender<()>,
     pub(super) shutdown_on_drop: ShutdownOnDrop,
+    /// Receives a signal when the runtime of the component shuts down.
+    pub(super) shutdown: ShutdownReceiver,
 }
 
 impl<C: Component> StateWatcher<C> {
@@ -43,6 +45,7 @@ where
             .field("state", &self.state)
             .field("notifier", &self.notifier)
             .field("shutdown_on_drop", &self.shutdown_on_drop)
+            .field("shutdown", &self.shutdown)
             .finish()
     }
 }
@@ -5,11 +5,18 @@
 use gtk::glib;
 use tracing::info_span;
 
+use crate::bus::Subscription;
+use crate::channel::ChannelOptions;
+use crate::component::panic::{PanicHandler, Recovery};
+use crate::inspector::Registration;
 use crate::{
-    Component, ComponentBuilder, ComponentParts, ComponentSender, GuardedReceiver, Receiver,
-    RuntimeSenders, Sender, ShutdownOnDrop, SimpleComponent,
+    Canceled, Component, ComponentBuilder, ComponentParts, ComponentSender, GuardedReceiver,
+    Receiver, Responder, RuntimeSenders, Sender, ShutdownOnDrop, ShutdownReceiver, SimpleComponent,
 };
+use std::collections::hash_map::DefaultHasher;
 use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use std::time::Instant;
 use std::{any, thread};
 
 /// Receives inputs and outputs in the background.
@@ -73,11 +80,39 @@ where
 {
     /// Starts a worker on a separate thread,
     /// passing ownership to a future attached to a [gtk::glib::MainContext].
-    pub fn detach_worker(self, payload: C::Init) -> WorkerHandle<C> {
-        let Self { root, .. } = self;
-
+    ///
+    /// The worker thread has no copy of the payload, so a worker configured with
+    /// [`restart_on_panic`](ComponentBuilder::restart_on_panic) is shut down after a panic.
+    /// Use [`detach_restartable_worker`](ComponentBuilder::detach_restartable_worker)
+    /// to restart it instead.
+    pub fn detach_worker(mut self, payload: C::Init) -> WorkerHandle<C> {
         // Used for all events to be processed by this component's internal service.
-        let (input_sender, input_receiver) = crate::channel::<C::Input>();
+        let channel = std::mem::take(&mut self.channel).channel();
+        let panics =
+            std::mem::take(&mut self.panic).handler_without_restarts(any::type_name::<C>());
+        self.spawn_worker(payload, channel, panics, |never| match never {})
+    }
+
+    /// Starts a worker on a separate thread that receives its input from `channel`.
+    ///
+    /// After a panic, the worker is initialized again with the payload
+    /// that `reinit` creates from the payload of `panics`.
+    fn spawn_worker<I: Send + 'static>(
+        self,
+        payload: C::Init,
+        channel: InputChannel<C::Input>,
+        mut panics: PanicHandler<I>,
+        reinit: fn(I) -> C::Init,
+    ) -> WorkerHandle<C> {
+        let Self {
+            root,
+            coalesce_inputs,
+            inspect,
+            bus,
+            ..
+        } = self;
+
+        let (input_sender, input_receiver, priority_receiver) = channel;
 
         let RuntimeSenders {
             output_sender,
@@ -88,17 +123,26 @@ where
             shutdown_recipient,
             shutdown_on_drop,
             mut shutdown_event,
-        } = RuntimeSenders::<C::Output, C::CommandOutput>::new();
+            registration,
+        } = RuntimeSenders::<C::Output, C::CommandOutput>::new(any::type_name::<C>());
+
+        let debug_model = inspect.install(&registration, input_sender.clone());
+        let subscriptions = bus.subscribe(&input_sender);
 
         // Encapsulates the senders used by component methods.
         let component_sender = ComponentSender::new(
             input_sender.clone(),
             output_sender.clone(),
             cmd_sender,
-            shutdown_recipient,
+            shutdown_recipient.clone(),
         );
 
-        let mut state = C::init(payload, &root, component_sender.clone());
+        let mut state = {
+            let _current = registration.enter();
+            C::init(payload, &root, component_sender.clone())
+        };
+        registration.set_name(state.model.id());
+        registration.snapshot(&state.model, debug_model);
 
         thread::spawn(move || {
             let context =
@@ -108,46 +152,78 @@ where
             // `Self::CommandOutput` messages. It will spawn commands as requested by
             // updates, and send `Self::Output` messages externally.
             context.block_on(async move {
+                // Unsubscribes from the bus when the runtime stops.
+                let _subscriptions = subscriptions;
+
                 let mut cmd = GuardedReceiver::new(cmd_receiver);
-                let mut input = GuardedReceiver::new(input_receiver);
+                let mut input = GuardedReceiver::with_priority(input_receiver, priority_receiver);
+                if coalesce_inputs {
+                    input = input.coalesce(C::coalesce);
+                }
 
                 loop {
                     futures::select!(
                         // Performs the model update, checking if the update requested a command.
                         // Runs that command asynchronously in the background using tokio.
                         message = input => {
-                            let ComponentParts {
-                                model,
-                                widgets,
-                            } = &mut state;
-
-                            let span = info_span!(
-                                "update_with_view",
-                                input=?message,
-                                component=any::type_name::<C>(),
-                                id=model.id(),
-                            );
-                            let _enter = span.enter();
-
-                            model.update_with_view(widgets, message, component_sender.clone(), &root);
+                            registration.counter().input(component_sender.input_sender().0.len());
+                            let started = Instant::now();
+                            let result = panics.guard(|| {
+                                let ComponentParts {
+                                    model,
+                                    widgets,
+                                } = &mut state;
+
+                                let span = info_span!(
+                                    "update_with_view",
+                                    input=?message,
+                                    component=any::type_name::<C>(),
+                                    id=model.id(),
+                                );
+                                let _enter = span.enter();
+
+                                model.update_with_view(widgets, message, component_sender.clone(), &root);
+
+                                registration.updated(started, model, debug_model);
+                            });
+
+                            if let Err(payload) = result {
+                                if !recover(payload, &mut panics, reinit, &mut state, &root, &component_sender, &registration) {
+                                    shutdown_notifier.shutdown();
+                                    return;
+                                }
+                            }
                         }
 
                         // Handles responses from a command.
                         message = cmd => {
-                            let ComponentParts {
-                                model,
-                                widgets,
-                            } = &mut state;
-
-                            let span = info_span!(
-                                "update_cmd_with_view",
-                                cmd_output=?message,
-                                component=any::type_name::<C>(),
-                                id=model.id(),
-                            );
-                            let _enter = span.enter();
-
-                            model.update_cmd_with_view(widgets, message, component_sender.clone(), &root);
+                            registration.counter().command();
+                            let started = Instant::now();
+                            let result = panics.guard(|| {
+                                let ComponentParts {
+                                    model,
+                                    widgets,
+                                } = &mut state;
+
+                                let span = info_span!(
+                                    "update_cmd_with_view",
+                                    cmd_output=?message,
+                                    component=any::type_name::<C>(),
+                                    id=model.id(),
+                                );
+                                let _enter = span.enter();
+
+                                model.update_cmd_with_view(widgets, message, component_sender.clone(), &root);
+
+                                registration.updated(started, model, debug_model);
+                            });
+
+                            if let Err(payload) = result {
+                                if !recover(payload, &mut panics, reinit, &mut state, &root, &component_sender, &registration) {
+                                    shutdown_notifier.shutdown();
+                                    return;
+                                }
+                            }
                         },
 
                         // Triggered when the component is destroyed
@@ -173,10 +249,207 @@ where
             sender: input_sender,
             receiver: output_receiver,
             shutdown_on_drop,
+            shutdown: shutdown_recipient,
+        }
+    }
+}
+
+impl<C> ComponentBuilder<C>
+where
+    C: Component<Root = (), Widgets = ()> + Send,
+    C::Init: Clone + Send,
+    C::Input: Send,
+    C::Output: Send,
+    C::CommandOutput: Send,
+{
+    /// Starts a worker on a separate thread like [`detach_worker`](ComponentBuilder::detach_worker),
+    /// but keeps a copy of the payload on the worker thread.
+    ///
+    /// If the worker was configured with [`restart_on_panic`](ComponentBuilder::restart_on_panic),
+    /// the worker thread sleeps during the backoff before it's initialized again.
+    pub fn detach_restartable_worker(mut self, payload: C::Init) -> WorkerHandle<C> {
+        let channel = std::mem::take(&mut self.channel).channel();
+        self.spawn_restartable_worker(payload, channel)
+    }
+
+    /// Starts `size` instances of a worker, each on a separate thread.
+    ///
+    /// All workers receive their input from the same queue,
+    /// so each message is handled by the next idle worker.
+    /// The outputs of all workers are merged into the returned handle.
+    ///
+    /// The options of this builder apply to every worker,
+    /// while [`bounded`](ComponentBuilder::bounded) and [`prioritize`](ComponentBuilder::prioritize)
+    /// apply to the shared queue.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    pub fn detach_worker_pool(mut self, size: usize, payload: C::Init) -> WorkerHandle<C> {
+        assert!(size > 0, "Worker pools need at least one worker");
+
+        let (sender, receiver, priority_receiver) = std::mem::take(&mut self.channel).channel();
+        let subscriptions = std::mem::take(&mut self.bus).subscribe(&sender);
+        let workers = (0..size).map(|_| {
+            let channel = (
+                sender.clone(),
+                Receiver(receiver.0.clone(), receiver.1.clone()),
+                priority_receiver
+                    .as_ref()
+                    .map(|priority| Receiver(priority.0.clone(), priority.1.clone())),
+            );
+            self.pool_member()
+                .spawn_restartable_worker(payload.clone(), channel)
+        });
+
+        WorkerHandle::merge(sender, workers.collect(), subscriptions)
+    }
+
+    /// Starts `size` instances of a worker, each on a separate thread.
+    ///
+    /// Messages with the same `key` are always handled by the same worker in the order
+    /// they were sent, which is useful if workers keep state, for example a cache.
+    /// The outputs of all workers are merged into the returned handle.
+    ///
+    /// The options of this builder apply to every worker,
+    /// while [`bounded`](ComponentBuilder::bounded) applies to the queue
+    /// in front of the workers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    pub fn detach_worker_pool_by_key<K, F>(
+        mut self,
+        size: usize,
+        payload: C::Init,
+        key: F,
+    ) -> WorkerHandle<C>
+    where
+        K: Hash,
+        F: Fn(&C::Input) -> K + Send + 'static,
+    {
+        assert!(size > 0, "Worker pools need at least one worker");
+
+        let ChannelOptions { bound, priority } = std::mem::take(&mut self.channel);
+        let (sender, receiver, _) = ChannelOptions {
+            bound,
+            priority: None,
+        }
+        .channel();
+        let subscriptions = std::mem::take(&mut self.bus).subscribe(&sender);
+
+        // Priority messages skip the queue of each worker instead.
+        let workers: Vec<_> = (0..size)
+            .map(|_| {
+                let channel = ChannelOptions {
+                    bound: None,
+                    priority: priority.clone(),
+                }
+                .channel();
+                self.pool_member()
+                    .spawn_restartable_worker(payload.clone(), channel)
+            })
+            .collect();
+
+        let senders: Vec<_> = workers.iter().map(|worker| worker.sender.clone()).collect();
+        crate::spawn(async move {
+            while let Some(message) = receiver.recv().await {
+                let mut hasher = DefaultHasher::new();
+                key(&message).hash(&mut hasher);
+                let index = (hasher.finish() % senders.len() as u64) as usize;
+
+                if senders[index].send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        WorkerHandle::merge(sender, workers, subscriptions)
+    }
+
+    /// Starts a worker that receives its input from `channel` and
+    /// can be restarted after a panic.
+    fn spawn_restartable_worker(
+        mut self,
+        payload: C::Init,
+        channel: InputChannel<C::Input>,
+    ) -> WorkerHandle<C> {
+        let panics = std::mem::take(&mut self.panic).handler(any::type_name::<C>(), &payload);
+        self.spawn_worker(payload, channel, panics, std::convert::identity)
+    }
+
+    /// Creates a builder for one worker of a pool with the options of this builder.
+    ///
+    /// Members never coalesce inputs, so they don't take a second message from
+    /// the shared queue while another worker could handle it.
+    fn pool_member(&self) -> Self {
+        let mut builder = Self::default();
+        builder.inspect = self.inspect.clone();
+        builder.panic = self.panic.clone();
+        builder
+    }
+}
+
+/// Recovers from a panic that was caught while a worker handled a message.
+///
+/// Before the worker is restarted, the previous model is shut down and its keyed
+/// commands are canceled. Panics while the worker is initialized again are
+/// handled like panics in updates.
+///
+/// Returns `false` if the worker should shut down,
+/// after its [`shutdown`](Component::shutdown) method was called.
+fn recover<C, I>(
+    mut payload: Box<dyn any::Any + Send>,
+    panics: &mut PanicHandler<I>,
+    reinit: fn(I) -> C::Init,
+    state: &mut ComponentParts<C>,
+    root: &C::Root,
+    sender: &ComponentSender<C>,
+    registration: &Registration,
+) -> bool
+where
+    C: Component,
+{
+    let mut shut_down = false;
+    loop {
+        let restart = match panics.recover(payload) {
+            Recovery::Continue => return true,
+            Recovery::Restart(init, delay) => Some((init, delay)),
+            Recovery::Stop => None,
+        };
+
+        if !shut_down {
+            let ComponentParts { model, widgets } = &mut *state;
+            let output = sender.output_sender().clone();
+            panics.guard(|| model.shutdown(widgets, output)).ok();
+            shut_down = true;
+        }
+
+        let Some((init, delay)) = restart else {
+            sender.stop();
+            return false;
+        };
+
+        sender.cancel_commands();
+        thread::sleep(delay);
+
+        let parts = {
+            let _current = registration.enter();
+            panics.guard(|| C::init(reinit(init), root, sender.clone()))
+        };
+        match parts {
+            Ok(parts) => {
+                *state = parts;
+                return true;
+            }
+            Err(panic) => payload = panic,
         }
     }
 }
 
+/// The input channel of a worker with its optional priority receiver.
+type InputChannel<T> = (Sender<T>, Receiver<T>, Option<Receiver<T>>);
+
 #[derive(Debug)]
 /// Handle to a worker task in the background
 pub struct WorkerHandle<W: Component> {
@@ -186,6 +459,55 @@ pub struct WorkerHandle<W: Component> {
     receiver: Receiver<W::Output>,
     // Shutdown the worker when this is dropped
     shutdown_on_drop: ShutdownOnDrop,
+    // Receives a signal when the worker shuts down
+    shutdown: ShutdownReceiver,
+}
+
+impl<W: Component> WorkerHandle<W>
+where
+    W::Output: Send,
+{
+    /// Combines the workers of a pool into one handle that sends
+    /// its input to `sender` and receives the outputs of all workers.
+    fn merge(
+        sender: Sender<W::Input>,
+        workers: Vec<Self>,
+        subscriptions: Vec<Subscription>,
+    ) -> Self {
+        let (output_sender, receiver) = crate::channel();
+
+        let mut shutdowns = Vec::with_capacity(workers.len());
+        let shutdown_on_drop = ShutdownOnDrop::combine(workers.into_iter().map(|worker| {
+            let output_sender = output_sender.clone();
+            let worker_receiver = worker.receiver;
+            crate::spawn(async move {
+                while let Some(output) = worker_receiver.recv().await {
+                    if output_sender.send(output).is_err() {
+                        break;
+                    }
+                }
+            });
+            shutdowns.push(worker.shutdown);
+            worker.shutdown_on_drop
+        }));
+
+        // The pool is shut down once its last worker stopped.
+        let shutdown = ShutdownReceiver::combine(shutdowns);
+
+        // Unsubscribes the pool from the bus when it shuts down.
+        let pool_shutdown = shutdown.clone();
+        crate::spawn(async move {
+            pool_shutdown.wait().await;
+            drop(subscriptions);
+        });
+
+        Self {
+            sender,
+            receiver,
+            shutdown_on_drop,
+            shutdown,
+        }
+    }
 }
 
 impl<W: Component> WorkerHandle<W>
@@ -202,6 +524,7 @@ where
             sender,
             receiver,
             shutdown_on_drop,
+            shutdown,
         } = self;
 
         let mut sender_ = sender.clone();
@@ -214,6 +537,7 @@ where
         WorkerController {
             sender,
             shutdown_on_drop,
+            shutdown,
         }
     }
 
@@ -227,12 +551,14 @@ where
             sender: own_sender,
             receiver,
             shutdown_on_drop,
+            shutdown,
         } = self;
 
         crate::spawn_local(receiver.forward(sender.clone(), transform));
         WorkerController {
             sender: own_sender,
             shutdown_on_drop,
+            shutdown,
         }
     }
 
@@ -242,12 +568,14 @@ where
         let Self {
             sender,
             shutdown_on_drop,
+            shutdown,
             ..
         } = self;
 
         WorkerController {
             sender,
             shutdown_on_drop,
+            shutdown,
         }
     }
 }
@@ -259,6 +587,8 @@ pub struct WorkerController<W: Component> {
     sender: Sender<W::Input>,
     // Shutdown the worker when this is dropped
     shutdown_on_drop: ShutdownOnDrop,
+    // Receives a signal when the worker shuts down
+    shutdown: ShutdownReceiver,
 }
 
 impl<W: Component> WorkerController<W> {
@@ -273,6 +603,20 @@ impl<W: Component> WorkerController<W> {
         &self.sender
     }
 
+    /// Sends a request to the worker and waits for the reply.
+    ///
+    /// `message` creates the input message from the [`Responder`] that the
+    /// worker uses to reply.
+    /// The request is canceled if the worker shuts down before it replies.
+    pub async fn request<R, F>(&self, message: F) -> Result<R, Canceled>
+    where
+        F: FnOnce(Responder<R>) -> W::Input,
+    {
+        self.sender
+            .request_until_shutdown(self.shutdown.clone(), message)
+            .await
+    }
+
     /// Dropping this type will usually stop the runtime of the worker.
     /// With this method you can give the runtime a static lifetime.
     /// In other words, dropping the [`WorkerController`] will not stop
@@ -281,3 +625,94 @@ impl<W: Component> WorkerController<W> {
         self.shutdown_on_drop.deactivate();
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use super::Worker;
+    use crate::{Component, ComponentSender};
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[derive(Debug)]
+    enum Job {
+        /// Waits until the gate is dropped.
+        Blocking(flume::Receiver<()>, u32),
+        /// Waits for the given time.
+        Sleep(Duration, u32),
+    }
+
+    struct Pool;
+
+    impl Worker for Pool {
+        type Init = ();
+        type Input = (u32, Job);
+        type Output = (u32, u32);
+
+        fn init(_init: Self::Init, _sender: ComponentSender<Self>) -> Self {
+            Self
+        }
+
+        fn update(&mut self, (key, job): Self::Input, sender: ComponentSender<Self>) {
+            let id = match job {
+                Job::Blocking(gate, id) => {
+                    gate.recv().ok();
+                    id
+                }
+                Job::Sleep(duration, id) => {
+                    std::thread::sleep(duration);
+                    id
+                }
+            };
+            sender.output((key, id)).unwrap();
+        }
+    }
+
+    #[test]
+    fn idle_worker_takes_next_job() {
+        let handle = Pool::builder().detach_worker_pool(2, ());
+        let (gate, gate_receiver) = flume::bounded::<()>(0);
+
+        handle
+            .sender
+            .send((0, Job::Blocking(gate_receiver, 1)))
+            .unwrap();
+        handle
+            .sender
+            .send((0, Job::Sleep(Duration::ZERO, 2)))
+            .unwrap();
+
+        // The second job finishes while the first one still blocks its worker.
+        assert_eq!(handle.receiver.0.recv_timeout(TIMEOUT), Ok((0, 2)));
+
+        drop(gate);
+        assert_eq!(handle.receiver.0.recv_timeout(TIMEOUT), Ok((0, 1)));
+    }
+
+    #[test]
+    fn keyed_jobs_keep_their_order() {
+        let handle = Pool::builder().detach_worker_pool_by_key(3, (), |(key, _)| *key);
+
+        for id in 0..10 {
+            for key in 0..4 {
+                // Earlier jobs take longer, so reordering would show up.
+                let duration = Duration::from_millis(u64::from(10 - id));
+                handle.sender.send((key, Job::Sleep(duration, id))).unwrap();
+            }
+        }
+
+        let mut handled: HashMap<u32, Vec<u32>> = HashMap::new();
+        for _ in 0..40 {
+            let (key, id) = handle.receiver.0.recv_timeout(TIMEOUT).unwrap();
+            handled.entry(key).or_default().push(id);
+        }
+
+        let expected: Vec<u32> = (0..10).collect();
+        assert_eq!(handled.len(), 4);
+        for ids in handled.values() {
+            assert_eq!(ids, &expected);
+        }
+    }
+}
@@ -7,7 +7,7 @@ use super::{AsyncFactoryComponent, AsyncFactoryHandle};
 
 use crate::channel::AsyncFactorySender;
 use crate::factory::{DataGuard, DynamicIndex, FactoryView};
-use crate::runtime_util::GuardedReceiver;
+use crate::runtime_util::{batch, GuardedReceiver, Queued, ViewUpdate};
 use crate::shutdown::ShutdownSender;
 use crate::{shutdown, Receiver, Sender};
 
@@ -16,6 +16,7 @@ pub(super) struct AsyncFactoryBuilder<C: AsyncFactoryComponent> {
     pub(super) root_widget: C::Root,
     pub(super) component_sender: AsyncFactorySender<C>,
     input_receiver: Receiver<C::Input>,
+    priority_receiver: Option<Receiver<C::Input>>,
     output_receiver: Receiver<C::Output>,
     cmd_receiver: Receiver<C::CommandOutput>,
     shutdown_notifier: ShutdownSender,
@@ -27,7 +28,8 @@ where
 {
     pub(super) fn new(init: C::Init) -> Self {
         // Used for all events to be processed by this component's internal service.
-        let (input_sender, input_receiver) = crate::channel::<C::Input>();
+        let (input_sender, input_receiver, priority_receiver) =
+            C::input_channel().main_thread_channel();
 
         // Used by this component to send events to be handled externally by the caller.
         let (output_sender, output_receiver) = crate::channel::<C::Output>();
@@ -49,6 +51,7 @@ where
             root_widget,
             component_sender,
             input_receiver,
+            priority_receiver,
             output_receiver,
             cmd_receiver,
             shutdown_notifier,
@@ -70,13 +73,14 @@ where
             mut root_widget,
             component_sender,
             input_receiver,
+            priority_receiver,
             output_receiver,
             cmd_receiver,
             shutdown_notifier,
             init,
         } = self;
 
-        let forward_sender = parent_sender.0.clone();
+        let forward_sender = parent_sender.clone();
         crate::spawn_local(async move {
             while let Some(msg) = output_receiver.recv().await {
                 if let Some(new_msg) = transform(msg) {
@@ -105,6 +109,7 @@ where
                 root: root_widget.clone(),
                 returned_widget: returned_widget.clone(),
                 input_receiver,
+                priority_receiver,
                 cmd_receiver,
                 notifier_receiver,
             };
@@ -138,6 +143,7 @@ impl<C: AsyncFactoryComponent> std::fmt::Debug for AsyncFactoryBuilder<C> {
             .field("root_widget", &self.root_widget)
             .field("component_sender", &"<AsyncComponentSender<C>>")
             .field("input_receiver", &self.input_receiver)
+            .field("priority_receiver", &self.priority_receiver)
             .field("output_receiver", &self.output_receiver)
             .field("cmd_receiver", &self.cmd_receiver)
             .field("shutdown_notifier", &self.shutdown_notifier)
@@ -152,6 +158,7 @@ struct FutureData<C: AsyncFactoryComponent> {
     root: C::Root,
     returned_widget: <C::ParentWidget as FactoryView>::ReturnedWidget,
     input_receiver: Receiver<C::Input>,
+    priority_receiver: Option<Receiver<C::Input>>,
     cmd_receiver: Receiver<C::CommandOutput>,
     notifier_receiver: Receiver<()>,
 }
@@ -166,6 +173,7 @@ impl<C: AsyncFactoryComponent> FutureData<C> {
             returned_widget,
             cmd_receiver,
             input_receiver,
+            priority_receiver,
             notifier_receiver,
         } = self;
 
@@ -186,34 +194,54 @@ impl<C: AsyncFactoryComponent> FutureData<C> {
             |mut model, mut widgets| async move {
                 let mut notifier = GuardedReceiver::new(notifier_receiver);
                 let mut cmd = GuardedReceiver::new(cmd_receiver);
-                let mut input = GuardedReceiver::new(input_receiver);
+                let mut input = GuardedReceiver::with_priority(input_receiver, priority_receiver);
+                let mut view_update = ViewUpdate::new(C::batch_view_updates());
                 loop {
                     futures::select!(
                         // Performs the model update, checking if the update requested a command.
                         // Runs that command asynchronously in the background using tokio.
                         message = input => {
-                            let span = info_span!(
-                                "update_with_view",
-                                input=?message,
-                                component=any::type_name::<C>(),
-                                id=model.id(),
-                            );
-                            let _enter = span.enter();
-
-                            model.update_with_view(&mut widgets, message, component_sender.clone()).await;
+                            if view_update.is_batched() {
+                                for message in batch(Queued::Input(message), &mut input, &mut cmd) {
+                                    update_batched(&mut **model, message, &component_sender).await;
+                                }
+                                view_update.schedule();
+                            } else {
+                                let span = info_span!(
+                                    "update_with_view",
+                                    input=?message,
+                                    component=any::type_name::<C>(),
+                                    id=model.id(),
+                                );
+                                let _enter = span.enter();
+
+                                model.update_with_view(&mut widgets, message, component_sender.clone()).await;
+                            }
                         }
 
                         // Handles responses from a command.
                         message = cmd => {
-                            let span = info_span!(
-                                "update_cmd_with_view",
-                                cmd_output=?message,
-                                component=any::type_name::<C>(),
-                                id=model.id(),
-                            );
-                            let _enter = span.enter();
-
-                            model.update_cmd_with_view(&mut widgets, message, component_sender.clone()).await;
+                            if view_update.is_batched() {
+                                for message in batch(Queued::Command(message), &mut input, &mut cmd) {
+                                    update_batched(&mut **model, message, &component_sender).await;
+                                }
+                                view_update.schedule();
+                            } else {
+                                let span = info_span!(
+                                    "update_cmd_with_view",
+                                    cmd_output=?message,
+                                    component=any::type_name::<C>(),
+                                    id=model.id(),
+                                );
+                                let _enter = span.enter();
+
+                                model.update_cmd_with_view(&mut widgets, message, component_sender.clone()).await;
+                            }
+                        }
+
+                        // Updates the view once after a batch of messages was handled.
+                        _ = view_update => {
+                            model.update_view(&mut widgets, component_sender.clone());
                         }
 
                         // Triggered when the model and view have been updated externally.
@@ -227,3 +255,18 @@ impl<C: AsyncFactoryComponent> FutureData<C> {
         )
     }
 }
+
+/// Updates the model with a message of a batch without updating the view.
+async fn update_batched<C: AsyncFactoryComponent>(
+    model: &mut C,
+    message: Queued<C::Input, C::CommandOutput>,
+    sender: &AsyncFactorySender<C>,
+) {
+    let span = message.span::<C>(&model.id());
+    let _enter = span.enter();
+
+    match message {
+        Queued::Input(message) => model.update(message, sender.clone()).await,
+        Queued::Command(message) => model.update_cmd(message, sender.clone()).await,
+    }
+}
@@ -0,0 +1,424 @@
+use crate::Sender;
+
+use crate::factory::r#async::traits::AsyncFactoryComponent;
+use crate::factory::r#async::{AsyncFactoryBuilder, AsyncFactoryHandle};
+use crate::factory::{DynamicIndex, FactoryView};
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::iter::FusedIterator;
+use std::ops;
+
+/// A mutable reference to the model of an element in an [`AsyncFactoryHashMap`].
+///
+/// The element is updated when the guard goes out of scope.
+#[derive(Debug)]
+#[must_use]
+pub struct AsyncFactoryElementGuard<'a, C>
+where
+    C: AsyncFactoryComponent,
+{
+    inner: &'a mut AsyncFactoryHandle<C>,
+}
+
+impl<'a, C> ops::Deref for AsyncFactoryElementGuard<'a, C>
+where
+    C: AsyncFactoryComponent,
+{
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        self.inner
+            .data
+            .get()
+            .expect("Guards are only created for initialized elements")
+    }
+}
+
+impl<'a, C> ops::DerefMut for AsyncFactoryElementGuard<'a, C>
+where
+    C: AsyncFactoryComponent,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.inner
+            .data
+            .get_mut()
+            .expect("Guards are only created for initialized elements")
+    }
+}
+
+impl<'a, C> Drop for AsyncFactoryElementGuard<'a, C>
+where
+    C: AsyncFactoryComponent,
+{
+    fn drop(&mut self) {
+        self.inner.notifier.send(()).unwrap()
+    }
+}
+
+/// An element of an [`AsyncFactoryHashMap`] and its position in the parent widget.
+#[derive(Debug)]
+struct Entry<C: AsyncFactoryComponent> {
+    index: DynamicIndex,
+    handle: AsyncFactoryHandle<C>,
+}
+
+/// A container similar to [`HashMap`] that can be used to store
+/// values of type [`AsyncFactoryComponent`].
+///
+/// Widgets are appended in insertion order.
+/// The [`DynamicIndex`] of each element is its position in the parent widget.
+#[derive(Debug)]
+pub struct AsyncFactoryHashMap<K, C, S = RandomState>
+where
+    C: AsyncFactoryComponent,
+    <C::ParentWidget as FactoryView>::ReturnedWidget: Clone,
+{
+    widget: C::ParentWidget,
+    parent_sender: Sender<C::ParentInput>,
+    inner: HashMap<K, Entry<C>, S>,
+}
+
+impl<K, C, S> Drop for AsyncFactoryHashMap<K, C, S>
+where
+    C: AsyncFactoryComponent,
+    <C::ParentWidget as FactoryView>::ReturnedWidget: Clone,
+{
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<K, C> AsyncFactoryHashMap<K, C, RandomState>
+where
+    C: AsyncFactoryComponent,
+    <C::ParentWidget as FactoryView>::ReturnedWidget: Clone,
+{
+    /// Creates a new [`AsyncFactoryHashMap`].
+    #[must_use]
+    pub fn new(widget: C::ParentWidget, parent_sender: &Sender<C::ParentInput>) -> Self {
+        Self {
+            widget,
+            parent_sender: parent_sender.clone(),
+            inner: HashMap::new(),
+        }
+    }
+}
+
+impl<K, C, S> AsyncFactoryHashMap<K, C, S>
+where
+    C: AsyncFactoryComponent,
+    <C::ParentWidget as FactoryView>::ReturnedWidget: Clone,
+{
+    /// Creates a new [`AsyncFactoryHashMap`] that uses the given hash builder.
+    #[must_use]
+    pub fn with_hasher(
+        widget: C::ParentWidget,
+        parent_sender: &Sender<C::ParentInput>,
+        hash_builder: S,
+    ) -> Self {
+        Self {
+            widget,
+            parent_sender: parent_sender.clone(),
+            inner: HashMap::with_hasher(hash_builder),
+        }
+    }
+
+    /// Returns the number of elements in the [`AsyncFactoryHashMap`].
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the [`AsyncFactoryHashMap`] is empty.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Send clone of a message to all of the elements.
+    pub fn broadcast(&self, msg: C::Input)
+    where
+        C::Input: Clone,
+    {
+        self.inner
+            .values()
+            .for_each(|entry| entry.handle.input.emit(msg.clone()));
+    }
+
+    /// Returns the widget all components are attached to.
+    pub const fn widget(&self) -> &C::ParentWidget {
+        &self.widget
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order.
+    ///
+    /// Values are [`None`] while their [`init_model()`] future is running.
+    ///
+    /// [`init_model()`]: AsyncFactoryComponent::init_model
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = (&K, Option<&C>)> + ExactSizeIterator + FusedIterator {
+        self.inner
+            .iter()
+            .map(|(key, entry)| (key, entry.handle.data.get()))
+    }
+
+    /// Returns an iterator over the factory components.
+    ///
+    /// Values are [`None`] while their [`init_model()`] future is running.
+    ///
+    /// [`init_model()`]: AsyncFactoryComponent::init_model
+    pub fn values(&self) -> impl Iterator<Item = Option<&C>> + ExactSizeIterator + FusedIterator {
+        self.inner.values().map(|entry| entry.handle.data.get())
+    }
+
+    /// Returns an iterator over the keys of the hash map.
+    pub fn keys(&self) -> impl Iterator<Item = &K> + ExactSizeIterator + FusedIterator {
+        self.inner.keys()
+    }
+
+    /// Clears the map, removing all factory components.
+    pub fn clear(&mut self) {
+        for (_, entry) in self.inner.drain() {
+            self.widget.factory_remove(&entry.handle.returned_widget);
+        }
+    }
+}
+
+impl<K, C, S> AsyncFactoryHashMap<K, C, S>
+where
+    C: AsyncFactoryComponent,
+    <C::ParentWidget as FactoryView>::ReturnedWidget: Clone,
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Send a message to one of the elements.
+    pub fn send(&self, key: &K, msg: C::Input) {
+        self.inner[key].handle.input.emit(msg);
+    }
+
+    /// Returns true if the map contains an element for `key`.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Returns the index of an element.
+    pub fn index_of(&self, key: &K) -> Option<DynamicIndex> {
+        self.inner.get(key).map(|entry| entry.index.clone())
+    }
+
+    /// Tries to get an immutable reference to
+    /// the model of one element.
+    ///
+    /// Returns [`None`] if `key` is invalid or
+    /// the [`init_model()`] future of the element is still running.
+    ///
+    /// [`init_model()`]: AsyncFactoryComponent::init_model
+    pub fn get(&self, key: &K) -> Option<&C> {
+        self.inner
+            .get(key)
+            .and_then(|entry| entry.handle.data.get())
+    }
+
+    /// Tries to get a mutable reference to
+    /// the model of one element.
+    ///
+    /// The element is updated when the guard is dropped.
+    /// Returns [`None`] if `key` is invalid or
+    /// the [`init_model()`] future of the element is still running.
+    ///
+    /// [`init_model()`]: AsyncFactoryComponent::init_model
+    pub fn get_mut(&mut self, key: &K) -> Option<AsyncFactoryElementGuard<'_, C>> {
+        let entry = self.inner.get_mut(key)?;
+        entry.handle.data.get_mut()?;
+        Some(AsyncFactoryElementGuard {
+            inner: &mut entry.handle,
+        })
+    }
+
+    /// Inserts a new factory component into the map and appends its widget.
+    ///
+    /// The loading widgets of the component are shown until its
+    /// [`init_model()`] future completes.
+    ///
+    /// If the map did have this key present, the old element is removed and returned,
+    /// if it was initialized.
+    ///
+    /// [`init_model()`]: AsyncFactoryComponent::init_model
+    pub fn insert(&mut self, key: K, init: C::Init) -> Option<C> {
+        let existing = self.remove(&key);
+
+        let index = DynamicIndex::new(self.inner.len());
+        let builder = AsyncFactoryBuilder::new(init);
+
+        let position = C::position(index.current_index());
+        let returned_widget = self
+            .widget
+            .factory_append(builder.root_widget.clone(), &position);
+
+        let handle = builder.launch(
+            &index,
+            returned_widget,
+            &self.parent_sender,
+            C::forward_to_parent,
+        );
+
+        assert!(self.inner.insert(key, Entry { index, handle }).is_none());
+
+        existing
+    }
+
+    /// Removes a key from the map, returning the factory component at the key
+    /// if the key was previously in the map and the component was initialized.
+    ///
+    /// The indexes of the elements after it are shifted.
+    pub fn remove(&mut self, key: &K) -> Option<C> {
+        let entry = self.inner.remove(key)?;
+        self.widget.factory_remove(&entry.handle.returned_widget);
+
+        let removed_index = entry.index.current_index();
+        for other in self.inner.values() {
+            if other.index.current_index() > removed_index {
+                other.index.decrement();
+                let position = C::position(other.index.current_index());
+                self.widget
+                    .factory_update_position(&other.handle.returned_widget, &position);
+            }
+        }
+
+        entry.handle.data.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::AsyncFactoryHashMap;
+    use crate::factory::{AsyncFactoryComponent, AsyncFactorySender, DynamicIndex, FactoryView};
+    use crate::loading_widgets::LoadingWidgets;
+    use crate::{gtk, RelmContainerExt, RelmIterChildrenExt};
+
+    use gtk::prelude::WidgetExt;
+
+    #[derive(Debug)]
+    struct Row(u8);
+
+    #[async_trait::async_trait(?Send)]
+    impl AsyncFactoryComponent for Row {
+        type ParentWidget = gtk::Box;
+        type ParentInput = ();
+        type CommandOutput = ();
+        type Input = ();
+        type Output = ();
+        /// The value and a gate that `init_model` waits for until it's closed.
+        type Init = (u8, flume::Receiver<()>);
+        type Root = gtk::Box;
+        type Widgets = ();
+
+        async fn init_model(
+            (value, gate): Self::Init,
+            _: &DynamicIndex,
+            _: AsyncFactorySender<Self>,
+        ) -> Self {
+            gate.recv_async().await.ok();
+            Self(value)
+        }
+
+        fn init_root() -> Self::Root {
+            gtk::Box::default()
+        }
+
+        fn init_loading_widgets(root: &mut Self::Root) -> Option<LoadingWidgets> {
+            let label = gtk::Label::new(Some("Loading"));
+            root.container_add(&label);
+            Some(LoadingWidgets::new(root, label))
+        }
+
+        fn init_widgets(
+            &mut self,
+            _: &DynamicIndex,
+            root: &Self::Root,
+            _: &<Self::ParentWidget as FactoryView>::ReturnedWidget,
+            _: AsyncFactorySender<Self>,
+        ) {
+            root.set_widget_name(&self.0.to_string());
+        }
+    }
+
+    fn settle() {
+        let context = gtk::glib::MainContext::default();
+        while context.iteration(false) {}
+    }
+
+    fn widget_names(factory: &AsyncFactoryHashMap<&str, Row>) -> Vec<String> {
+        factory
+            .widget()
+            .iter_children()
+            .map(|child| child.widget_name().to_string())
+            .collect()
+    }
+
+    /// Returns whether the loading widgets of each element are shown.
+    fn loading(factory: &AsyncFactoryHashMap<&str, Row>) -> Vec<bool> {
+        factory
+            .widget()
+            .iter_children()
+            .map(|child| child.first_child().is_some())
+            .collect()
+    }
+
+    #[gtk::test]
+    fn loading_elements() {
+        let (sender, _receiver) = crate::channel();
+        let mut factory = AsyncFactoryHashMap::<&str, Row>::new(gtk::Box::default(), &sender);
+        let (release, gate) = flume::unbounded();
+
+        factory.insert("a", (1, gate));
+        settle();
+
+        assert!(factory.contains_key(&"a"));
+        assert!(factory.get(&"a").is_none());
+        assert!(factory.get_mut(&"a").is_none());
+        assert!(factory.values().all(|row| row.is_none()));
+        assert_eq!(loading(&factory), [true]);
+
+        drop(release);
+        settle();
+
+        assert_eq!(factory.get(&"a").map(|row| row.0), Some(1));
+        assert!(factory.get_mut(&"a").is_some());
+        assert_eq!(loading(&factory), [false]);
+        assert_eq!(widget_names(&factory), ["1"]);
+    }
+
+    #[gtk::test]
+    fn remove_shifts_indexes() {
+        let (sender, _receiver) = crate::channel();
+        let mut factory = AsyncFactoryHashMap::<&str, Row>::new(gtk::Box::default(), &sender);
+        let (_, gate) = flume::unbounded();
+
+        for (value, key) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            factory.insert(key, (value as u8, gate.clone()));
+        }
+        settle();
+        assert_eq!(widget_names(&factory), ["0", "1", "2", "3"]);
+
+        let indexes: Vec<DynamicIndex> = ["a", "c", "d"]
+            .iter()
+            .map(|key| factory.index_of(key).unwrap())
+            .collect();
+
+        assert_eq!(factory.remove(&"b").map(|row| row.0), Some(1));
+        assert!(factory.remove(&"b").is_none());
+        assert_eq!(factory.len(), 3);
+        assert_eq!(widget_names(&factory), ["0", "2", "3"]);
+
+        let current: Vec<usize> = indexes.iter().map(DynamicIndex::current_index).collect();
+        assert_eq!(current, [0, 1, 2]);
+
+        // New elements are appended after the shifted ones.
+        factory.insert("e", (4, gate));
+        settle();
+        assert_eq!(factory.index_of(&"e").unwrap().current_index(), 3);
+        assert_eq!(widget_names(&factory), ["0", "2", "3", "4"]);
+    }
+}
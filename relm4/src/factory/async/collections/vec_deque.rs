@@ -6,6 +6,7 @@ use crate::factory::r#async::AsyncFactoryBuilder;
 use crate::factory::{DynamicIndex, FactoryView};
 
 use super::{ModelStateValue, RenderedState};
+use crate::factory::diff::{diff, Change};
 
 use std::collections::hash_map::DefaultHasher;
 use std::collections::VecDeque;
@@ -449,63 +450,58 @@ where
     ///
     /// Also, only modified elements will be updated.
     fn render_changes(&mut self) {
-        let mut first_position_change_idx = None;
+        let changes = diff(
+            self.rendered_state.iter().map(|r| r.uid),
+            self.model_state.iter().map(|s| s.uid),
+        );
 
         let components = &mut self.components;
-        let rendered_state = &mut self.rendered_state;
-        for (index, state) in self.model_state.iter().enumerate() {
-            if state.uid == rendered_state.front().map(|r| r.uid).unwrap_or_default() {
-                // Remove item from previously rendered list
-                rendered_state.pop_front();
-
-                if state.changed {
-                    // Update component
-                    components[index].state_change_notify();
-                }
-            } else if let Some(rendered_index) =
-                rendered_state.iter().position(|r| r.uid == state.uid)
-            {
-                if first_position_change_idx.is_none() {
-                    first_position_change_idx = Some(index);
-                }
-
-                // Remove item from previously rendered list
-                rendered_state.remove(rendered_index);
-
-                // Detach and re-attach item
-                let widget = components[index].returned_widget().unwrap();
-                if index == 0 {
-                    self.widget.factory_move_start(widget);
-                } else {
-                    let previous_widget = components[index - 1].returned_widget().unwrap();
-                    self.widget.factory_move_after(widget, previous_widget);
+        for (index, change) in changes.into_iter().enumerate() {
+            let state = &self.model_state[index];
+            let update_position = match change {
+                Change::Keep { index_changed } => index_changed,
+                Change::Move => {
+                    // Detach and re-attach item
+                    let widget = components[index].returned_widget().unwrap();
+                    if index == 0 {
+                        self.widget.factory_move_start(widget);
+                    } else {
+                        let previous_widget = components[index - 1].returned_widget().unwrap();
+                        self.widget.factory_move_after(widget, previous_widget);
+                    }
+                    true
                 }
-
-                if state.changed {
-                    // Update component
-                    components[index].state_change_notify();
-                }
-            } else {
-                if first_position_change_idx.is_none() {
-                    first_position_change_idx = Some(index);
+                Change::Insert => {
+                    // The element doesn't exist yet
+                    let comp = &components[index];
+                    let insert_widget = comp.widget();
+                    let position = C::position(index);
+                    let returned_widget = if index == 0 {
+                        self.widget.factory_prepend(insert_widget, &position)
+                    } else {
+                        let previous_widget = components[index - 1].returned_widget().unwrap();
+                        self.widget
+                            .factory_insert_after(insert_widget, &position, previous_widget)
+                    };
+                    let component = components.remove(index).unwrap();
+                    let component = component
+                        .launch(&state.index, returned_widget, &self.parent_sender)
+                        .unwrap();
+                    components.insert(index, component);
+                    continue;
                 }
+            };
 
-                // The element doesn't exist yet
-                let insert_widget = components[index].widget();
+            let comp = &components[index];
+            if update_position {
                 let position = C::position(index);
-                let returned_widget = if index == 0 {
-                    self.widget.factory_prepend(insert_widget, &position)
-                } else {
-                    let previous_widget = components[index - 1].returned_widget().unwrap();
-                    self.widget
-                        .factory_insert_after(insert_widget, &position, previous_widget)
-                };
-                let component = components.remove(index).unwrap();
-                let dyn_index = &self.model_state[index].index;
-                let component = component
-                    .launch(dyn_index, returned_widget, &self.parent_sender)
-                    .unwrap();
-                components.insert(index, component);
+                self.widget
+                    .factory_update_position(comp.returned_widget().unwrap(), &position);
+            }
+
+            if state.changed {
+                // Update component
+                comp.state_change_notify();
             }
         }
 
@@ -529,14 +525,6 @@ where
                 }
             })
             .collect();
-
-        if let Some(change_index) = first_position_change_idx {
-            for (index, comp) in components.iter().enumerate().skip(change_index) {
-                let position = C::position(index);
-                self.widget
-                    .factory_update_position(comp.returned_widget().unwrap(), &position);
-            }
-        }
     }
 
     /// Returns the number of elements in the [`AsyncFactoryVecDeque`].
@@ -613,3 +601,89 @@ where
         self.components.iter().map(AsyncComponentStorage::get)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::AsyncFactoryVecDeque;
+    use crate::factory::{AsyncFactoryComponent, AsyncFactorySender, DynamicIndex, FactoryView};
+    use crate::{gtk, RelmIterChildrenExt};
+
+    use gtk::prelude::WidgetExt;
+
+    #[derive(Debug)]
+    struct Row(u8);
+
+    #[async_trait::async_trait(?Send)]
+    impl AsyncFactoryComponent for Row {
+        type ParentWidget = gtk::Box;
+        type ParentInput = ();
+        type CommandOutput = ();
+        type Input = ();
+        type Output = ();
+        type Init = u8;
+        type Root = gtk::Label;
+        type Widgets = ();
+
+        async fn init_model(value: u8, _: &DynamicIndex, _: AsyncFactorySender<Self>) -> Self {
+            Self(value)
+        }
+
+        fn init_root() -> Self::Root {
+            gtk::Label::default()
+        }
+
+        fn init_widgets(
+            &mut self,
+            _: &DynamicIndex,
+            root: &Self::Root,
+            _: &<Self::ParentWidget as FactoryView>::ReturnedWidget,
+            _: AsyncFactorySender<Self>,
+        ) {
+            root.set_widget_name(&self.0.to_string());
+        }
+    }
+
+    fn settle() {
+        let context = gtk::glib::MainContext::default();
+        while context.iteration(false) {}
+    }
+
+    fn widget_names(factory: &AsyncFactoryVecDeque<Row>) -> Vec<String> {
+        factory
+            .widget()
+            .iter_children()
+            .map(|child| child.widget_name().to_string())
+            .collect()
+    }
+
+    #[gtk::test]
+    fn reorder() {
+        let (sender, _receiver) = crate::channel();
+        let mut factory = AsyncFactoryVecDeque::<Row>::new(gtk::Box::default(), &sender);
+
+        let indexes: Vec<DynamicIndex> = {
+            let mut guard = factory.guard();
+            (0..5).map(|value| guard.push_back(value)).collect()
+        };
+        settle();
+        assert_eq!(widget_names(&factory), ["0", "1", "2", "3", "4"]);
+
+        {
+            let mut guard = factory.guard();
+            guard.move_front(3);
+            guard.swap(1, 4);
+            guard.remove(2);
+            guard.insert(1, 5);
+        }
+        settle();
+
+        let values: Vec<u8> = factory.iter().map(|row| row.unwrap().0).collect();
+        assert_eq!(values, [3, 5, 4, 2, 0]);
+        assert_eq!(widget_names(&factory), ["3", "5", "4", "2", "0"]);
+
+        assert_eq!(indexes[3].current_index(), 0);
+        assert_eq!(indexes[4].current_index(), 2);
+        assert_eq!(indexes[2].current_index(), 3);
+        assert_eq!(indexes[0].current_index(), 4);
+    }
+}
@@ -9,5 +9,7 @@ use builder::AsyncFactoryBuilder;
 use future_data::AsyncData;
 use handle::AsyncFactoryHandle;
 
-pub use collections::{AsyncFactoryVecDeque, AsyncFactoryVecDequeGuard};
+pub use collections::{
+    AsyncFactoryElementGuard, AsyncFactoryHashMap, AsyncFactoryVecDeque, AsyncFactoryVecDequeGuard,
+};
 pub use traits::AsyncFactoryComponent;
@@ -3,7 +3,7 @@
 use crate::channel::AsyncFactorySender;
 use crate::factory::{AsyncPosition, DynamicIndex, FactoryView};
 use crate::loading_widgets::LoadingWidgets;
-use crate::Sender;
+use crate::{ChannelOptions, Sender};
 
 use std::fmt::Debug;
 
@@ -112,6 +112,29 @@ pub trait AsyncFactoryComponent:
         self.update_view(widgets, sender);
     }
 
+    /// Whether the view should only be updated once for all messages
+    /// that are handled in one iteration of the main loop.
+    ///
+    /// See [`FactoryComponent::batch_view_updates`](crate::factory::FactoryComponent::batch_view_updates)
+    /// for details.
+    fn batch_view_updates() -> bool {
+        false
+    }
+
+    /// Options for the input channel of each factory component.
+    ///
+    /// Use this to bound the channel with an [`OverflowPolicy`](crate::OverflowPolicy)
+    /// or to prioritize messages, similar to
+    /// [`ComponentBuilder::bounded`](crate::ComponentBuilder::bounded) and
+    /// [`ComponentBuilder::prioritize`](crate::ComponentBuilder::prioritize).
+    ///
+    /// Returns an unbounded channel without priority messages by default.
+    /// Factory components run on the main thread, so [`OverflowPolicy::Block`](crate::OverflowPolicy::Block)
+    /// isn't allowed.
+    fn input_channel() -> ChannelOptions<Self::Input> {
+        ChannelOptions::default()
+    }
+
     /// Last method called before a factory component is shut down.
     #[allow(unused)]
     fn shutdown(&mut self, widgets: &mut Self::Widgets, output: Sender<Self::Output>) {}
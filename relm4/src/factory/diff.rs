@@ -0,0 +1,126 @@
+//! Keyed diff between the rendered and the current order of a factory.
+
+use std::collections::HashMap;
+
+/// What needs to be done to render an element at its current position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Change {
+    /// The widget stays where it is.
+    ///
+    /// Its position needs an update if its index changed.
+    Keep {
+        /// Whether the index differs from the rendered index.
+        index_changed: bool,
+    },
+    /// The widget has to be moved after the widget of the previous element.
+    Move,
+    /// The element is new.
+    Insert,
+}
+
+/// Compares the rendered order of unique IDs with their current order
+/// and returns the change for each current element.
+///
+/// As few elements as possible are moved: all elements that are part
+/// of the longest subsequence that kept its relative order stay in place.
+/// Moved and inserted elements must be placed after the previous element,
+/// going from front to back.
+///
+/// Runs in `O(n log n)`.
+pub(super) fn diff(
+    rendered: impl Iterator<Item = usize>,
+    current: impl Iterator<Item = usize>,
+) -> Vec<Change> {
+    let rendered_indices: HashMap<usize, usize> = rendered
+        .enumerate()
+        .map(|(index, uid)| (uid, index))
+        .collect();
+    let old_indices: Vec<Option<usize>> = current
+        .map(|uid| rendered_indices.get(&uid).copied())
+        .collect();
+
+    let stable = longest_increasing(&old_indices);
+
+    old_indices
+        .iter()
+        .zip(stable)
+        .enumerate()
+        .map(|(index, (old_index, stable))| match old_index {
+            None => Change::Insert,
+            Some(old_index) if stable => Change::Keep {
+                index_changed: *old_index != index,
+            },
+            Some(_) => Change::Move,
+        })
+        .collect()
+}
+
+/// Marks the elements of the longest strictly increasing subsequence,
+/// ignoring missing values.
+fn longest_increasing(values: &[Option<usize>]) -> Vec<bool> {
+    // The value and position of the smallest last element
+    // of all increasing subsequences of length `k + 1`.
+    let mut tails: Vec<(usize, usize)> = Vec::new();
+    let mut predecessors: Vec<Option<usize>> = vec![None; values.len()];
+
+    for (position, value) in values.iter().enumerate() {
+        let Some(value) = *value else {
+            continue;
+        };
+
+        let length = tails.partition_point(|(tail, _)| *tail < value);
+        if length > 0 {
+            predecessors[position] = Some(tails[length - 1].1);
+        }
+        if length == tails.len() {
+            tails.push((value, position));
+        } else {
+            tails[length] = (value, position);
+        }
+    }
+
+    let mut stable = vec![false; values.len()];
+    let mut next = tails.last().map(|(_, position)| *position);
+    while let Some(position) = next {
+        stable[position] = true;
+        next = predecessors[position];
+    }
+    stable
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff, Change};
+
+    const KEEP: Change = Change::Keep {
+        index_changed: false,
+    };
+    const SHIFT: Change = Change::Keep {
+        index_changed: true,
+    };
+
+    #[test]
+    fn unchanged() {
+        assert_eq!(diff(1..4, 1..4), [KEEP, KEEP, KEEP]);
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        let changes = diff([1, 2, 3].into_iter(), [4, 1, 3].into_iter());
+        assert_eq!(changes, [Change::Insert, SHIFT, KEEP]);
+    }
+
+    #[test]
+    fn move_single_element() {
+        // Moving the first element to the back only moves one widget.
+        let changes = diff([1, 2, 3, 4].into_iter(), [2, 3, 4, 1].into_iter());
+        assert_eq!(changes, [SHIFT, SHIFT, SHIFT, Change::Move]);
+    }
+
+    #[test]
+    fn reverse() {
+        let changes = diff(1..=100, (1..=100).rev());
+        let moved = changes.iter().filter(|c| **c == Change::Move).count();
+        assert_eq!(moved, 99);
+    }
+}
@@ -1,6 +1,7 @@
 //! Defines traits and data types to generate widgets from collections efficiently.
 
 mod dynamic_index;
+mod tree_index;
 
 /// Traits and implementations used for factories to interact with widgets.
 pub mod widgets;
@@ -15,12 +16,19 @@ mod sync;
 mod data_guard;
 use data_guard::DataGuard;
 
-pub use r#async::{AsyncFactoryComponent, AsyncFactoryVecDeque, AsyncFactoryVecDequeGuard};
+/// Keyed diff used to render reordered factories.
+mod diff;
+
+pub use r#async::{
+    AsyncFactoryComponent, AsyncFactoryElementGuard, AsyncFactoryHashMap, AsyncFactoryVecDeque,
+    AsyncFactoryVecDequeGuard,
+};
 pub use sync::{
-    CloneableFactoryComponent, FactoryComponent, FactoryHashMap, FactoryVecDeque,
-    FactoryVecDequeGuard,
+    CloneableFactoryComponent, FactoryBTreeMap, FactoryComponent, FactoryElementGuard,
+    FactoryHashMap, FactoryTree, FactoryVecDeque, FactoryVecDequeGuard, TreeFactoryComponent,
 };
 
 pub use crate::channel::{AsyncFactorySender, FactorySender};
 pub use dynamic_index::DynamicIndex;
+pub use tree_index::TreeIndex;
 pub use widgets::traits::*;
@@ -1,6 +1,7 @@
 use super::{FactoryComponent, FactoryHandle};
 
 use crate::factory::{DataGuard, FactorySender, FactoryView};
+use crate::runtime_util::{batch, Queued, ViewUpdate};
 use crate::shutdown::ShutdownSender;
 use crate::{shutdown, GuardedReceiver, Receiver, Sender};
 
@@ -14,6 +15,7 @@ pub(super) struct FactoryBuilder<C: FactoryComponent> {
     pub(super) root_widget: C::Root,
     pub(super) component_sender: FactorySender<C>,
     pub(super) input_receiver: Receiver<C::Input>,
+    pub(super) priority_receiver: Option<Receiver<C::Input>>,
     pub(super) output_receiver: Receiver<C::Output>,
     pub(super) cmd_receiver: Receiver<C::CommandOutput>,
     pub(super) shutdown_notifier: ShutdownSender,
@@ -22,7 +24,8 @@ pub(super) struct FactoryBuilder<C: FactoryComponent> {
 impl<C: FactoryComponent> FactoryBuilder<C> {
     pub(super) fn new(index: &C::Index, init: C::Init) -> Self {
         // Used for all events to be processed by this component's internal service.
-        let (input_sender, input_receiver) = crate::channel::<C::Input>();
+        let (input_sender, input_receiver, priority_receiver) =
+            C::input_channel().main_thread_channel();
 
         // Used by this component to send events to be handled externally by the caller.
         let (output_sender, output_receiver) = crate::channel::<C::Output>();
@@ -45,6 +48,7 @@ impl<C: FactoryComponent> FactoryBuilder<C> {
             root_widget,
             component_sender,
             input_receiver,
+            priority_receiver,
             output_receiver,
             cmd_receiver,
             shutdown_notifier,
@@ -67,12 +71,13 @@ impl<C: FactoryComponent> FactoryBuilder<C> {
             root_widget,
             component_sender,
             input_receiver,
+            priority_receiver,
             output_receiver,
             cmd_receiver,
             shutdown_notifier,
         } = self;
 
-        let forward_sender = parent_sender.0.clone();
+        let forward_sender = parent_sender.clone();
         crate::spawn_local(async move {
             while let Some(msg) = output_receiver.recv().await {
                 if let Some(new_msg) = transform(msg) {
@@ -108,34 +113,55 @@ impl<C: FactoryComponent> FactoryBuilder<C> {
                 async move {
                     let mut notifier = GuardedReceiver::new(notifier_receiver);
                     let mut cmd = GuardedReceiver::new(cmd_receiver);
-                    let mut input = GuardedReceiver::new(input_receiver);
+                    let mut input =
+                        GuardedReceiver::with_priority(input_receiver, priority_receiver);
+                    let mut view_update = ViewUpdate::new(C::batch_view_updates());
                     loop {
                         futures::select!(
                             // Performs the model update, checking if the update requested a command.
                             // Runs that command asynchronously in the background using tokio.
                             message = input => {
-                                let span = info_span!(
-                                    "update_with_view",
-                                    input=?message,
-                                    component=any::type_name::<C>(),
-                                    id=model.id(),
-                                );
-                                let _enter = span.enter();
-
-                                model.update_with_view(&mut widgets, message, component_sender.clone());
+                                if view_update.is_batched() {
+                                    for message in batch(Queued::Input(message), &mut input, &mut cmd) {
+                                        update_batched(&mut **model, message, &component_sender);
+                                    }
+                                    view_update.schedule();
+                                } else {
+                                    let span = info_span!(
+                                        "update_with_view",
+                                        input=?message,
+                                        component=any::type_name::<C>(),
+                                        id=model.id(),
+                                    );
+                                    let _enter = span.enter();
+
+                                    model.update_with_view(&mut widgets, message, component_sender.clone());
+                                }
                             }
 
                             // Handles responses from a command.
                             message = cmd => {
-                                let span = info_span!(
-                                    "update_cmd_with_view",
-                                    cmd_output=?message,
-                                    component=any::type_name::<C>(),
-                                    id=model.id(),
-                                );
-                                let _enter = span.enter();
-
-                                model.update_cmd_with_view(&mut widgets, message, component_sender.clone());
+                                if view_update.is_batched() {
+                                    for message in batch(Queued::Command(message), &mut input, &mut cmd) {
+                                        update_batched(&mut **model, message, &component_sender);
+                                    }
+                                    view_update.schedule();
+                                } else {
+                                    let span = info_span!(
+                                        "update_cmd_with_view",
+                                        cmd_output=?message,
+                                        component=any::type_name::<C>(),
+                                        id=model.id(),
+                                    );
+                                    let _enter = span.enter();
+
+                                    model.update_cmd_with_view(&mut widgets, message, component_sender.clone());
+                                }
+                            }
+
+                            // Updates the view once after a batch of messages was handled.
+                            _ = view_update => {
+                                model.update_view(&mut widgets, component_sender.clone());
                             }
 
                             // Triggered when the model and view have been updated externally.
@@ -159,3 +185,18 @@ impl<C: FactoryComponent> FactoryBuilder<C> {
         }
     }
 }
+
+/// Updates the model with a message of a batch without updating the view.
+fn update_batched<C: FactoryComponent>(
+    model: &mut C,
+    message: Queued<C::Input, C::CommandOutput>,
+    sender: &FactorySender<C>,
+) {
+    let span = message.span::<C>(&model.id());
+    let _enter = span.enter();
+
+    match message {
+        Queued::Input(message) => model.update(message, sender.clone()),
+        Queued::Command(message) => model.update_cmd(message, sender.clone()),
+    }
+}
@@ -0,0 +1,421 @@
+use crate::Sender;
+
+use crate::factory::sync::builder::FactoryBuilder;
+use crate::factory::sync::handle::FactoryHandle;
+use crate::factory::{CloneableFactoryComponent, FactoryComponent, FactoryView};
+
+use super::FactoryElementGuard;
+
+use std::iter::FusedIterator;
+use std::ops::{self, Bound, RangeBounds};
+
+/// A container similar to [`BTreeMap`](std::collections::BTreeMap) that can be used to store
+/// values of type [`FactoryComponent`].
+///
+/// The widgets are ordered by their keys.
+/// Unlike [`BTreeMap`](std::collections::BTreeMap), elements are stored sorted
+/// in a contiguous buffer, so looking up keys and positions takes `O(log n)`
+/// and accessing elements by their position takes `O(1)`,
+/// but [`insert`](Self::insert) and [`remove`](Self::remove) take `O(n)`
+/// because the following elements are shifted.
+#[derive(Debug)]
+pub struct FactoryBTreeMap<K, C: FactoryComponent> {
+    widget: C::ParentWidget,
+    parent_sender: Sender<C::ParentInput>,
+    inner: Vec<(K, FactoryHandle<C>)>,
+}
+
+impl<K, C> Drop for FactoryBTreeMap<K, C>
+where
+    C: FactoryComponent,
+{
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<K, C> ops::Index<&K> for FactoryBTreeMap<K, C>
+where
+    C: FactoryComponent<Index = K>,
+    K: Ord,
+{
+    type Output = C;
+
+    fn index(&self, key: &K) -> &Self::Output {
+        self.get(key).expect("Called `get` on an invalid key")
+    }
+}
+
+impl<K, C> FactoryBTreeMap<K, C>
+where
+    C: FactoryComponent,
+{
+    /// Creates a new [`FactoryBTreeMap`].
+    #[must_use]
+    pub fn new(widget: C::ParentWidget, parent_sender: &Sender<C::ParentInput>) -> Self {
+        Self {
+            widget,
+            parent_sender: parent_sender.clone(),
+            inner: Vec::new(),
+        }
+    }
+
+    /// Returns the number of elements in the [`FactoryBTreeMap`].
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the [`FactoryBTreeMap`] is empty.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Send clone of a message to all of the elements.
+    pub fn broadcast(&self, msg: C::Input)
+    where
+        C::Input: Clone,
+    {
+        self.inner
+            .iter()
+            .for_each(|(_, c)| c.input.emit(msg.clone()));
+    }
+
+    /// Returns the widget all components are attached to.
+    pub const fn widget(&self) -> &C::ParentWidget {
+        &self.widget
+    }
+
+    /// Returns the key and the model of the element at `index` in key order.
+    pub fn get_index(&self, index: usize) -> Option<(&K, &C)> {
+        self.inner.get(index).map(|(k, c)| (k, c.data.get()))
+    }
+
+    /// Returns the first element in key order.
+    pub fn first(&self) -> Option<(&K, &C)> {
+        self.get_index(0)
+    }
+
+    /// Returns the last element in key order.
+    pub fn last(&self) -> Option<(&K, &C)> {
+        self.inner.last().map(|(k, c)| (k, c.data.get()))
+    }
+
+    /// An iterator visiting all key-value pairs in key order.
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = (&K, &C)> + DoubleEndedIterator + ExactSizeIterator + FusedIterator
+    {
+        self.inner.iter().map(|(k, c)| (k, c.data.get()))
+    }
+
+    /// Returns an iterator over the factory components in key order.
+    pub fn values(
+        &self,
+    ) -> impl Iterator<Item = &C> + DoubleEndedIterator + ExactSizeIterator + FusedIterator {
+        self.inner.iter().map(|(_, c)| c.data.get())
+    }
+
+    /// Returns an iterator over the keys in order.
+    pub fn keys(
+        &self,
+    ) -> impl Iterator<Item = &K> + DoubleEndedIterator + ExactSizeIterator + FusedIterator {
+        self.inner.iter().map(|(k, _)| k)
+    }
+
+    /// Clears the map, removing all factory components.
+    pub fn clear(&mut self) {
+        for (_, handle) in self.inner.drain(..) {
+            self.widget.factory_remove(&handle.returned_widget);
+        }
+    }
+}
+
+impl<K, C> FactoryBTreeMap<K, C>
+where
+    C: FactoryComponent<Index = K>,
+    K: Ord,
+{
+    /// Creates a [`FactoryBTreeMap`] from a [`Vec`].
+    ///
+    /// This takes `O(n²)` if the elements aren't sorted by their keys.
+    pub fn from_vec(
+        component_vec: Vec<(K, C::Init)>,
+        widget: C::ParentWidget,
+        parent_sender: &Sender<C::ParentInput>,
+    ) -> Self {
+        let mut output = Self::new(widget, parent_sender);
+        for (key, init) in component_vec {
+            output.insert(key, init);
+        }
+        output
+    }
+
+    /// Send a message to one of the elements.
+    pub fn send(&self, key: &K, msg: C::Input) {
+        let index = self.index_of(key).expect("Called `send` on an invalid key");
+        self.inner[index].1.input.emit(msg);
+    }
+
+    /// Returns the position of `key` in key order.
+    pub fn index_of(&self, key: &K) -> Option<usize> {
+        self.search(key).ok()
+    }
+
+    /// Returns true if the map contains an element for `key`.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.search(key).is_ok()
+    }
+
+    /// Tries to get an immutable reference to
+    /// the model of one element.
+    ///
+    /// Returns [`None`] if `key` is invalid.
+    pub fn get(&self, key: &K) -> Option<&C> {
+        self.index_of(key)
+            .map(|index| self.inner[index].1.data.get())
+    }
+
+    /// Tries to get a mutable reference to
+    /// the model of one element.
+    ///
+    /// The element is updated when the guard is dropped.
+    /// Returns [`None`] if `key` is invalid.
+    pub fn get_mut(&mut self, key: &K) -> Option<FactoryElementGuard<'_, C>> {
+        self.index_of(key).map(|index| FactoryElementGuard {
+            inner: &mut self.inner[index].1,
+        })
+    }
+
+    /// An iterator over the elements with keys in `range`, in key order.
+    pub fn range<R>(
+        &self,
+        range: R,
+    ) -> impl Iterator<Item = (&K, &C)> + DoubleEndedIterator + ExactSizeIterator + FusedIterator
+    where
+        R: RangeBounds<K>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(start) => self.inner.partition_point(|(k, _)| k < start),
+            Bound::Excluded(start) => self.inner.partition_point(|(k, _)| k <= start),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => self.inner.partition_point(|(k, _)| k <= end),
+            Bound::Excluded(end) => self.inner.partition_point(|(k, _)| k < end),
+            Bound::Unbounded => self.inner.len(),
+        };
+
+        self.inner[start..end.max(start)]
+            .iter()
+            .map(|(k, c)| (k, c.data.get()))
+    }
+
+    /// Inserts a new factory component into the map.
+    ///
+    /// The widget is placed according to the order of `key`.
+    ///
+    /// If the map did not have this key present, None is returned.
+    ///
+    /// If the map did have this key present, the value is updated, and the old value is returned.
+    pub fn insert(&mut self, key: K, init: C::Init) -> Option<C> {
+        let existing = self.remove(&key);
+        let index = self.search(&key).unwrap_err();
+
+        let builder = FactoryBuilder::new(&key, init);
+
+        let position = C::position(&builder.data, &key);
+        let returned_widget = if let Some((_, previous)) = index
+            .checked_sub(1)
+            .and_then(|previous| self.inner.get(previous))
+        {
+            self.widget.factory_insert_after(
+                builder.root_widget.clone(),
+                &position,
+                &previous.returned_widget,
+            )
+        } else {
+            self.widget
+                .factory_prepend(builder.root_widget.clone(), &position)
+        };
+
+        let component = builder.launch(
+            &key,
+            returned_widget,
+            &self.parent_sender,
+            C::forward_to_parent,
+        );
+
+        self.inner.insert(index, (key, component));
+
+        existing
+    }
+
+    /// Removes a key from the map, returning the factory component at the key if the key was previously in the map.
+    pub fn remove(&mut self, key: &K) -> Option<C> {
+        let index = self.index_of(key)?;
+        let (_, handle) = self.inner.remove(index);
+        self.widget.factory_remove(&handle.returned_widget);
+        Some(handle.data.into_inner())
+    }
+
+    fn search(&self, key: &K) -> Result<usize, usize> {
+        self.inner.binary_search_by(|(k, _)| k.cmp(key))
+    }
+}
+
+/// Implements the Clone Trait for [`FactoryBTreeMap`] if the component implements [`CloneableFactoryComponent`].
+impl<K, C> Clone for FactoryBTreeMap<K, C>
+where
+    C: CloneableFactoryComponent,
+    K: Clone + Ord,
+    C: FactoryComponent<Index = K>,
+{
+    fn clone(&self) -> Self {
+        let mut clone = FactoryBTreeMap::new(self.widget.clone(), &self.parent_sender);
+        for (k, item) in self.iter() {
+            clone.insert(k.clone(), C::get_init(item));
+        }
+        clone
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FactoryBTreeMap;
+    use crate::factory::{FactoryComponent, FactorySender, FactoryView};
+    use crate::{gtk, RelmIterChildrenExt};
+
+    use gtk::prelude::WidgetExt;
+
+    use std::ops::Bound;
+
+    #[derive(Debug)]
+    struct Row(&'static str);
+
+    impl FactoryComponent for Row {
+        type ParentWidget = gtk::Box;
+        type ParentInput = ();
+        type CommandOutput = ();
+        type Input = ();
+        type Output = ();
+        type Init = &'static str;
+        type Root = gtk::Label;
+        type Widgets = ();
+        type Index = u8;
+
+        fn init_model(value: &'static str, _: &u8, _: FactorySender<Self>) -> Self {
+            Self(value)
+        }
+
+        fn init_root(&self) -> Self::Root {
+            gtk::Label::default()
+        }
+
+        fn init_widgets(
+            &mut self,
+            _: &u8,
+            root: &Self::Root,
+            _: &<Self::ParentWidget as FactoryView>::ReturnedWidget,
+            _: FactorySender<Self>,
+        ) {
+            root.set_widget_name(self.0);
+        }
+    }
+
+    fn factory(entries: &[(u8, &'static str)]) -> FactoryBTreeMap<u8, Row> {
+        let (sender, _) = crate::channel();
+        FactoryBTreeMap::from_vec(entries.to_vec(), gtk::Box::default(), &sender)
+    }
+
+    fn values<'a>(entries: impl Iterator<Item = (&'a u8, &'a Row)>) -> Vec<(u8, &'static str)> {
+        entries.map(|(key, row)| (*key, row.0)).collect()
+    }
+
+    fn widget_names(factory: &FactoryBTreeMap<u8, Row>) -> Vec<String> {
+        factory
+            .widget()
+            .iter_children()
+            .map(|child| child.widget_name().to_string())
+            .collect()
+    }
+
+    #[gtk::test]
+    fn insert_in_key_order() {
+        let factory = factory(&[(3, "c"), (1, "a"), (4, "d"), (0, "z"), (2, "b")]);
+
+        assert_eq!(
+            values(factory.iter()),
+            [(0, "z"), (1, "a"), (2, "b"), (3, "c"), (4, "d")]
+        );
+        assert_eq!(widget_names(&factory), ["z", "a", "b", "c", "d"]);
+        assert_eq!(factory.first().map(|(key, _)| *key), Some(0));
+        assert_eq!(factory.last().map(|(key, _)| *key), Some(4));
+    }
+
+    #[gtk::test]
+    fn range() {
+        let factory = factory(&[(0, "a"), (2, "b"), (4, "c"), (6, "d")]);
+
+        assert_eq!(values(factory.range(2..=4)), [(2, "b"), (4, "c")]);
+        assert_eq!(values(factory.range(2..4)), [(2, "b")]);
+        assert_eq!(values(factory.range(1..5)), [(2, "b"), (4, "c")]);
+        assert_eq!(
+            values(factory.range((Bound::Excluded(2), Bound::Unbounded))),
+            [(4, "c"), (6, "d")]
+        );
+        assert_eq!(values(factory.range(..2)), [(0, "a")]);
+        assert_eq!(factory.range(..).len(), 4);
+        assert!(factory.range(7..).next().is_none());
+        assert!(factory
+            .range((Bound::Excluded(4), Bound::Excluded(6)))
+            .next()
+            .is_none());
+    }
+
+    #[gtk::test]
+    fn insert_replaces_existing_key() {
+        let mut factory = factory(&[(0, "a"), (1, "b"), (2, "c")]);
+
+        let previous = factory.insert(1, "x");
+        assert_eq!(previous.map(|row| row.0), Some("b"));
+        assert_eq!(factory.len(), 3);
+        assert_eq!(values(factory.iter()), [(0, "a"), (1, "x"), (2, "c")]);
+        assert_eq!(widget_names(&factory), ["a", "x", "c"]);
+
+        // The first element has no predecessor to insert the widget after.
+        factory.insert(0, "y");
+        assert_eq!(widget_names(&factory), ["y", "x", "c"]);
+    }
+
+    #[gtk::test]
+    fn remove() {
+        let mut factory = factory(&[(0, "a"), (1, "b"), (2, "c")]);
+
+        assert_eq!(factory.remove(&1).map(|row| row.0), Some("b"));
+        assert!(factory.remove(&1).is_none());
+        assert!(!factory.contains_key(&1));
+        assert_eq!(values(factory.iter()), [(0, "a"), (2, "c")]);
+        assert_eq!(widget_names(&factory), ["a", "c"]);
+
+        factory.clear();
+        assert!(factory.is_empty());
+        assert!(widget_names(&factory).is_empty());
+    }
+
+    #[gtk::test]
+    fn positions() {
+        let factory = factory(&[(5, "a"), (1, "b"), (3, "c")]);
+
+        assert_eq!(factory.index_of(&1), Some(0));
+        assert_eq!(factory.index_of(&5), Some(2));
+        assert_eq!(factory.index_of(&2), None);
+
+        assert_eq!(
+            factory.get_index(1).map(|(key, row)| (*key, row.0)),
+            Some((3, "c"))
+        );
+        assert!(factory.get_index(3).is_none());
+        assert_eq!(factory.get(&5).map(|row| row.0), Some("a"));
+        assert_eq!(factory[&3].0, "c");
+    }
+}
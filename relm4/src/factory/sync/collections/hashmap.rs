@@ -10,13 +10,16 @@ use std::hash::{BuildHasher, Hash};
 use std::iter::FusedIterator;
 use std::ops;
 
+/// A mutable reference to the model of an element in a keyed factory.
+///
+/// The element is updated when the guard goes out of scope.
 #[derive(Debug)]
 #[must_use]
 pub struct FactoryElementGuard<'a, C>
 where
     C: FactoryComponent,
 {
-    inner: &'a mut FactoryHandle<C>,
+    pub(super) inner: &'a mut FactoryHandle<C>,
 }
 
 impl<'a, C> ops::Deref for FactoryElementGuard<'a, C>
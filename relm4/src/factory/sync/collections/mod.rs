@@ -1,8 +1,12 @@
 //! Containers similar to [`std::collections`] that can be used to store factory data.
 
+mod btree_map;
 mod hashmap;
+mod tree;
 mod vec_deque;
-pub use hashmap::FactoryHashMap;
+pub use btree_map::FactoryBTreeMap;
+pub use hashmap::{FactoryElementGuard, FactoryHashMap};
+pub use tree::FactoryTree;
 pub use vec_deque::{FactoryVecDeque, FactoryVecDequeGuard};
 
 use crate::factory::DynamicIndex;
@@ -0,0 +1,441 @@
+use crate::Sender;
+
+use crate::factory::sync::builder::FactoryBuilder;
+use crate::factory::sync::handle::FactoryHandle;
+use crate::factory::sync::traits::TreeFactoryComponent;
+use crate::factory::{FactoryView, TreeIndex};
+
+use super::FactoryElementGuard;
+
+use gtk::prelude::WidgetExt;
+
+#[derive(Debug)]
+struct Node<C: TreeFactoryComponent> {
+    index: TreeIndex,
+    /// Declared before the handle, so descendants are shut down first.
+    children: Vec<Node<C>>,
+    handle: FactoryHandle<C>,
+    /// The container for the widgets of the children.
+    container: C::ParentWidget,
+}
+
+/// A container for hierarchical data like outlines, file trees or threaded comments
+/// that stores values of type [`TreeFactoryComponent`].
+///
+/// Each node provides a container for the widgets of its children,
+/// which can be collapsed with [`FactoryTree::set_expanded`].
+/// Nodes are addressed by their path, the indexes of the node and its ancestors
+/// among their siblings starting at the top level.
+/// Outputs of nodes are forwarded together with their [`TreeIndex`] with
+/// [`TreeFactoryComponent::forward_with_index`].
+#[derive(Debug)]
+pub struct FactoryTree<C: TreeFactoryComponent> {
+    widget: C::ParentWidget,
+    parent_sender: Sender<C::ParentInput>,
+    roots: Vec<Node<C>>,
+}
+
+impl<C: TreeFactoryComponent> Drop for FactoryTree<C> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<C: TreeFactoryComponent> FactoryTree<C> {
+    /// Creates a new [`FactoryTree`].
+    #[must_use]
+    pub fn new(widget: C::ParentWidget, parent_sender: &Sender<C::ParentInput>) -> Self {
+        Self {
+            widget,
+            parent_sender: parent_sender.clone(),
+            roots: Vec::new(),
+        }
+    }
+
+    /// Returns the widget the top-level nodes are attached to.
+    pub const fn widget(&self) -> &C::ParentWidget {
+        &self.widget
+    }
+
+    /// Returns the number of top-level nodes.
+    pub fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    /// Returns true if the tree has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// Returns the number of children of the node at `path`.
+    ///
+    /// Returns [`None`] if `path` is invalid.
+    pub fn child_count(&self, path: &[usize]) -> Option<usize> {
+        find(&self.roots, path).map(|node| node.children.len())
+    }
+
+    /// Returns the index of the node at `path`.
+    pub fn index(&self, path: &[usize]) -> Option<TreeIndex> {
+        find(&self.roots, path).map(|node| node.index.clone())
+    }
+
+    /// Tries to get an immutable reference to
+    /// the model of the node at `path`.
+    ///
+    /// Returns [`None`] if `path` is invalid.
+    pub fn get(&self, path: &[usize]) -> Option<&C> {
+        find(&self.roots, path).map(|node| node.handle.data.get())
+    }
+
+    /// Tries to get a mutable reference to
+    /// the model of the node at `path`.
+    ///
+    /// The node is updated when the guard is dropped.
+    /// Returns [`None`] if `path` is invalid.
+    pub fn get_mut(&mut self, path: &[usize]) -> Option<FactoryElementGuard<'_, C>> {
+        find_mut(&mut self.roots, path).map(|node| FactoryElementGuard {
+            inner: &mut node.handle,
+        })
+    }
+
+    /// Send a message to the node at `path`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `path` is invalid.
+    pub fn send(&self, path: &[usize], msg: C::Input) {
+        find(&self.roots, path)
+            .expect("Called `send` on an invalid path")
+            .handle
+            .input
+            .emit(msg);
+    }
+
+    /// Send clone of a message to all nodes.
+    pub fn broadcast(&self, msg: C::Input)
+    where
+        C::Input: Clone,
+    {
+        fn broadcast<C: TreeFactoryComponent>(nodes: &[Node<C>], msg: &C::Input)
+        where
+            C::Input: Clone,
+        {
+            for node in nodes {
+                node.handle.input.emit(msg.clone());
+                broadcast(&node.children, msg);
+            }
+        }
+
+        broadcast(&self.roots, &msg);
+    }
+
+    /// Shows or hides the children of the node at `path`.
+    ///
+    /// Returns `false` if `path` is invalid.
+    pub fn set_expanded(&self, path: &[usize], expanded: bool) -> bool {
+        if let Some(node) = find(&self.roots, path) {
+            node.container.set_visible(expanded);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns whether the children of the node at `path` are shown.
+    ///
+    /// Returns [`None`] if `path` is invalid.
+    pub fn is_expanded(&self, path: &[usize]) -> Option<bool> {
+        find(&self.roots, path).map(|node| node.container.is_visible())
+    }
+
+    /// Appends a node to the children of the node at `parent`.
+    ///
+    /// Use an empty `parent` path to append a top-level node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent` is invalid.
+    pub fn push(&mut self, parent: &[usize], init: C::Init) -> TreeIndex {
+        let index = if parent.is_empty() {
+            self.len()
+        } else {
+            self.child_count(parent)
+                .expect("Called `push` on an invalid path")
+        };
+        self.insert(parent, index, init)
+    }
+
+    /// Inserts a node at `index` into the children of the node at `parent`,
+    /// shifting all following siblings.
+    ///
+    /// Use an empty `parent` path to insert a top-level node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent` is invalid or `index` is greater than the number of children.
+    pub fn insert(&mut self, parent: &[usize], index: usize, init: C::Init) -> TreeIndex {
+        let (container, parent_index, siblings) = if parent.is_empty() {
+            (self.widget.clone(), None, &mut self.roots)
+        } else {
+            let node =
+                find_mut(&mut self.roots, parent).expect("Called `insert` on an invalid path");
+            (
+                node.container.clone(),
+                Some(node.index.clone()),
+                &mut node.children,
+            )
+        };
+        assert!(
+            index <= siblings.len(),
+            "Index {index} is out of bounds for {} children",
+            siblings.len()
+        );
+
+        for sibling in &siblings[index..] {
+            sibling.index.own().increment();
+            update_position(&container, sibling);
+        }
+
+        let tree_index = TreeIndex::new(parent_index.as_ref(), index);
+        let builder = FactoryBuilder::new(&tree_index, init);
+
+        let position = C::position(&builder.data, &tree_index);
+        let returned_widget = if index == 0 {
+            container.factory_prepend(builder.root_widget.clone(), &position)
+        } else {
+            container.factory_insert_after(
+                builder.root_widget.clone(),
+                &position,
+                &siblings[index - 1].handle.returned_widget,
+            )
+        };
+
+        let forward_index = tree_index.clone();
+        let handle = builder.launch(
+            &tree_index,
+            returned_widget,
+            &self.parent_sender,
+            move |output| C::forward_with_index(output, &forward_index),
+        );
+        let children_container = handle
+            .data
+            .get()
+            .init_children_container(&handle.root_widget);
+
+        siblings.insert(
+            index,
+            Node {
+                index: tree_index.clone(),
+                children: Vec::new(),
+                handle,
+                container: children_container,
+            },
+        );
+
+        tree_index
+    }
+
+    /// Removes the node at `path` together with its descendants and returns its model,
+    /// shifting all following siblings.
+    ///
+    /// The [`TreeIndex`] of the node and its descendants is invalidated,
+    /// see [`TreeIndex::is_removed`].
+    ///
+    /// Returns [`None`] if `path` is invalid.
+    pub fn remove(&mut self, path: &[usize]) -> Option<C> {
+        let (&index, parent) = path.split_last()?;
+        let (container, siblings) = if parent.is_empty() {
+            (self.widget.clone(), &mut self.roots)
+        } else {
+            let node = find_mut(&mut self.roots, parent)?;
+            (node.container.clone(), &mut node.children)
+        };
+        if index >= siblings.len() {
+            return None;
+        }
+
+        let Node {
+            index: tree_index,
+            handle,
+            children,
+            ..
+        } = siblings.remove(index);
+        container.factory_remove(&handle.returned_widget);
+        tree_index.invalidate();
+
+        for sibling in &siblings[index..] {
+            sibling.index.own().decrement();
+            update_position(&container, sibling);
+        }
+
+        // Shut down the descendants before their ancestor.
+        drop(children);
+        Some(handle.data.into_inner())
+    }
+
+    /// Removes all nodes.
+    pub fn clear(&mut self) {
+        for node in self.roots.drain(..) {
+            self.widget.factory_remove(&node.handle.returned_widget);
+            node.index.invalidate();
+        }
+    }
+}
+
+fn find<'a, C: TreeFactoryComponent>(nodes: &'a [Node<C>], path: &[usize]) -> Option<&'a Node<C>> {
+    let (first, rest) = path.split_first()?;
+    let node = nodes.get(*first)?;
+    if rest.is_empty() {
+        Some(node)
+    } else {
+        find(&node.children, rest)
+    }
+}
+
+fn find_mut<'a, C: TreeFactoryComponent>(
+    nodes: &'a mut [Node<C>],
+    path: &[usize],
+) -> Option<&'a mut Node<C>> {
+    let (first, rest) = path.split_first()?;
+    let node = nodes.get_mut(*first)?;
+    if rest.is_empty() {
+        Some(node)
+    } else {
+        find_mut(&mut node.children, rest)
+    }
+}
+
+fn update_position<C: TreeFactoryComponent>(container: &C::ParentWidget, node: &Node<C>) {
+    let position = C::position(node.handle.data.get(), &node.index);
+    container.factory_update_position(&node.handle.returned_widget, &position);
+}
+
+#[cfg(test)]
+mod test {
+    use super::FactoryTree;
+    use crate::factory::{
+        FactoryComponent, FactorySender, FactoryView, TreeFactoryComponent, TreeIndex,
+    };
+    use crate::{gtk, RelmIterChildrenExt};
+
+    use gtk::prelude::{BoxExt, WidgetExt};
+
+    #[derive(Debug)]
+    struct Node(&'static str);
+
+    impl FactoryComponent for Node {
+        type ParentWidget = gtk::Box;
+        type ParentInput = ();
+        type CommandOutput = ();
+        type Input = ();
+        type Output = ();
+        type Init = &'static str;
+        type Root = gtk::Box;
+        type Widgets = ();
+        type Index = TreeIndex;
+
+        fn init_model(name: &'static str, _: &TreeIndex, _: FactorySender<Self>) -> Self {
+            Self(name)
+        }
+
+        fn init_root(&self) -> Self::Root {
+            gtk::Box::default()
+        }
+
+        fn init_widgets(
+            &mut self,
+            _: &TreeIndex,
+            root: &Self::Root,
+            _: &<Self::ParentWidget as FactoryView>::ReturnedWidget,
+            _: FactorySender<Self>,
+        ) {
+            root.set_widget_name(self.0);
+        }
+    }
+
+    impl TreeFactoryComponent for Node {
+        fn init_children_container(&self, root: &Self::Root) -> gtk::Box {
+            let children = gtk::Box::default();
+            root.append(&children);
+            children
+        }
+    }
+
+    fn widget_names(container: &gtk::Box) -> Vec<String> {
+        container
+            .iter_children()
+            .map(|child| child.widget_name().to_string())
+            .collect()
+    }
+
+    #[gtk::test]
+    fn paths_follow_siblings_and_ancestors() {
+        let (sender, _) = crate::channel();
+        let mut tree = FactoryTree::<Node>::new(gtk::Box::default(), &sender);
+
+        let a = tree.push(&[], "a");
+        let b = tree.push(&[], "b");
+        let a0 = tree.push(&[0], "a0");
+        let a1 = tree.push(&[0], "a1");
+        let a1x = tree.push(&[0, 1], "a1x");
+        assert_eq!(a1x.current_path(), [0, 1, 0]);
+        assert_eq!(a1x.parent().unwrap().current_path(), a1.current_path());
+
+        // Inserting a sibling of an ancestor shifts the descendants.
+        let c = tree.insert(&[], 0, "c");
+        assert_eq!(c.current_path(), [0]);
+        assert_eq!(a.current_path(), [1]);
+        assert_eq!(b.current_path(), [2]);
+        assert_eq!(a1x.current_path(), [1, 1, 0]);
+        assert_eq!(widget_names(tree.widget()), ["c", "a", "b"]);
+
+        // Inserting a sibling shifts the following siblings and their descendants.
+        let a_new = tree.insert(&[1], 0, "a_new");
+        assert_eq!(a_new.current_path(), [1, 0]);
+        assert_eq!(a0.current_path(), [1, 1]);
+        assert_eq!(a1.current_path(), [1, 2]);
+        assert_eq!(a1x.current_path(), [1, 2, 0]);
+        assert_eq!(tree.get(&[1, 2, 0]).unwrap().0, "a1x");
+
+        // Removing a sibling of an ancestor shifts the descendants back.
+        assert_eq!(tree.remove(&[0]).unwrap().0, "c");
+        assert!(c.is_removed());
+        assert_eq!(a.current_path(), [0]);
+        assert_eq!(a1x.current_path(), [0, 2, 0]);
+
+        assert_eq!(tree.remove(&[0, 1]).unwrap().0, "a0");
+        assert_eq!(a1.current_path(), [0, 1]);
+        assert_eq!(a1x.current_path(), [0, 1, 0]);
+        assert_eq!(tree.get(&a1x.current_path()).unwrap().0, "a1x");
+        assert_eq!(widget_names(tree.widget()), ["a", "b"]);
+    }
+
+    #[gtk::test]
+    fn remove_invalidates_descendants() {
+        let (sender, _) = crate::channel();
+        let mut tree = FactoryTree::<Node>::new(gtk::Box::default(), &sender);
+
+        let a = tree.push(&[], "a");
+        let a0 = tree.push(&[0], "a0");
+        let a00 = tree.push(&[0, 0], "a00");
+        let b = tree.push(&[], "b");
+        let b0 = tree.push(&[1], "b0");
+
+        assert!(tree.remove(&a.current_path()).is_some());
+        assert!(a.is_removed());
+        assert!(a0.is_removed());
+        assert!(a00.is_removed());
+        assert!(!b.is_removed());
+
+        // The paths of removed nodes don't point to the nodes that took their place.
+        assert_eq!(b.current_path(), [0]);
+        assert_eq!(b0.current_path(), [0, 0]);
+        assert!(tree.get(&a0.current_path()).is_none());
+        assert!(tree.remove(&a00.current_path()).is_none());
+        assert_eq!(tree.get(&[0, 0]).unwrap().0, "b0");
+
+        tree.clear();
+        assert!(b.is_removed());
+        assert!(b0.is_removed());
+    }
+}
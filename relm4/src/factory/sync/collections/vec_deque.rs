@@ -6,12 +6,14 @@ use crate::factory::sync::traits::CloneableFactoryComponent;
 use crate::factory::{DynamicIndex, FactoryComponent, FactoryView};
 
 use super::{ModelStateValue, RenderedState};
+use crate::factory::diff::{diff, Change};
 
+use std::cmp::Ordering;
 use std::collections::hash_map::DefaultHasher;
 use std::collections::VecDeque;
 use std::hash::Hash;
 use std::iter::FusedIterator;
-use std::ops::{Deref, Index, IndexMut};
+use std::ops::{Bound, Deref, Index, IndexMut, Range, RangeBounds};
 
 #[cfg(feature = "libadwaita")]
 use gtk::prelude::Cast;
@@ -338,6 +340,123 @@ where
         self.inner.uid_counter = 1;
     }
 
+    /// Sorts the elements with a comparator function.
+    ///
+    /// The sort is stable and only the widgets that are out of order are moved.
+    pub fn sort_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&C, &C) -> Ordering,
+    {
+        self.permute(|entries| {
+            entries.sort_by(|(first, _), (second, _)| compare(first.get(), second.get()));
+        });
+    }
+
+    /// Sorts the elements with a key extraction function.
+    ///
+    /// The sort is stable and only the widgets that are out of order are moved.
+    pub fn sort_by_key<K, F>(&mut self, mut key: F)
+    where
+        K: Ord,
+        F: FnMut(&C) -> K,
+    {
+        self.sort_by(|first, second| key(first).cmp(&key(second)));
+    }
+
+    /// Reverses the order of the elements.
+    pub fn reverse(&mut self) {
+        self.permute(<[_]>::reverse);
+    }
+
+    /// Retains only the elements for which `keep` returns `true`.
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&C) -> bool,
+    {
+        let components = std::mem::take(&mut self.inner.components);
+        let model_state = std::mem::take(&mut self.inner.model_state);
+
+        for (component, state) in components.into_iter().zip(model_state) {
+            if keep(component.get()) {
+                self.inner.components.push_back(component);
+                self.inner.model_state.push_back(state);
+            } else {
+                if let Some(widget) = component.returned_widget() {
+                    self.inner.widget.factory_remove(widget);
+                }
+                component.extract();
+            }
+        }
+
+        self.update_indexes(0);
+    }
+
+    /// Shortens the [`FactoryVecDeque`], keeping the first `len` elements.
+    ///
+    /// Does nothing if `len` is greater than or equal to the current length.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len() {
+            self.drain(len..);
+        }
+    }
+
+    /// Removes the elements in `range` and returns them.
+    ///
+    /// The elements are removed even if the returned iterator isn't consumed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn drain<R>(
+        &mut self,
+        range: R,
+    ) -> impl Iterator<Item = C> + DoubleEndedIterator + ExactSizeIterator + FusedIterator
+    where
+        R: RangeBounds<usize>,
+    {
+        let range = self.resolve_range(range);
+        let start = range.start;
+
+        self.inner.model_state.drain(range.clone());
+        let removed: Vec<C> = self
+            .inner
+            .components
+            .drain(range)
+            .map(|component| {
+                if let Some(widget) = component.returned_widget() {
+                    self.inner.widget.factory_remove(widget);
+                }
+                component.extract()
+            })
+            .collect();
+
+        self.update_indexes(start);
+        removed.into_iter()
+    }
+
+    /// Replaces the elements in `range` with new elements created from `replace_with`
+    /// and returns the removed elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn splice<R, I>(
+        &mut self,
+        range: R,
+        replace_with: I,
+    ) -> impl Iterator<Item = C> + DoubleEndedIterator + ExactSizeIterator + FusedIterator
+    where
+        R: RangeBounds<usize>,
+        I: IntoIterator<Item = C::Init>,
+    {
+        let range = self.resolve_range(range);
+        let start = range.start;
+
+        let removed = self.drain(range);
+        self.insert_all(start, replace_with);
+        removed
+    }
+
     /// Returns an iterator over the components that returns mutable references.
     pub fn iter_mut(
         &mut self,
@@ -354,6 +473,96 @@ where
     }
 }
 
+impl<'a, C> FactoryVecDequeGuard<'a, C>
+where
+    C: FactoryComponent<Index = DynamicIndex>,
+{
+    /// Rearranges the elements with `func` and updates their indexes.
+    fn permute<F>(&mut self, func: F)
+    where
+        F: FnOnce(&mut [(ComponentStorage<C>, ModelStateValue)]),
+    {
+        let mut entries: Vec<_> = self
+            .inner
+            .components
+            .drain(..)
+            .zip(self.inner.model_state.drain(..))
+            .collect();
+        func(&mut entries);
+
+        let (components, model_state) = entries.into_iter().unzip();
+        self.inner.components = components;
+        self.inner.model_state = model_state;
+        self.update_indexes(0);
+    }
+
+    /// Inserts new elements at `index` without shifting
+    /// the following elements once per element.
+    fn insert_all<I>(&mut self, index: usize, inits: I)
+    where
+        I: IntoIterator<Item = C::Init>,
+    {
+        let components = self.inner.components.split_off(index);
+        let model_state = self.inner.model_state.split_off(index);
+
+        for init in inits {
+            let dyn_index = DynamicIndex::new(self.inner.components.len());
+            let builder = FactoryBuilder::new(&dyn_index, init);
+
+            self.inner
+                .components
+                .push_back(ComponentStorage::Builder(builder));
+            self.inner.model_state.push_back(ModelStateValue {
+                index: dyn_index,
+                uid: self.inner.uid_counter,
+                changed: false,
+            });
+            self.inner.uid_counter += 1;
+        }
+
+        let end = self.inner.components.len();
+        self.inner.components.extend(components);
+        self.inner.model_state.extend(model_state);
+        self.update_indexes(end);
+    }
+
+    /// Sets the indexes of all elements starting at `start`.
+    fn update_indexes(&mut self, start: usize) {
+        for (index, state) in self.inner.model_state.iter().enumerate().skip(start) {
+            state.index.set_value(index);
+        }
+    }
+
+    fn resolve_range<R: RangeBounds<usize>>(&self, range: R) -> Range<usize> {
+        let start = match range.start_bound() {
+            Bound::Included(start) => *start,
+            Bound::Excluded(start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => end + 1,
+            Bound::Excluded(end) => *end,
+            Bound::Unbounded => self.len(),
+        };
+        assert!(
+            start <= end && end <= self.len(),
+            "Range {start}..{end} is out of bounds for length {}",
+            self.len()
+        );
+        start..end
+    }
+}
+
+impl<'a, C> Extend<C::Init> for FactoryVecDequeGuard<'a, C>
+where
+    C: FactoryComponent<Index = DynamicIndex>,
+{
+    fn extend<I: IntoIterator<Item = C::Init>>(&mut self, iter: I) {
+        let len = self.len();
+        self.insert_all(len, iter);
+    }
+}
+
 impl<'a, C> Deref for FactoryVecDequeGuard<'a, C>
 where
     C: FactoryComponent<Index = DynamicIndex>,
@@ -457,64 +666,58 @@ where
     ///
     /// Also, only modified elements will be updated.
     fn render_changes(&mut self) {
-        let mut first_position_change_idx = None;
+        let changes = diff(
+            self.rendered_state.iter().map(|r| r.uid),
+            self.model_state.iter().map(|s| s.uid),
+        );
 
         let components = &mut self.components;
-        let rendered_state = &mut self.rendered_state;
-        for (index, state) in self.model_state.iter().enumerate() {
-            if state.uid == rendered_state.front().map(|r| r.uid).unwrap_or_default() {
-                // Remove item from previously rendered list
-                rendered_state.pop_front();
-
-                if state.changed {
-                    // Update component
-                    components[index].state_change_notify();
-                }
-            } else if let Some(rendered_index) =
-                rendered_state.iter().position(|r| r.uid == state.uid)
-            {
-                if first_position_change_idx.is_none() {
-                    first_position_change_idx = Some(index);
-                }
-
-                // Remove item from previously rendered list
-                rendered_state.remove(rendered_index);
-
-                // Detach and re-attach item
-                let widget = components[index].returned_widget().unwrap();
-                if index == 0 {
-                    self.widget.factory_move_start(widget);
-                } else {
-                    let previous_widget = components[index - 1].returned_widget().unwrap();
-                    self.widget.factory_move_after(widget, previous_widget);
-                }
-
-                if state.changed {
-                    // Update component
-                    components[index].state_change_notify();
+        for (index, change) in changes.into_iter().enumerate() {
+            let state = &self.model_state[index];
+            let update_position = match change {
+                Change::Keep { index_changed } => index_changed,
+                Change::Move => {
+                    // Detach and re-attach item
+                    let widget = components[index].returned_widget().unwrap();
+                    if index == 0 {
+                        self.widget.factory_move_start(widget);
+                    } else {
+                        let previous_widget = components[index - 1].returned_widget().unwrap();
+                        self.widget.factory_move_after(widget, previous_widget);
+                    }
+                    true
                 }
-            } else {
-                if first_position_change_idx.is_none() {
-                    first_position_change_idx = Some(index);
+                Change::Insert => {
+                    // The element doesn't exist yet
+                    let comp = &components[index];
+                    let insert_widget = comp.widget();
+                    let position = C::position(comp.get(), &state.index);
+                    let returned_widget = if index == 0 {
+                        self.widget.factory_prepend(insert_widget, &position)
+                    } else {
+                        let previous_widget = components[index - 1].returned_widget().unwrap();
+                        self.widget
+                            .factory_insert_after(insert_widget, &position, previous_widget)
+                    };
+                    let component = components.remove(index).unwrap();
+                    let component = component
+                        .launch(&state.index, returned_widget, &self.parent_sender)
+                        .unwrap();
+                    components.insert(index, component);
+                    continue;
                 }
+            };
 
-                // The element doesn't exist yet
-                let comp = &components[index];
-                let insert_widget = comp.widget();
+            let comp = &components[index];
+            if update_position {
                 let position = C::position(comp.get(), &state.index);
-                let returned_widget = if index == 0 {
-                    self.widget.factory_prepend(insert_widget, &position)
-                } else {
-                    let previous_widget = components[index - 1].returned_widget().unwrap();
-                    self.widget
-                        .factory_insert_after(insert_widget, &position, previous_widget)
-                };
-                let component = components.remove(index).unwrap();
-                let dyn_index = &self.model_state[index].index;
-                let component = component
-                    .launch(dyn_index, returned_widget, &self.parent_sender)
-                    .unwrap();
-                components.insert(index, component);
+                self.widget
+                    .factory_update_position(comp.returned_widget().unwrap(), &position);
+            }
+
+            if state.changed {
+                // Update component
+                comp.state_change_notify();
             }
         }
 
@@ -538,14 +741,6 @@ where
                 }
             })
             .collect();
-
-        if let Some(change_index) = first_position_change_idx {
-            for (index, comp) in components.iter().enumerate().skip(change_index) {
-                let position = C::position(comp.get(), &self.model_state[index].index);
-                self.widget
-                    .factory_update_position(comp.returned_widget().unwrap(), &position);
-            }
-        }
     }
 
     /// Returns the number of elements in the [`FactoryVecDeque`].
@@ -641,3 +836,136 @@ where
         clone
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::FactoryVecDeque;
+    use crate::factory::{DynamicIndex, FactoryComponent, FactorySender, FactoryView};
+    use crate::{gtk, RelmIterChildrenExt};
+
+    use gtk::prelude::WidgetExt;
+
+    #[derive(Debug)]
+    struct Row(u8);
+
+    impl FactoryComponent for Row {
+        type ParentWidget = gtk::Box;
+        type ParentInput = ();
+        type CommandOutput = ();
+        type Input = ();
+        type Output = ();
+        type Init = u8;
+        type Root = gtk::Label;
+        type Widgets = ();
+        type Index = DynamicIndex;
+
+        fn init_model(value: u8, _: &DynamicIndex, _: FactorySender<Self>) -> Self {
+            Self(value)
+        }
+
+        fn init_root(&self) -> Self::Root {
+            gtk::Label::default()
+        }
+
+        fn init_widgets(
+            &mut self,
+            _: &DynamicIndex,
+            root: &Self::Root,
+            _: &<Self::ParentWidget as FactoryView>::ReturnedWidget,
+            _: FactorySender<Self>,
+        ) {
+            root.set_widget_name(&self.0.to_string());
+        }
+    }
+
+    fn factory(values: &[u8]) -> (FactoryVecDeque<Row>, Vec<DynamicIndex>) {
+        let (sender, _) = crate::channel();
+        let mut factory = FactoryVecDeque::new(gtk::Box::default(), &sender);
+        let indexes = {
+            let mut guard = factory.guard();
+            values.iter().map(|value| guard.push_back(*value)).collect()
+        };
+        (factory, indexes)
+    }
+
+    fn values(factory: &FactoryVecDeque<Row>) -> Vec<u8> {
+        factory.iter().map(|row| row.0).collect()
+    }
+
+    fn widget_names(factory: &FactoryVecDeque<Row>) -> Vec<String> {
+        factory
+            .widget()
+            .iter_children()
+            .map(|child| child.widget_name().to_string())
+            .collect()
+    }
+
+    fn current_indexes(indexes: &[DynamicIndex]) -> Vec<usize> {
+        indexes.iter().map(DynamicIndex::current_index).collect()
+    }
+
+    #[gtk::test]
+    fn sort_by() {
+        let (mut factory, indexes) = factory(&[3, 1, 4, 0, 2]);
+        let widgets: Vec<gtk::Widget> = factory.widget().iter_children().collect();
+
+        factory
+            .guard()
+            .sort_by(|first, second| first.0.cmp(&second.0));
+
+        assert_eq!(values(&factory), [0, 1, 2, 3, 4]);
+        assert_eq!(widget_names(&factory), ["0", "1", "2", "3", "4"]);
+        assert_eq!(current_indexes(&indexes), [3, 1, 4, 0, 2]);
+
+        // The widgets are moved instead of being recreated.
+        let mut sorted: Vec<gtk::Widget> = factory.widget().iter_children().collect();
+        sorted.sort_by_key(|widget| widget.widget_name());
+        let mut original = widgets;
+        original.sort_by_key(|widget| widget.widget_name());
+        assert_eq!(sorted, original);
+    }
+
+    #[gtk::test]
+    fn reverse_and_retain() {
+        let (mut factory, indexes) = factory(&[0, 1, 2, 3, 4, 5]);
+
+        {
+            let mut guard = factory.guard();
+            guard.reverse();
+            guard.retain(|row| row.0 % 2 == 0);
+        }
+
+        assert_eq!(values(&factory), [4, 2, 0]);
+        assert_eq!(widget_names(&factory), ["4", "2", "0"]);
+        assert_eq!(indexes[4].current_index(), 0);
+        assert_eq!(indexes[2].current_index(), 1);
+        assert_eq!(indexes[0].current_index(), 2);
+    }
+
+    #[gtk::test]
+    fn drain_and_splice() {
+        let (mut factory, indexes) = factory(&[0, 1, 2, 3, 4, 5]);
+
+        let drained: Vec<u8> = factory.guard().drain(1..3).map(|row| row.0).collect();
+        assert_eq!(drained, [1, 2]);
+        assert_eq!(values(&factory), [0, 3, 4, 5]);
+        assert_eq!(widget_names(&factory), ["0", "3", "4", "5"]);
+        assert_eq!(indexes[0].current_index(), 0);
+        assert_eq!(indexes[3].current_index(), 1);
+        assert_eq!(indexes[5].current_index(), 3);
+
+        let removed: Vec<u8> = factory
+            .guard()
+            .splice(1..=2, [7, 8, 9])
+            .map(|row| row.0)
+            .collect();
+        assert_eq!(removed, [3, 4]);
+        assert_eq!(values(&factory), [0, 7, 8, 9, 5]);
+        assert_eq!(widget_names(&factory), ["0", "7", "8", "9", "5"]);
+        assert_eq!(indexes[0].current_index(), 0);
+        assert_eq!(indexes[5].current_index(), 4);
+
+        factory.guard().truncate(2);
+        assert_eq!(widget_names(&factory), ["0", "7"]);
+    }
+}
@@ -7,5 +7,8 @@ mod traits;
 use builder::FactoryBuilder;
 use handle::FactoryHandle;
 
-pub use collections::{FactoryHashMap, FactoryVecDeque, FactoryVecDequeGuard};
-pub use traits::{CloneableFactoryComponent, FactoryComponent};
+pub use collections::{
+    FactoryBTreeMap, FactoryElementGuard, FactoryHashMap, FactoryTree, FactoryVecDeque,
+    FactoryVecDequeGuard,
+};
+pub use traits::{CloneableFactoryComponent, FactoryComponent, TreeFactoryComponent};
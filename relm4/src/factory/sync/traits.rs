@@ -1,7 +1,7 @@
 //! Traits for for managing and updating factories.
 
-use crate::factory::{FactorySender, FactoryView, Position};
-use crate::Sender;
+use crate::factory::{FactorySender, FactoryView, Position, TreeIndex};
+use crate::{ChannelOptions, Sender};
 
 use std::fmt::Debug;
 
@@ -39,7 +39,8 @@ pub trait FactoryComponent:
     ///
     /// For example, for [`FactoryVecDeque`](crate::factory::FactoryVecDeque), this type
     /// is [`DynamicIndex`](crate::factory::DynamicIndex).
-    /// For [`FactoryHashMap`](crate::factory::FactoryHashMap), this type is equal to the key
+    /// For [`FactoryHashMap`](crate::factory::FactoryHashMap) and
+    /// [`FactoryBTreeMap`](crate::factory::FactoryBTreeMap), this type is equal to the key
     /// you use for inserting values.
     type Index;
 
@@ -101,6 +102,34 @@ pub trait FactoryComponent:
         self.update_view(widgets, sender);
     }
 
+    /// Whether the view should only be updated once for all messages
+    /// that are handled in one iteration of the main loop.
+    ///
+    /// If this returns `true`, the runtime calls [`update`](Self::update) or
+    /// [`update_cmd`](Self::update_cmd) for all queued messages and
+    /// [`update_view`](Self::update_view) only once afterwards.
+    /// [`update_with_view`](Self::update_with_view) and
+    /// [`update_cmd_with_view`](Self::update_cmd_with_view) aren't called in this mode.
+    ///
+    /// Returns `false` by default.
+    fn batch_view_updates() -> bool {
+        false
+    }
+
+    /// Options for the input channel of each factory component.
+    ///
+    /// Use this to bound the channel with an [`OverflowPolicy`](crate::OverflowPolicy)
+    /// or to prioritize messages, similar to
+    /// [`ComponentBuilder::bounded`](crate::ComponentBuilder::bounded) and
+    /// [`ComponentBuilder::prioritize`](crate::ComponentBuilder::prioritize).
+    ///
+    /// Returns an unbounded channel without priority messages by default.
+    /// Factory components run on the main thread, so [`OverflowPolicy::Block`](crate::OverflowPolicy::Block)
+    /// isn't allowed.
+    fn input_channel() -> ChannelOptions<Self::Input> {
+        ChannelOptions::default()
+    }
+
     /// Last method called before a component is shut down.
     #[allow(unused)]
     fn shutdown(&mut self, widgets: &mut Self::Widgets, output: Sender<Self::Output>) {}
@@ -120,3 +149,22 @@ pub trait CloneableFactoryComponent: FactoryComponent {
     /// This is necessary for cloning the factory.
     fn get_init(&self) -> Self::Init;
 }
+
+/// Extension for [`FactoryComponent`] that allows elements to be nodes
+/// of a [`FactoryTree`](crate::factory::FactoryTree).
+pub trait TreeFactoryComponent: FactoryComponent<Index = TreeIndex> {
+    /// Creates the container for the children of this node and attaches it to `root`.
+    ///
+    /// This is called after the widgets were initialized.
+    /// The container is hidden while the node is collapsed.
+    fn init_children_container(&self, root: &Self::Root) -> Self::ParentWidget;
+
+    /// Optionally convert an output message from this node to an input message for the
+    /// component that owns the tree, using the index of the node.
+    ///
+    /// This replaces [`FactoryComponent::forward_to_parent`] for nodes of a tree.
+    /// If [`None`] is returned, nothing is forwarded.
+    fn forward_with_index(_output: Self::Output, _index: &TreeIndex) -> Option<Self::ParentInput> {
+        None
+    }
+}
@@ -0,0 +1,75 @@
+use super::DynamicIndex;
+
+/// The index of removed nodes, which is never a valid index of a sibling.
+const REMOVED: usize = usize::MAX;
+
+/// The path of a node in a [`FactoryTree`](super::FactoryTree)
+/// that updates automatically when nodes are shifted.
+///
+/// The path contains the index of the node among its siblings for each level of the tree,
+/// starting at the top level.
+/// Like [`DynamicIndex`], this type should be sent in messages instead of the current path,
+/// because the path might be stale by the time a message is handled.
+///
+/// When a node is removed, the indexes of the node and all of its descendants
+/// are invalidated, so messages that are still queued for them can be ignored
+/// by checking [`is_removed`](Self::is_removed).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeIndex {
+    path: Vec<DynamicIndex>,
+}
+
+impl TreeIndex {
+    /// Returns the current path from the top level to this node.
+    ///
+    /// The path of a removed node doesn't point to any node.
+    #[must_use]
+    pub fn current_path(&self) -> Vec<usize> {
+        self.path.iter().map(DynamicIndex::current_index).collect()
+    }
+
+    /// Returns true if the node or one of its ancestors was removed from the tree.
+    #[must_use]
+    pub fn is_removed(&self) -> bool {
+        self.path
+            .iter()
+            .any(|index| index.current_index() == REMOVED)
+    }
+
+    /// Returns the current index of the node among its siblings.
+    #[must_use]
+    pub fn current_index(&self) -> usize {
+        self.own().current_index()
+    }
+
+    /// Returns the depth of the node, which is `0` for top-level nodes.
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        self.path.len() - 1
+    }
+
+    /// Returns the index of the parent node or [`None`] for top-level nodes.
+    #[must_use]
+    pub fn parent(&self) -> Option<Self> {
+        (self.path.len() > 1).then(|| Self {
+            path: self.path[..self.path.len() - 1].to_vec(),
+        })
+    }
+
+    /// Creates the index of a child at `index`. Without a parent, the child is a top-level node.
+    pub(super) fn new(parent: Option<&Self>, index: usize) -> Self {
+        let mut path = parent.map(|parent| parent.path.clone()).unwrap_or_default();
+        path.push(DynamicIndex::new(index));
+        Self { path }
+    }
+
+    /// Marks the node and thereby all of its descendants as removed.
+    pub(super) fn invalidate(&self) {
+        self.own().set_value(REMOVED);
+    }
+
+    /// The index of the node among its siblings.
+    pub(super) fn own(&self) -> &DynamicIndex {
+        self.path.last().expect("Tree indexes are never empty")
+    }
+}
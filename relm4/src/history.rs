@@ -0,0 +1,497 @@
+//! Undo and redo for component models.
+//!
+//! Implement [`Reversible`] for the data that should support undo and keep it
+//! in a [`History`] inside your component model.
+//! Every change that is applied through the history records its inverse,
+//! so it can be undone and redone later.
+//! Several changes can be grouped into a transaction that is undone in one step.
+//!
+//! [`HistoryActions`] provides the standard `app.undo` and `app.redo` actions
+//! with their usual accelerators.
+//!
+//! ```
+//! use relm4::history::{History, Reversible};
+//!
+//! #[derive(Default)]
+//! struct Document(String);
+//!
+//! enum Edit {
+//!     Push(char),
+//!     Pop,
+//! }
+//!
+//! impl Reversible for Document {
+//!     type Change = Edit;
+//!
+//!     fn apply(&mut self, change: Edit) -> Option<Edit> {
+//!         match change {
+//!             Edit::Push(c) => {
+//!                 self.0.push(c);
+//!                 Some(Edit::Pop)
+//!             }
+//!             Edit::Pop => self.0.pop().map(Edit::Push),
+//!         }
+//!     }
+//! }
+//!
+//! let mut history = History::new(Document::default());
+//! history.apply(Edit::Push('a'));
+//!
+//! history.begin_transaction();
+//! history.apply(Edit::Push('b'));
+//! history.apply(Edit::Push('c'));
+//! history.commit_transaction();
+//! assert_eq!(history.0, "abc");
+//!
+//! history.undo();
+//! assert_eq!(history.0, "a");
+//! history.redo();
+//! assert_eq!(history.0, "abc");
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::Deref;
+
+use gtk::gio;
+use gtk::prelude::ActionExt;
+
+use crate::actions::{AccelsPlus, ActionGroupName, ActionName, RelmAction, RelmActionGroup};
+use crate::Sender;
+
+/// Data that can be changed in a reversible way.
+pub trait Reversible {
+    /// A change of the data.
+    ///
+    /// The inverse of a change is a change as well, so undoing and
+    /// redoing both apply changes.
+    type Change;
+
+    /// Applies `change` and returns the change that reverts it.
+    ///
+    /// Return [`None`] if `change` had no effect, so it isn't recorded.
+    fn apply(&mut self, change: Self::Change) -> Option<Self::Change>;
+}
+
+/// Messages that modify a [`History`].
+///
+/// Usually wrapped by the input message of a component and passed to [`History::handle`].
+#[derive(Debug)]
+pub enum HistoryMsg<Change> {
+    /// Apply a change.
+    Apply(Change),
+    /// Revert the last step.
+    Undo,
+    /// Apply the last reverted step again.
+    Redo,
+    /// Group the following changes until [`HistoryMsg::CommitTransaction`].
+    BeginTransaction,
+    /// Finish the current transaction.
+    CommitTransaction,
+}
+
+/// Reversible data that records the inverse of all changes applied to it.
+///
+/// Read access is available through [`Deref`], changes must be made with [`History::apply`].
+pub struct History<T: Reversible> {
+    data: T,
+    /// Each step holds the inverses of its changes in the order they were applied.
+    undo: VecDeque<Vec<T::Change>>,
+    redo: Vec<Vec<T::Change>>,
+    /// The open transaction and how often it was begun.
+    transaction: Option<(Vec<T::Change>, usize)>,
+    limit: Option<usize>,
+}
+
+impl<T: Reversible> History<T> {
+    /// Creates a history without a depth limit.
+    pub fn new(data: T) -> Self {
+        Self {
+            data,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            transaction: None,
+            limit: None,
+        }
+    }
+
+    /// Creates a history that keeps at most `limit` steps.
+    pub fn with_limit(data: T, limit: usize) -> Self {
+        let mut history = Self::new(data);
+        history.limit = Some(limit);
+        history
+    }
+
+    /// Sets the maximum number of steps that can be undone.
+    ///
+    /// The oldest steps are dropped if there are more.
+    pub fn set_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+        self.enforce_limit();
+    }
+
+    /// Returns the maximum number of steps that can be undone.
+    #[must_use]
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    /// Applies `change` and records its inverse.
+    ///
+    /// Returns `true` if the change had an effect.
+    /// Recording a change discards the steps that could be redone.
+    pub fn apply(&mut self, change: T::Change) -> bool {
+        let Some(inverse) = self.data.apply(change) else {
+            return false;
+        };
+
+        self.redo.clear();
+        if let Some((changes, _)) = &mut self.transaction {
+            changes.push(inverse);
+        } else {
+            self.push_undo(vec![inverse]);
+        }
+        true
+    }
+
+    /// Reverts the last step.
+    ///
+    /// An open transaction is committed first.
+    /// Returns `false` if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        self.finish_transaction();
+        match self.undo.pop_back() {
+            Some(step) => {
+                let step = revert(&mut self.data, step);
+                self.redo.push(step);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Applies the last reverted step again.
+    ///
+    /// An open transaction is committed first.
+    /// Returns `false` if there was nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        self.finish_transaction();
+        match self.redo.pop() {
+            Some(step) => {
+                let step = revert(&mut self.data, step);
+                self.undo.push_back(step);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Groups all following changes into one step until the transaction is committed.
+    ///
+    /// Transactions can be nested, only the outermost commit finishes the step.
+    pub fn begin_transaction(&mut self) {
+        match &mut self.transaction {
+            Some((_, depth)) => *depth += 1,
+            None => self.transaction = Some((Vec::new(), 1)),
+        }
+    }
+
+    /// Commits the current transaction.
+    ///
+    /// Empty transactions aren't recorded.
+    pub fn commit_transaction(&mut self) {
+        match &mut self.transaction {
+            Some((_, depth)) if *depth > 1 => *depth -= 1,
+            Some(_) => self.finish_transaction(),
+            None => tracing::warn!("Committed a transaction that wasn't begun"),
+        }
+    }
+
+    /// Reverts all changes of the current transaction and closes it.
+    pub fn rollback_transaction(&mut self) {
+        if let Some((changes, _)) = self.transaction.take() {
+            revert(&mut self.data, changes);
+        }
+    }
+
+    /// Returns `true` if a transaction is open.
+    #[must_use]
+    pub fn in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+
+    /// Handles a [`HistoryMsg`] and returns `true` if the data changed.
+    pub fn handle(&mut self, message: HistoryMsg<T::Change>) -> bool {
+        match message {
+            HistoryMsg::Apply(change) => self.apply(change),
+            HistoryMsg::Undo => self.undo(),
+            HistoryMsg::Redo => self.redo(),
+            HistoryMsg::BeginTransaction => {
+                self.begin_transaction();
+                false
+            }
+            HistoryMsg::CommitTransaction => {
+                self.commit_transaction();
+                false
+            }
+        }
+    }
+
+    /// Returns `true` if there is a step to undo.
+    #[must_use]
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+            || self
+                .transaction
+                .as_ref()
+                .map_or(false, |(changes, _)| !changes.is_empty())
+    }
+
+    /// Returns `true` if there is a step to redo.
+    #[must_use]
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Forgets all recorded steps without changing the data.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.transaction = None;
+    }
+
+    /// Returns the data and drops the recorded steps.
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+
+    fn finish_transaction(&mut self) {
+        if let Some((changes, _)) = self.transaction.take() {
+            if !changes.is_empty() {
+                self.push_undo(changes);
+            }
+        }
+    }
+
+    fn push_undo(&mut self, step: Vec<T::Change>) {
+        self.undo.push_back(step);
+        self.enforce_limit();
+    }
+
+    fn enforce_limit(&mut self) {
+        if let Some(limit) = self.limit {
+            while self.undo.len() > limit {
+                self.undo.pop_front();
+            }
+            if self.redo.len() > limit {
+                self.redo.drain(..self.redo.len() - limit);
+            }
+        }
+    }
+}
+
+/// Applies the inverses of a step in reverse order and returns the step that reverts it.
+fn revert<T: Reversible>(data: &mut T, step: Vec<T::Change>) -> Vec<T::Change> {
+    let mut inverse: Vec<T::Change> = step
+        .into_iter()
+        .rev()
+        .filter_map(|change| data.apply(change))
+        .collect();
+    inverse.reverse();
+    inverse
+}
+
+impl<T: Reversible> Deref for History<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.data
+    }
+}
+
+impl<T: Reversible + Default> Default for History<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Reversible + fmt::Debug> fmt::Debug for History<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("History")
+            .field("data", &self.data)
+            .field("undo", &self.undo.len())
+            .field("redo", &self.redo.len())
+            .field("in_transaction", &self.in_transaction())
+            .field("limit", &self.limit)
+            .finish()
+    }
+}
+
+/// The `app` action group.
+#[derive(Debug)]
+pub struct AppActionGroup;
+
+impl ActionGroupName for AppActionGroup {
+    const NAME: &'static str = "app";
+}
+
+/// The `app.undo` action.
+#[derive(Debug)]
+pub struct UndoAction;
+
+impl ActionName for UndoAction {
+    type Group = AppActionGroup;
+    type Target = ();
+    type State = ();
+
+    const NAME: &'static str = "undo";
+}
+
+/// The `app.redo` action.
+#[derive(Debug)]
+pub struct RedoAction;
+
+impl ActionName for RedoAction {
+    type Group = AppActionGroup;
+    type Target = ();
+    type State = ();
+
+    const NAME: &'static str = "redo";
+}
+
+/// The `app.undo` and `app.redo` actions that send messages to a component.
+///
+/// Call [`HistoryActions::update`] after the history changed,
+/// so the actions and widgets using them are only enabled if there's something to do.
+#[derive(Debug, Clone)]
+pub struct HistoryActions {
+    undo: gio::SimpleAction,
+    redo: gio::SimpleAction,
+}
+
+impl HistoryActions {
+    /// Creates the actions that send `undo` and `redo` to `sender`.
+    pub fn new<M>(sender: &Sender<M>, undo: M, redo: M) -> Self
+    where
+        M: Clone + 'static,
+    {
+        let undo_sender = sender.clone();
+        let redo_sender = sender.clone();
+        let undo: RelmAction<UndoAction> =
+            RelmAction::new_stateless(move |_| undo_sender.emit(undo.clone()));
+        let redo: RelmAction<RedoAction> =
+            RelmAction::new_stateless(move |_| redo_sender.emit(redo.clone()));
+
+        let actions = Self {
+            undo: undo.into(),
+            redo: redo.into(),
+        };
+        actions.undo.set_enabled(false);
+        actions.redo.set_enabled(false);
+        actions
+    }
+
+    /// Registers the actions at the main application and sets
+    /// the accelerators `<primary>z`, `<primary><shift>z` and `<primary>y`.
+    pub fn register_for_main_application(&self) {
+        let group: RelmActionGroup<AppActionGroup> =
+            [self.undo.clone(), self.redo.clone()].into_iter().collect();
+        group.register_for_main_application();
+
+        let app = crate::main_application();
+        app.set_accelerators_for_action::<UndoAction>(&["<primary>z"]);
+        app.set_accelerators_for_action::<RedoAction>(&["<primary><shift>z", "<primary>y"]);
+    }
+
+    /// Enables or disables the actions depending on the state of `history`.
+    pub fn update<T: Reversible>(&self, history: &History<T>) {
+        self.undo.set_enabled(history.can_undo());
+        self.redo.set_enabled(history.can_redo());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{History, HistoryMsg, Reversible};
+
+    #[derive(Debug, Default)]
+    struct Counter(i32);
+
+    impl Reversible for Counter {
+        type Change = i32;
+
+        fn apply(&mut self, change: i32) -> Option<i32> {
+            if change == 0 {
+                None
+            } else {
+                self.0 += change;
+                Some(-change)
+            }
+        }
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut history = History::new(Counter::default());
+        assert!(history.apply(1));
+        assert!(history.apply(2));
+        assert!(!history.apply(0));
+        assert_eq!(history.0, 3);
+
+        assert!(history.undo());
+        assert_eq!(history.0, 1);
+        assert!(history.undo());
+        assert!(!history.undo());
+        assert_eq!(history.0, 0);
+
+        assert!(history.redo());
+        assert_eq!(history.0, 1);
+
+        // New changes discard the redo steps.
+        history.apply(5);
+        assert!(!history.can_redo());
+        assert_eq!(history.0, 6);
+    }
+
+    #[test]
+    fn transactions() {
+        let mut history = History::new(Counter::default());
+        history.handle(HistoryMsg::BeginTransaction);
+        history.apply(1);
+        history.begin_transaction();
+        history.apply(2);
+        history.commit_transaction();
+        assert!(history.in_transaction());
+        history.apply(3);
+        history.handle(HistoryMsg::CommitTransaction);
+
+        assert_eq!(history.0, 6);
+        assert!(history.handle(HistoryMsg::Undo));
+        assert_eq!(history.0, 0);
+        assert!(history.handle(HistoryMsg::Redo));
+        assert_eq!(history.0, 6);
+
+        history.begin_transaction();
+        history.apply(4);
+        history.rollback_transaction();
+        assert_eq!(history.0, 6);
+        assert!(!history.in_transaction());
+    }
+
+    #[test]
+    fn limit() {
+        let mut history = History::with_limit(Counter::default(), 2);
+        for _ in 0..4 {
+            history.apply(1);
+        }
+
+        assert!(history.undo());
+        assert!(history.undo());
+        assert!(!history.undo());
+        assert_eq!(history.0, 2);
+
+        history.set_limit(Some(1));
+        assert!(history.redo());
+        assert!(!history.redo());
+    }
+}
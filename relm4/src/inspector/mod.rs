@@ -0,0 +1,534 @@
+//! Inspect the components of a running application.
+//!
+//! Once [enabled](set_enabled), the runtime keeps a registry of all components,
+//! async components and workers from the moment they are launched until they are shut down.
+//! For each component, the registry knows its type, its [`id`](crate::Component::id),
+//! the component that launched it and how many messages it processed.
+//!
+//! The registry is disabled by default, so it doesn't slow down applications
+//! that don't use it. Components launched while it's disabled are never registered,
+//! so enable it before launching the components you want to inspect.
+//!
+//! The [`Debug`] output of a model and sending input messages from text
+//! are only available for components that opted in when they were launched:
+//!
+//! ```no_run
+//! # use relm4::prelude::*;
+//! # #[derive(Debug)]
+//! # struct App;
+//! # impl SimpleComponent for App {
+//! #     type Input = u8;
+//! #     type Output = ();
+//! #     type Init = ();
+//! #     type Root = gtk::Window;
+//! #     type Widgets = ();
+//! #     fn init_root() -> Self::Root { gtk::Window::default() }
+//! #     fn init(_: (), _: &Self::Root, _: ComponentSender<Self>) -> ComponentParts<Self> {
+//! #         ComponentParts { model: App, widgets: () }
+//! #     }
+//! # }
+//! relm4::inspector::set_enabled(true);
+//!
+//! let connector = App::builder()
+//!     .inspect_model()
+//!     .inspect_input(|text| text.parse::<u8>())
+//!     .launch(());
+//!
+//! // For example in the handler of a keyboard shortcut:
+//! relm4::inspector::open_window();
+//! ```
+
+mod window;
+
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+
+use crate::{metrics, Sender};
+
+pub use window::open_window;
+
+static REGISTRY: Lazy<Mutex<BTreeMap<ComponentId, ComponentInfo>>> = Lazy::new(Mutex::default);
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+type Injector = Box<dyn Fn(&str) -> Result<(), InjectError>>;
+type Parser<Input> = Rc<dyn Fn(&str) -> Result<Input, String>>;
+
+thread_local! {
+    /// The component whose runtime is currently running on this thread.
+    static CURRENT: Cell<Option<ComponentId>> = Cell::new(None);
+    /// Senders of components that accept input messages from text.
+    static INJECTORS: RefCell<HashMap<ComponentId, Injector>> = RefCell::default();
+}
+
+/// Identifies a component in the registry.
+///
+/// Unlike [`Component::id`](crate::Component::id), identifiers are never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ComponentId(u64);
+
+impl fmt::Display for ComponentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Information about a live component.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ComponentInfo {
+    /// The identifier of the component in the registry.
+    pub id: ComponentId,
+    /// The type name of the component.
+    pub type_name: &'static str,
+    /// The value returned by [`Component::id`](crate::Component::id).
+    pub name: String,
+    /// The component that launched this component.
+    pub parent: Option<ComponentId>,
+    /// The number of input messages the component processed.
+    pub messages: u64,
+    /// The number of command outputs the component processed.
+    pub commands: u64,
+    /// How long the last update took.
+    pub last_update: Option<Duration>,
+    /// The [`Debug`] output of the model after the last update.
+    ///
+    /// This is only available if the component was launched with `inspect_model()`.
+    pub model: Option<String>,
+}
+
+/// The error returned by [`inject_input`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InjectError {
+    /// No component with this identifier is running.
+    NotFound,
+    /// The component wasn't launched with `inspect_input()`.
+    Unsupported,
+    /// The text couldn't be parsed into an input message.
+    Parse(String),
+    /// The component doesn't receive messages anymore.
+    Closed,
+}
+
+impl fmt::Display for InjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound => f.write_str("component not found"),
+            Self::Unsupported => f.write_str("component doesn't accept input from text"),
+            Self::Parse(error) => write!(f, "invalid input: {error}"),
+            Self::Closed => f.write_str("component was shut down"),
+        }
+    }
+}
+
+impl std::error::Error for InjectError {}
+
+fn registry() -> std::sync::MutexGuard<'static, BTreeMap<ComponentId, ComponentInfo>> {
+    REGISTRY
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// Enables or disables the registry.
+///
+/// Only components that are launched while the registry is enabled are registered.
+/// The registry is disabled by default.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns `true` if components are registered when they are launched.
+#[must_use]
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Returns all live components in the order they were launched.
+#[must_use]
+pub fn components() -> Vec<ComponentInfo> {
+    registry().values().cloned().collect()
+}
+
+/// Returns information about a live component.
+#[must_use]
+pub fn component(id: ComponentId) -> Option<ComponentInfo> {
+    registry().get(&id).cloned()
+}
+
+/// Returns the live components that were launched by a component.
+#[must_use]
+pub fn children(id: ComponentId) -> Vec<ComponentInfo> {
+    registry()
+        .values()
+        .filter(|info| info.parent == Some(id))
+        .cloned()
+        .collect()
+}
+
+/// Returns the live components without a live parent.
+#[must_use]
+pub fn roots() -> Vec<ComponentInfo> {
+    let registry = registry();
+    registry
+        .values()
+        .filter(|info| {
+            info.parent
+                .map_or(true, |parent| !registry.contains_key(&parent))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Parses `text` into an input message and sends it to a component.
+///
+/// Only works on the thread the component was launched on
+/// and for components that were launched with `inspect_input()`.
+pub fn inject_input(id: ComponentId, text: &str) -> Result<(), InjectError> {
+    if !registry().contains_key(&id) {
+        // Workers are shut down on their own thread and can't remove their injector.
+        INJECTORS.with(|injectors| injectors.borrow_mut().remove(&id));
+        return Err(InjectError::NotFound);
+    }
+
+    INJECTORS.with(|injectors| match injectors.borrow().get(&id) {
+        Some(inject) => inject(text),
+        None => Err(InjectError::Unsupported),
+    })
+}
+
+/// Runs `future` as the runtime of a component.
+///
+/// Components that are launched while the future is polled
+/// are registered as children of this component.
+pub(crate) fn scope<F: Future>(id: ComponentId, future: F) -> Scoped<F> {
+    Scoped {
+        id,
+        future: Box::pin(future),
+    }
+}
+
+/// A future that marks a component as current while it's polled.
+pub(crate) struct Scoped<F> {
+    id: ComponentId,
+    future: Pin<Box<F>>,
+}
+
+impl<F> fmt::Debug for Scoped<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scoped").field("id", &self.id).finish()
+    }
+}
+
+impl<F: Future> Future for Scoped<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let _current = Entered::new(self.id);
+        self.future.as_mut().poll(cx)
+    }
+}
+
+/// Marks a component as current until dropped.
+#[derive(Debug)]
+pub(crate) struct Entered {
+    previous: Option<ComponentId>,
+}
+
+impl Entered {
+    fn new(id: ComponentId) -> Self {
+        Self {
+            previous: CURRENT.with(|current| current.replace(Some(id))),
+        }
+    }
+}
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.set(self.previous));
+    }
+}
+
+/// Counts the messages of a component in the registry and its [`metrics`](crate::metrics).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct MessageCounter {
+    id: ComponentId,
+    type_name: &'static str,
+    registered: bool,
+}
+
+impl MessageCounter {
+    /// Counts an input message while `queue_length` messages are still queued.
+    pub(crate) fn input(self, queue_length: usize) {
+        if self.registered {
+            if let Some(info) = registry().get_mut(&self.id) {
+                info.messages += 1;
+            }
+        }
+        metrics::record_input(self.type_name, queue_length);
+    }
+
+    /// Counts a command output.
+    pub(crate) fn command(self) {
+        if self.registered {
+            if let Some(info) = registry().get_mut(&self.id) {
+                info.commands += 1;
+            }
+        }
+        metrics::record_command(self.type_name);
+    }
+}
+
+/// Keeps a component in the registry until dropped.
+#[derive(Debug)]
+pub(crate) struct Registration {
+    id: ComponentId,
+    type_name: &'static str,
+    /// Whether the component was added to the registry.
+    ///
+    /// Components are only added while the registry is enabled,
+    /// but their identifiers are still used to track their children.
+    registered: bool,
+    /// Whether the component is counted by the [`metrics`].
+    counted: bool,
+}
+
+impl Registration {
+    /// Registers a new component as child of the current component.
+    pub(crate) fn new(type_name: &'static str) -> Self {
+        let id = ComponentId(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+        let registered = is_enabled();
+
+        if registered {
+            let parent = CURRENT.with(Cell::get);
+            registry().insert(
+                id,
+                ComponentInfo {
+                    id,
+                    type_name,
+                    name: String::new(),
+                    parent,
+                    messages: 0,
+                    commands: 0,
+                    last_update: None,
+                    model: None,
+                },
+            );
+        }
+        let counted = metrics::component_started(type_name);
+
+        Self {
+            id,
+            type_name,
+            registered,
+            counted,
+        }
+    }
+
+    pub(crate) const fn id(&self) -> ComponentId {
+        self.id
+    }
+
+    pub(crate) const fn counter(&self) -> MessageCounter {
+        MessageCounter {
+            id: self.id,
+            type_name: self.type_name,
+            registered: self.registered,
+        }
+    }
+
+    /// Marks this component as current, for example while it's initialized.
+    pub(crate) fn enter(&self) -> Entered {
+        Entered::new(self.id)
+    }
+
+    /// Stores the value of [`Component::id`](crate::Component::id).
+    pub(crate) fn set_name(&self, name: String) {
+        if !self.registered {
+            return;
+        }
+        if let Some(info) = registry().get_mut(&self.id) {
+            info.name = name;
+        }
+    }
+
+    /// Stores the [`Debug`] output of the model if the component opted in.
+    pub(crate) fn snapshot<Model>(&self, model: &Model, debug: Option<fn(&Model) -> String>) {
+        if !self.registered {
+            return;
+        }
+        if let Some(debug) = debug {
+            let output = debug(model);
+            if let Some(info) = registry().get_mut(&self.id) {
+                info.model = Some(output);
+            }
+        }
+    }
+
+    /// Records the duration of an update that started at `started`.
+    pub(crate) fn updated<Model>(
+        &self,
+        started: Instant,
+        model: &Model,
+        debug: Option<fn(&Model) -> String>,
+    ) {
+        let duration = started.elapsed();
+        if self.registered {
+            if let Some(info) = registry().get_mut(&self.id) {
+                info.last_update = Some(duration);
+            }
+        }
+        metrics::record_update(self.type_name, duration);
+        self.snapshot(model, debug);
+    }
+
+    /// Records the duration of a separate view update that started at `started`.
+    pub(crate) fn viewed(&self, started: Instant) {
+        metrics::record_view(self.type_name, started.elapsed());
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        if self.counted {
+            metrics::component_stopped(self.type_name);
+        }
+        if self.registered {
+            registry().remove(&self.id);
+            INJECTORS
+                .try_with(|injectors| injectors.borrow_mut().remove(&self.id))
+                .ok();
+        }
+    }
+}
+
+/// Inspection options of a component builder.
+pub(crate) struct InspectOptions<Model, Input> {
+    debug: Option<fn(&Model) -> String>,
+    parse: Option<Parser<Input>>,
+}
+
+impl<Model, Input> Default for InspectOptions<Model, Input> {
+    fn default() -> Self {
+        Self {
+            debug: None,
+            parse: None,
+        }
+    }
+}
+
+impl<Model, Input> Clone for InspectOptions<Model, Input> {
+    fn clone(&self) -> Self {
+        Self {
+            debug: self.debug,
+            parse: self.parse.clone(),
+        }
+    }
+}
+
+impl<Model, Input> fmt::Debug for InspectOptions<Model, Input> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InspectOptions")
+            .field("debug", &self.debug.is_some())
+            .field("parse", &self.parse.is_some())
+            .finish()
+    }
+}
+
+impl<Model, Input: 'static> InspectOptions<Model, Input> {
+    pub(crate) fn debug_model(&mut self)
+    where
+        Model: fmt::Debug,
+    {
+        self.debug = Some(|model| format!("{model:#?}"));
+    }
+
+    pub(crate) fn parse_input<F, E>(&mut self, parse: F)
+    where
+        F: Fn(&str) -> Result<Input, E> + 'static,
+        E: fmt::Display,
+    {
+        self.parse = Some(Rc::new(move |text| parse(text).map_err(|e| e.to_string())));
+    }
+
+    /// Makes the component accept input messages from text
+    /// and returns the function that formats the model.
+    pub(crate) fn install(
+        self,
+        registration: &Registration,
+        sender: Sender<Input>,
+    ) -> Option<fn(&Model) -> String> {
+        let Self { debug, parse } = self;
+
+        if !registration.registered {
+            return None;
+        }
+
+        if let Some(parse) = parse {
+            let inject: Injector = Box::new(move |text| {
+                let input = parse(text).map_err(InjectError::Parse)?;
+                sender.send(input).map_err(|_| InjectError::Closed)
+            });
+            INJECTORS.with(|injectors| injectors.borrow_mut().insert(registration.id(), inject));
+        }
+
+        debug
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{children, component, roots, InjectError, InspectOptions, Registration};
+
+    #[test]
+    fn tree() {
+        super::set_enabled(true);
+        let parent = Registration::new("Parent");
+        let child = {
+            let _current = parent.enter();
+            Registration::new("Child")
+        };
+        let child_id = child.id();
+
+        assert_eq!(component(child_id).unwrap().parent, Some(parent.id()));
+        assert_eq!(children(parent.id())[0].type_name, "Child");
+
+        drop(parent);
+        assert!(roots().iter().any(|info| info.id == child_id));
+
+        drop(child);
+        assert!(component(child_id).is_none());
+    }
+
+    #[test]
+    fn inject() {
+        super::set_enabled(true);
+        let registration = Registration::new("Component");
+        let (sender, receiver) = crate::channel();
+
+        let mut options = InspectOptions::<(), u8>::default();
+        options.parse_input(str::parse::<u8>);
+        options.install(&registration, sender);
+
+        super::inject_input(registration.id(), "42").unwrap();
+        assert_eq!(receiver.0.try_recv(), Ok(42));
+
+        assert!(matches!(
+            super::inject_input(registration.id(), "x"),
+            Err(InjectError::Parse(_))
+        ));
+
+        let id = registration.id();
+        drop(registration);
+        assert_eq!(super::inject_input(id, "1"), Err(InjectError::NotFound));
+    }
+}
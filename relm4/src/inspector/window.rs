@@ -0,0 +1,273 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use gtk::glib;
+use gtk::prelude::{
+    BoxExt, ButtonExt, EditableExt, EntryExt, GtkWindowExt, TextBufferExt, TextViewExt, WidgetExt,
+};
+
+use super::{ComponentId, ComponentInfo};
+
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Opens a window that shows the component tree of the application.
+///
+/// The window refreshes itself while it's open.
+/// Selecting a component shows its statistics and the [`Debug`] output of its model.
+/// Components that accept input from text can receive messages from the entry at the bottom.
+///
+/// This [enables](super::set_enabled) the registry, but only components
+/// that were launched while it was enabled are shown.
+pub fn open_window() -> gtk::Window {
+    super::set_enabled(true);
+
+    let window = gtk::Window::builder()
+        .title("Relm4 Inspector")
+        .default_width(900)
+        .default_height(600)
+        .build();
+
+    let list = gtk::ListBox::new();
+    let details = gtk::Label::builder()
+        .xalign(0.0)
+        .selectable(true)
+        .wrap(true)
+        .build();
+    let model = gtk::TextView::builder()
+        .editable(false)
+        .monospace(true)
+        .vexpand(true)
+        .build();
+    let entry = gtk::Entry::builder()
+        .placeholder_text("Input message")
+        .hexpand(true)
+        .build();
+    let send = gtk::Button::with_label("Send");
+    let status = gtk::Label::builder().xalign(0.0).wrap(true).build();
+
+    let input_row = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+    input_row.append(&entry);
+    input_row.append(&send);
+
+    let side = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(6)
+        .margin_top(6)
+        .margin_bottom(6)
+        .margin_start(6)
+        .margin_end(6)
+        .build();
+    side.append(&details);
+    side.append(
+        &gtk::ScrolledWindow::builder()
+            .child(&model)
+            .vexpand(true)
+            .build(),
+    );
+    side.append(&input_row);
+    side.append(&status);
+
+    let paned = gtk::Paned::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .start_child(
+            &gtk::ScrolledWindow::builder()
+                .child(&list)
+                .min_content_width(300)
+                .build(),
+        )
+        .end_child(&side)
+        .build();
+    window.set_child(Some(&paned));
+
+    let inspector = Rc::new(Inspector {
+        list,
+        details,
+        model,
+        status,
+        rows: RefCell::default(),
+        selected: RefCell::default(),
+        model_text: RefCell::default(),
+    });
+
+    inspector.list.connect_row_selected({
+        let inspector = Rc::downgrade(&inspector);
+        move |_, row| {
+            if let Some(inspector) = inspector.upgrade() {
+                let id = row.and_then(|row| {
+                    let index = usize::try_from(row.index()).ok()?;
+                    inspector.rows.borrow().get(index).copied()
+                });
+                *inspector.selected.borrow_mut() = id;
+                inspector.status.set_text("");
+                inspector.show_details();
+            }
+        }
+    });
+
+    let inject = {
+        let inspector = Rc::downgrade(&inspector);
+        let entry = entry.clone();
+        move || {
+            if let Some(inspector) = inspector.upgrade() {
+                inspector.inject(&entry);
+            }
+        }
+    };
+    entry.connect_activate({
+        let inject = inject.clone();
+        move |_| inject()
+    });
+    send.connect_clicked(move |_| inject());
+
+    inspector.refresh();
+    glib::timeout_add_local(REFRESH_INTERVAL, {
+        let inspector = Rc::downgrade(&inspector);
+        move || match inspector.upgrade() {
+            Some(inspector) => {
+                if inspector.list.is_mapped() {
+                    inspector.refresh();
+                }
+                glib::Continue(true)
+            }
+            None => glib::Continue(false),
+        }
+    });
+
+    // The window owns the state of the inspector.
+    window.connect_destroy(move |_| {
+        inspector.rows.take();
+    });
+
+    window.present();
+    window
+}
+
+struct Inspector {
+    list: gtk::ListBox,
+    details: gtk::Label,
+    model: gtk::TextView,
+    status: gtk::Label,
+    /// The components in the order of the rows.
+    rows: RefCell<Vec<ComponentId>>,
+    selected: RefCell<Option<ComponentId>>,
+    /// The text of the model view, which is only replaced if it changed
+    /// so the scroll position and selection are kept.
+    model_text: RefCell<String>,
+}
+
+impl Inspector {
+    /// Rebuilds the rows if components were added or removed
+    /// and updates the details of the selected component.
+    fn refresh(&self) {
+        let mut tree = Vec::new();
+        let components = super::components();
+        for root in super::roots() {
+            flatten(&components, &root, 0, &mut tree);
+        }
+
+        let ids: Vec<ComponentId> = tree.iter().map(|(info, _)| info.id).collect();
+        if *self.rows.borrow() != ids {
+            let selected = *self.selected.borrow();
+
+            *self.rows.borrow_mut() = Vec::new();
+            while let Some(child) = self.list.first_child() {
+                self.list.remove(&child);
+            }
+
+            for &(ref info, depth) in &tree {
+                let label = gtk::Label::builder()
+                    .label(format!("{} {}", short_name(info.type_name), info.id))
+                    .tooltip_text(info.type_name)
+                    .xalign(0.0)
+                    .margin_start(6 + 16 * depth)
+                    .margin_end(6)
+                    .build();
+                self.list.append(&label);
+            }
+            *self.rows.borrow_mut() = ids;
+
+            let index =
+                selected.and_then(|id| self.rows.borrow().iter().position(|row| *row == id));
+            let row = index
+                .and_then(|index| i32::try_from(index).ok())
+                .and_then(|index| self.list.row_at_index(index));
+            self.list.select_row(row.as_ref());
+        }
+
+        self.show_details();
+    }
+
+    fn show_details(&self) {
+        let info = self.selected.borrow().and_then(super::component);
+        match info {
+            Some(info) => {
+                let last_update = info
+                    .last_update
+                    .map_or_else(|| "-".to_owned(), |duration| format!("{duration:?}"));
+                let parent = info
+                    .parent
+                    .map_or_else(|| "-".to_owned(), |parent| parent.to_string());
+                self.details.set_text(&format!(
+                    "{}\nId: {}\nParent: {parent}\nMessages: {}\nCommands: {}\nLast update: {last_update}",
+                    info.type_name, info.name, info.messages, info.commands,
+                ));
+                self.set_model_text(
+                    info.model
+                        .as_deref()
+                        .unwrap_or("Launch the component with `inspect_model()` to see its model."),
+                );
+            }
+            None => {
+                self.details.set_text("No component selected");
+                self.set_model_text("");
+            }
+        }
+    }
+
+    fn set_model_text(&self, text: &str) {
+        let mut model_text = self.model_text.borrow_mut();
+        if *model_text != text {
+            self.model.buffer().set_text(text);
+            text.clone_into(&mut *model_text);
+        }
+    }
+
+    fn inject(&self, entry: &gtk::Entry) {
+        let Some(id) = *self.selected.borrow() else {
+            return;
+        };
+
+        match super::inject_input(id, &entry.text()) {
+            Ok(()) => {
+                self.status.set_text("Sent");
+                entry.set_text("");
+            }
+            Err(error) => self.status.set_text(&error.to_string()),
+        }
+    }
+}
+
+/// Appends `info` and its children to `tree` in depth-first order.
+fn flatten(
+    components: &[ComponentInfo],
+    info: &ComponentInfo,
+    depth: i32,
+    tree: &mut Vec<(ComponentInfo, i32)>,
+) {
+    tree.push((info.clone(), depth));
+    for child in components
+        .iter()
+        .filter(|child| child.parent == Some(info.id))
+    {
+        flatten(components, child, depth + 1, tree);
+    }
+}
+
+/// Strips the module path from a type name, but not from its generic parameters.
+fn short_name(type_name: &str) -> &str {
+    let end = type_name.find('<').unwrap_or(type_name.len());
+    type_name[..end]
+        .rfind("::")
+        .map_or(type_name, |start| &type_name[start + 2..])
+}
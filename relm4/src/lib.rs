@@ -37,26 +37,38 @@ mod runtime_util;
 
 pub mod actions;
 pub mod binding;
+pub mod bus;
 pub mod component;
 pub mod drawing;
 pub mod factory;
+pub mod history;
+pub mod inspector;
 pub mod loading_widgets;
+pub mod metrics;
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub mod recorder;
 pub mod safe_settings_and_actions;
 pub mod shared_state;
+pub mod test;
 pub mod typed_list_view;
+pub mod windows;
 
 pub use channel::ComponentSender;
 pub use channel::*;
+pub use component::async_worker::{
+    AsyncWorker, AsyncWorkerBuilder, AsyncWorkerController, AsyncWorkerHandle,
+};
 pub use component::worker::{Worker, WorkerController, WorkerHandle};
 pub use component::{
-    Component, ComponentBuilder, ComponentController, ComponentParts, Controller, MessageBroker,
-    SimpleComponent,
+    Component, ComponentBuilder, ComponentController, ComponentParts, Controller, ControllerMap,
+    MessageBroker, SimpleComponent,
 };
 pub use extensions::*;
 pub use shared_state::{Reducer, Reducible, SharedState};
 pub use shutdown::ShutdownReceiver;
 
-pub use app::RelmApp;
+pub use app::{request_quit, CommandLine, RelmApp};
 pub use tokio::task::JoinHandle;
 
 use gtk::prelude::{Cast, IsA};
@@ -0,0 +1,529 @@
+//! Performance metrics of components.
+//!
+//! Once [enabled](set_enabled), the runtime collects metrics for each component type:
+//! how many messages were processed, how long updates and view updates took,
+//! how many input messages were queued and how many commands are running.
+//!
+//! Collecting metrics is disabled by default, so applications that
+//! don't use them only pay for checking a flag per message.
+//!
+//! ```no_run
+//! relm4::metrics::set_enabled(true);
+//!
+//! // ...
+//!
+//! // Find the component type with the slowest updates.
+//! let slowest = relm4::metrics::snapshot()
+//!     .into_iter()
+//!     .max_by(|a, b| a.update_time.mean().total_cmp(&b.update_time.mean()));
+//!
+//! // Or export all metrics, for example to a file or an HTTP endpoint.
+//! let text = relm4::metrics::to_prometheus();
+//! ```
+//!
+//! Components that don't use [batched view updates](crate::ComponentBuilder::batch_view_updates)
+//! update their model and view in one step, so the time
+//! of their view updates is part of the update time.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+
+static METRICS: Lazy<Mutex<BTreeMap<&'static str, ComponentMetrics>>> = Lazy::new(Mutex::default);
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Upper bounds of the buckets for durations in seconds.
+const DURATION_BUCKETS: &[f64] = &[
+    0.000_1, 0.000_5, 0.001, 0.002_5, 0.005, 0.01, 0.016, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0,
+];
+
+/// Upper bounds of the buckets for queue lengths.
+const QUEUE_BUCKETS: &[f64] = &[0.0, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 500.0, 1000.0];
+
+/// A histogram with fixed buckets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    bounds: &'static [f64],
+    /// The number of samples per bucket with an additional bucket for larger samples.
+    counts: Vec<u64>,
+    sum: f64,
+    max: f64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            counts: vec![0; bounds.len() + 1],
+            sum: 0.0,
+            max: 0.0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        let bucket = self.bounds.partition_point(|bound| *bound < value);
+        self.counts[bucket] += 1;
+        self.sum += value;
+        self.max = self.max.max(value);
+    }
+
+    /// The number of samples.
+    #[must_use]
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// The sum of all samples.
+    #[must_use]
+    pub const fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    /// The largest sample.
+    #[must_use]
+    pub const fn max(&self) -> f64 {
+        self.max
+    }
+
+    /// The mean of all samples or zero if there are no samples.
+    #[must_use]
+    pub fn mean(&self) -> f64 {
+        match self.count() {
+            0 => 0.0,
+            count => self.sum / count as f64,
+        }
+    }
+
+    /// Returns the upper bound of each bucket together with the number
+    /// of samples that are less than or equal to that bound.
+    ///
+    /// The last bucket has the bound [`f64::INFINITY`] and contains all samples.
+    pub fn buckets(&self) -> impl Iterator<Item = (f64, u64)> + '_ {
+        let bounds = self.bounds.iter().copied().chain(Some(f64::INFINITY));
+        bounds.zip(self.counts.iter().scan(0, |total, count| {
+            *total += count;
+            Some(*total)
+        }))
+    }
+}
+
+/// The metrics of all components of one type.
+///
+/// Durations are measured in seconds.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ComponentMetrics {
+    /// The type name of the components.
+    pub type_name: &'static str,
+    /// The number of running components.
+    pub components: u64,
+    /// The number of input messages that were processed.
+    pub messages: u64,
+    /// The number of command outputs that were processed.
+    pub commands: u64,
+    /// The duration of updates.
+    pub update_time: Histogram,
+    /// The duration of view updates that ran separately from updates.
+    pub view_time: Histogram,
+    /// The number of input messages that were still queued
+    /// when the processing of an input message started.
+    pub queue_length: Histogram,
+    /// The number of commands that are currently running.
+    pub commands_in_flight: u64,
+}
+
+impl ComponentMetrics {
+    fn new(type_name: &'static str) -> Self {
+        Self {
+            type_name,
+            components: 0,
+            messages: 0,
+            commands: 0,
+            update_time: Histogram::new(DURATION_BUCKETS),
+            view_time: Histogram::new(DURATION_BUCKETS),
+            queue_length: Histogram::new(QUEUE_BUCKETS),
+            commands_in_flight: 0,
+        }
+    }
+}
+
+fn metrics() -> MutexGuard<'static, BTreeMap<&'static str, ComponentMetrics>> {
+    METRICS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Applies `func` to the metrics of `type_name` if metrics are enabled.
+///
+/// Returns `true` if `func` was applied.
+fn record<F: FnOnce(&mut ComponentMetrics)>(type_name: &'static str, func: F) -> bool {
+    let enabled = ENABLED.load(Ordering::Relaxed);
+    if enabled {
+        update(type_name, func);
+    }
+    enabled
+}
+
+/// Applies `func` to the metrics of `type_name`, even if metrics are disabled.
+fn update<F: FnOnce(&mut ComponentMetrics)>(type_name: &'static str, func: F) {
+    func(
+        metrics()
+            .entry(type_name)
+            .or_insert_with(|| ComponentMetrics::new(type_name)),
+    );
+}
+
+/// Enables or disables collecting metrics.
+///
+/// Metrics are not collected by default.
+/// The number of running components and commands only counts
+/// components and commands that were started while metrics were enabled.
+/// They're still subtracted once they stop, even if metrics were disabled meanwhile.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns the metrics of all component types, sorted by type name.
+#[must_use]
+pub fn snapshot() -> Vec<ComponentMetrics> {
+    metrics().values().cloned().collect()
+}
+
+/// Returns the metrics of the component type `C`.
+#[must_use]
+pub fn component<C: ?Sized>() -> Option<ComponentMetrics> {
+    metrics().get(std::any::type_name::<C>()).cloned()
+}
+
+/// Resets all counters and histograms.
+///
+/// The number of running components and commands is kept.
+pub fn reset() {
+    for metrics in metrics().values_mut() {
+        *metrics = ComponentMetrics {
+            components: metrics.components,
+            commands_in_flight: metrics.commands_in_flight,
+            ..ComponentMetrics::new(metrics.type_name)
+        };
+    }
+}
+
+/// Exports all metrics in the Prometheus text format.
+#[must_use]
+pub fn to_prometheus() -> String {
+    let snapshot = snapshot();
+    let mut out = String::new();
+
+    let gauges: [(&str, &str, &str, fn(&ComponentMetrics) -> u64); 4] = [
+        (
+            "relm4_components",
+            "gauge",
+            "Number of running components.",
+            |m| m.components,
+        ),
+        (
+            "relm4_messages_total",
+            "counter",
+            "Input messages processed by components.",
+            |m| m.messages,
+        ),
+        (
+            "relm4_commands_total",
+            "counter",
+            "Command outputs processed by components.",
+            |m| m.commands,
+        ),
+        (
+            "relm4_commands_in_flight",
+            "gauge",
+            "Commands that are currently running.",
+            |m| m.commands_in_flight,
+        ),
+    ];
+    for (name, kind, help, value) in gauges {
+        writeln!(out, "# HELP {name} {help}\n# TYPE {name} {kind}").unwrap();
+        for metrics in &snapshot {
+            let label = escape_label(metrics.type_name);
+            writeln!(out, "{name}{{component=\"{label}\"}} {}", value(metrics)).unwrap();
+        }
+    }
+
+    let histograms: [(&str, &str, fn(&ComponentMetrics) -> &Histogram); 3] = [
+        (
+            "relm4_update_seconds",
+            "Duration of component updates.",
+            |m| &m.update_time,
+        ),
+        (
+            "relm4_view_seconds",
+            "Duration of separate view updates.",
+            |m| &m.view_time,
+        ),
+        (
+            "relm4_queue_length",
+            "Queued input messages when an input message is processed.",
+            |m| &m.queue_length,
+        ),
+    ];
+    for (name, help, histogram) in histograms {
+        writeln!(out, "# HELP {name} {help}\n# TYPE {name} histogram").unwrap();
+        for metrics in &snapshot {
+            let label = escape_label(metrics.type_name);
+            let histogram = histogram(metrics);
+            for (bound, count) in histogram.buckets() {
+                let bound = if bound.is_infinite() {
+                    "+Inf".to_owned()
+                } else {
+                    bound.to_string()
+                };
+                writeln!(
+                    out,
+                    "{name}_bucket{{component=\"{label}\",le=\"{bound}\"}} {count}"
+                )
+                .unwrap();
+            }
+            writeln!(
+                out,
+                "{name}_sum{{component=\"{label}\"}} {}",
+                histogram.sum()
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "{name}_count{{component=\"{label}\"}} {}",
+                histogram.count()
+            )
+            .unwrap();
+        }
+    }
+
+    out
+}
+
+/// Exports all metrics as JSON.
+///
+/// The result is an array with one object per component type.
+/// Histograms contain their sum, count, maximum and
+/// cumulative bucket counts keyed by their upper bound.
+#[must_use]
+pub fn to_json() -> String {
+    let mut out = String::from("[");
+
+    for (index, metrics) in snapshot().iter().enumerate() {
+        if index > 0 {
+            out.push(',');
+        }
+        write!(
+            out,
+            "{{\"type_name\":\"{}\",\"components\":{},\"messages\":{},\"commands\":{},\"commands_in_flight\":{}",
+            escape_json(metrics.type_name),
+            metrics.components,
+            metrics.messages,
+            metrics.commands,
+            metrics.commands_in_flight,
+        )
+        .unwrap();
+
+        for (name, histogram) in [
+            ("update_time", &metrics.update_time),
+            ("view_time", &metrics.view_time),
+            ("queue_length", &metrics.queue_length),
+        ] {
+            write!(
+                out,
+                ",\"{name}\":{{\"count\":{},\"sum\":{},\"max\":{},\"buckets\":{{",
+                histogram.count(),
+                histogram.sum(),
+                histogram.max(),
+            )
+            .unwrap();
+            for (index, (bound, count)) in histogram.buckets().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                if bound.is_infinite() {
+                    write!(out, "\"+Inf\":{count}").unwrap();
+                } else {
+                    write!(out, "\"{bound}\":{count}").unwrap();
+                }
+            }
+            out.push_str("}}");
+        }
+
+        out.push('}');
+    }
+
+    out.push(']');
+    out
+}
+
+/// Escapes backslashes, quotes and line breaks in a Prometheus label value.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Escapes backslashes, quotes and control characters in a JSON string.
+fn escape_json(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c < '\u{20}' => write!(escaped, "\\u{:04x}", u32::from(c)).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Counts a running component.
+///
+/// Returns `true` if the component was counted, so it must be
+/// passed to [`component_stopped`] once it stops.
+pub(crate) fn component_started(type_name: &'static str) -> bool {
+    record(type_name, |metrics| metrics.components += 1)
+}
+
+/// Stops counting a component that was counted by [`component_started`].
+pub(crate) fn component_stopped(type_name: &'static str) {
+    update(type_name, |metrics| metrics.components -= 1);
+}
+
+pub(crate) fn record_input(type_name: &'static str, queue_length: usize) {
+    record(type_name, |metrics| {
+        metrics.messages += 1;
+        metrics.queue_length.observe(queue_length as f64);
+    });
+}
+
+pub(crate) fn record_command(type_name: &'static str) {
+    record(type_name, |metrics| metrics.commands += 1);
+}
+
+pub(crate) fn record_update(type_name: &'static str, duration: Duration) {
+    record(type_name, |metrics| {
+        metrics.update_time.observe(duration.as_secs_f64());
+    });
+}
+
+pub(crate) fn record_view(type_name: &'static str, duration: Duration) {
+    record(type_name, |metrics| {
+        metrics.view_time.observe(duration.as_secs_f64());
+    });
+}
+
+/// Counts a running command until dropped.
+#[derive(Debug)]
+pub(crate) struct CommandGuard {
+    type_name: &'static str,
+    /// Whether the command was counted because metrics were enabled.
+    counted: bool,
+}
+
+impl CommandGuard {
+    pub(crate) fn new(type_name: &'static str) -> Self {
+        let counted = record(type_name, |metrics| metrics.commands_in_flight += 1);
+        Self { type_name, counted }
+    }
+}
+
+impl Drop for CommandGuard {
+    fn drop(&mut self) {
+        if self.counted {
+            update(self.type_name, |metrics| metrics.commands_in_flight -= 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{CommandGuard, Histogram, DURATION_BUCKETS};
+
+    struct Counted;
+
+    #[test]
+    fn histogram() {
+        let mut histogram = Histogram::new(&[1.0, 2.0]);
+        for value in [0.5, 1.0, 1.5, 3.0] {
+            histogram.observe(value);
+        }
+
+        assert_eq!(histogram.count(), 4);
+        assert_eq!(histogram.max(), 3.0);
+        let buckets: Vec<_> = histogram.buckets().collect();
+        assert_eq!(buckets, vec![(1.0, 2), (2.0, 3), (f64::INFINITY, 4)]);
+    }
+
+    #[test]
+    fn export() {
+        super::set_enabled(true);
+        let type_name = std::any::type_name::<Counted>();
+        super::record_input(type_name, 3);
+        super::record_update(type_name, Duration::from_millis(2));
+        let guard = CommandGuard::new(type_name);
+
+        let metrics = super::component::<Counted>().unwrap();
+        assert_eq!(metrics.messages, 1);
+        assert_eq!(metrics.commands_in_flight, 1);
+        assert_eq!(
+            metrics.update_time.buckets().count(),
+            DURATION_BUCKETS.len() + 1
+        );
+
+        drop(guard);
+        assert_eq!(super::component::<Counted>().unwrap().commands_in_flight, 0);
+
+        let text = super::to_prometheus();
+        assert!(text.contains(&format!(
+            "relm4_messages_total{{component=\"{type_name}\"}} 1"
+        )));
+        assert!(text.contains(&format!(
+            "relm4_queue_length_bucket{{component=\"{type_name}\",le=\"5\"}} 1"
+        )));
+
+        let json = super::to_json();
+        assert!(json.contains(&format!(
+            "\"type_name\":\"{type_name}\",\"components\":0,\"messages\":1"
+        )));
+    }
+
+    #[test]
+    fn uncounted_commands() {
+        struct Uncounted;
+
+        super::set_enabled(true);
+        let type_name = std::any::type_name::<Uncounted>();
+        let counted = CommandGuard::new(type_name);
+
+        // Started while metrics were disabled.
+        drop(CommandGuard {
+            type_name,
+            counted: false,
+        });
+        let metrics = super::component::<Uncounted>().unwrap();
+        assert_eq!(metrics.commands_in_flight, 1);
+
+        drop(counted);
+        let metrics = super::component::<Uncounted>().unwrap();
+        assert_eq!(metrics.commands_in_flight, 0);
+    }
+
+    #[test]
+    fn escape() {
+        assert_eq!(super::escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+        assert_eq!(
+            super::escape_json("a\"b\t\u{0}\u{1f}"),
+            "a\\\"b\\t\\u0000\\u001f"
+        );
+    }
+}
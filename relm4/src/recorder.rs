@@ -0,0 +1,431 @@
+//! Record and replay the message traffic of components.
+//!
+//! A recording contains every input, command output and output message
+//! that passed through the runtime of a component, together with the time
+//! at which it was handled.
+//! Replaying a recording feeds the recorded inputs and command outputs to the
+//! component instead of the messages it produces for itself.
+//! Recordings are stored as [JSON Lines](https://jsonlines.org/), so they can
+//! be attached to bug reports and inspected with regular text tools.
+//!
+//! ```no_run
+//! # use relm4::prelude::*;
+//! # use relm4::recorder::Recording;
+//! # type App = ();
+//! // On the machine of the user:
+//! let connector = App::builder()
+//!     .record_to("trace.jsonl")
+//!     .expect("Couldn't create trace file")
+//!     .launch(());
+//!
+//! // Later, when reproducing the bug:
+//! let recording = Recording::load("trace.jsonl").expect("Couldn't load trace file");
+//! let connector = App::builder().launch_replay((), recording);
+//! ```
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::component::{AsyncComponent, AsyncComponentBuilder, AsyncConnector};
+use crate::runtime_util::RuntimeHooks;
+use crate::{Component, ComponentBuilder, Connector, Sender};
+
+/// The kind of a recorded message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageKind {
+    /// An input message of the component.
+    Input,
+    /// A command output handled by the component.
+    Command,
+    /// An output message sent by the component.
+    Output,
+}
+
+/// A message that was recorded from the runtime of a component.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordedMessage<Input, Command, Output> {
+    /// An input message of the component.
+    Input(Input),
+    /// A command output handled by the component.
+    Command(Command),
+    /// An output message sent by the component.
+    Output(Output),
+}
+
+/// A recorded message together with the time at which it was handled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedEvent<Input, Command, Output> {
+    /// Time since the component was launched.
+    pub elapsed: Duration,
+    /// The recorded message.
+    pub message: RecordedMessage<Input, Command, Output>,
+}
+
+/// The format of a single line in a recording.
+#[derive(Serialize, Deserialize)]
+struct Line<T> {
+    elapsed_us: u64,
+    kind: MessageKind,
+    message: T,
+}
+
+/// Writes the messages of a component to a file.
+#[derive(Debug)]
+struct Recorder {
+    writer: RefCell<BufWriter<File>>,
+    start: Instant,
+}
+
+impl Recorder {
+    fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            writer: RefCell::new(BufWriter::new(File::create(path)?)),
+            start: Instant::now(),
+        })
+    }
+
+    fn write<T: Serialize>(&self, kind: MessageKind, message: &T) {
+        let line = Line {
+            elapsed_us: u64::try_from(self.start.elapsed().as_micros()).unwrap_or(u64::MAX),
+            kind,
+            message,
+        };
+
+        let writer = &mut *self.writer.borrow_mut();
+        let result = serde_json::to_writer(&mut *writer, &line)
+            .map_err(io::Error::from)
+            .and_then(|_| writer.write_all(b"\n"))
+            // Flush every message to keep the trace in case the application crashes.
+            .and_then(|_| writer.flush());
+
+        if let Err(err) = result {
+            tracing::error!("Couldn't record message: {}", err);
+        }
+    }
+
+    fn register<Input, Command, Output>(self, hooks: &mut RuntimeHooks<Input, Command, Output>)
+    where
+        Input: Serialize,
+        Command: Serialize,
+        Output: Serialize,
+    {
+        let recorder = Rc::new(self);
+
+        let input_recorder = recorder.clone();
+        hooks.on_input(move |message| input_recorder.write(MessageKind::Input, message));
+
+        let command_recorder = recorder.clone();
+        hooks.on_command(move |message| command_recorder.write(MessageKind::Command, message));
+
+        hooks.on_output(move |message| recorder.write(MessageKind::Output, message));
+    }
+}
+
+/// The messages that were recorded from a component.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recording<Input, Command, Output> {
+    /// All recorded events in the order in which they were handled.
+    pub events: Vec<RecordedEvent<Input, Command, Output>>,
+}
+
+impl<Input, Command, Output> Recording<Input, Command, Output>
+where
+    Input: DeserializeOwned,
+    Command: DeserializeOwned,
+    Output: DeserializeOwned,
+{
+    /// Loads a recording from a file that was written by
+    /// [`ComponentBuilder::record_to`] or [`AsyncComponentBuilder::record_to`].
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Reads a recording line by line.
+    pub fn from_reader(reader: impl BufRead) -> io::Result<Self> {
+        let mut events = Vec::new();
+
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let Line {
+                elapsed_us,
+                kind,
+                message,
+            } = serde_json::from_str::<Line<serde_json::Value>>(&line)?;
+
+            let message = match kind {
+                MessageKind::Input => RecordedMessage::Input(serde_json::from_value(message)?),
+                MessageKind::Command => RecordedMessage::Command(serde_json::from_value(message)?),
+                MessageKind::Output => RecordedMessage::Output(serde_json::from_value(message)?),
+            };
+
+            events.push(RecordedEvent {
+                elapsed: Duration::from_micros(elapsed_us),
+                message,
+            });
+        }
+
+        Ok(Self { events })
+    }
+}
+
+impl<Input, Command, Output> Recording<Input, Command, Output> {
+    /// Returns the recorded input messages together with the time
+    /// at which they were handled.
+    pub fn into_inputs(self) -> impl Iterator<Item = (Duration, Input)> {
+        self.events
+            .into_iter()
+            .filter_map(|event| match event.message {
+                RecordedMessage::Input(input) => Some((event.elapsed, input)),
+                _ => None,
+            })
+    }
+}
+
+/// Sends the recorded inputs and command outputs with the recorded timing.
+fn replay<Input, Command, Output>(
+    recording: Recording<Input, Command, Output>,
+    input_sender: Sender<Input>,
+    command_sender: Sender<Command>,
+) where
+    Input: 'static,
+    Command: 'static,
+    Output: 'static,
+{
+    crate::spawn_local(async move {
+        let start = Instant::now();
+        for event in recording.events {
+            if let Some(delay) = event.elapsed.checked_sub(start.elapsed()) {
+                gtk::glib::timeout_future(delay).await;
+            }
+            let sent = match event.message {
+                RecordedMessage::Input(input) => input_sender.send(input).is_ok(),
+                RecordedMessage::Command(command) => command_sender.send(command).is_ok(),
+                RecordedMessage::Output(_) => true,
+            };
+            if !sent {
+                tracing::warn!("Component was shut down during replay");
+                break;
+            }
+        }
+    });
+}
+
+impl<C: Component> ComponentBuilder<C>
+where
+    C::Input: Serialize,
+    C::CommandOutput: Serialize,
+    C::Output: Serialize,
+{
+    /// Records all messages handled by the component to a file.
+    ///
+    /// The recording can be loaded with [`Recording::load`] and
+    /// replayed with [`launch_replay`](ComponentBuilder::launch_replay).
+    pub fn record_to(mut self, path: impl AsRef<Path>) -> io::Result<Self> {
+        Recorder::create(path.as_ref())?.register(&mut self.hooks);
+        Ok(self)
+    }
+}
+
+impl<C: Component> ComponentBuilder<C> {
+    /// Launches the component and sends the recorded input messages
+    /// and command outputs to it, preserving the timing of the recording.
+    ///
+    /// The recording replaces all messages the component would produce for itself:
+    /// inputs it sends with its own sender and the outputs of its commands are discarded,
+    /// so they aren't handled twice and nondeterministic commands are reproduced exactly.
+    /// Commands are still started, so their side effects happen again.
+    /// Output messages of the recording are ignored because they are produced
+    /// again by the component.
+    ///
+    /// Inputs sent through the returned connector are handled in addition to
+    /// the recording, so the component shouldn't receive messages from other
+    /// sources during a replay.
+    pub fn launch_replay(
+        mut self,
+        payload: C::Init,
+        recording: Recording<C::Input, C::CommandOutput, C::Output>,
+    ) -> Connector<C> {
+        self.hooks
+            .replace_sources(move |input, command| replay(recording, input, command));
+        self.launch(payload)
+    }
+}
+
+impl<C: AsyncComponent> AsyncComponentBuilder<C>
+where
+    C::Input: Serialize,
+    C::CommandOutput: Serialize,
+    C::Output: Serialize,
+{
+    /// Records all messages handled by the component to a file.
+    ///
+    /// The recording can be loaded with [`Recording::load`] and
+    /// replayed with [`launch_replay`](AsyncComponentBuilder::launch_replay).
+    pub fn record_to(mut self, path: impl AsRef<Path>) -> io::Result<Self> {
+        Recorder::create(path.as_ref())?.register(&mut self.hooks);
+        Ok(self)
+    }
+}
+
+impl<C: AsyncComponent> AsyncComponentBuilder<C> {
+    /// Launches the component and sends the recorded input messages
+    /// and command outputs to it, preserving the timing of the recording.
+    ///
+    /// The recording replaces all messages the component would produce for itself:
+    /// inputs it sends with its own sender and the outputs of its commands are discarded,
+    /// so they aren't handled twice and nondeterministic commands are reproduced exactly.
+    /// Commands are still started, so their side effects happen again.
+    /// Output messages of the recording are ignored because they are produced
+    /// again by the component.
+    ///
+    /// Inputs sent through the returned connector are handled in addition to
+    /// the recording, so the component shouldn't receive messages from other
+    /// sources during a replay.
+    pub fn launch_replay(
+        mut self,
+        payload: C::Init,
+        recording: Recording<C::Input, C::CommandOutput, C::Output>,
+    ) -> AsyncConnector<C> {
+        self.hooks
+            .replace_sources(move |input, command| replay(recording, input, command));
+        self.launch(payload)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{replay, MessageKind, RecordedEvent, RecordedMessage, Recording};
+    use crate::test::ComponentHarness;
+    use crate::{Component, ComponentParts, ComponentSender};
+
+    #[test]
+    fn parse_recording() {
+        let trace = concat!(
+            r#"{"elapsed_us":10,"kind":"input","message":"Start"}"#,
+            "\n",
+            r#"{"elapsed_us":20,"kind":"command","message":5}"#,
+            "\n\n",
+            r#"{"elapsed_us":30,"kind":"output","message":[1,2]}"#,
+            "\n",
+        );
+
+        let recording: Recording<String, u8, Vec<u8>> =
+            Recording::from_reader(trace.as_bytes()).unwrap();
+
+        assert_eq!(
+            recording.events,
+            vec![
+                RecordedEvent {
+                    elapsed: Duration::from_micros(10),
+                    message: RecordedMessage::Input("Start".to_owned()),
+                },
+                RecordedEvent {
+                    elapsed: Duration::from_micros(20),
+                    message: RecordedMessage::Command(5),
+                },
+                RecordedEvent {
+                    elapsed: Duration::from_micros(30),
+                    message: RecordedMessage::Output(vec![1, 2]),
+                },
+            ]
+        );
+
+        let inputs: Vec<_> = recording.into_inputs().collect();
+        assert_eq!(
+            inputs,
+            vec![(Duration::from_micros(10), "Start".to_owned())]
+        );
+    }
+
+    #[test]
+    fn kind_names() {
+        assert_eq!(
+            serde_json::to_string(&MessageKind::Command).unwrap(),
+            r#""command""#
+        );
+    }
+
+    #[derive(Debug)]
+    struct Sum(u8);
+
+    #[derive(Debug)]
+    enum SumMsg {
+        Start,
+        Add(u8),
+    }
+
+    impl Component for Sum {
+        type CommandOutput = u8;
+        type Input = SumMsg;
+        type Output = ();
+        type Init = ();
+        type Root = ();
+        type Widgets = ();
+
+        fn init_root() -> Self::Root {}
+
+        fn init(_: (), _: &(), _: ComponentSender<Self>) -> ComponentParts<Self> {
+            ComponentParts {
+                model: Sum(0),
+                widgets: (),
+            }
+        }
+
+        fn update(&mut self, message: SumMsg, sender: ComponentSender<Self>, _: &()) {
+            match message {
+                SumMsg::Start => {
+                    sender.input(SumMsg::Add(1));
+                    sender.oneshot_command(async { 10 });
+                }
+                SumMsg::Add(value) => self.0 += value,
+            }
+        }
+
+        fn update_cmd(&mut self, value: u8, _: ComponentSender<Self>, _: &()) {
+            self.0 += value;
+        }
+    }
+
+    #[gtk::test]
+    fn replay_replaces_own_messages() {
+        let event = |message| RecordedEvent {
+            elapsed: Duration::ZERO,
+            message,
+        };
+        let recording: Recording<SumMsg, u8, ()> = Recording {
+            events: vec![
+                event(RecordedMessage::Input(SumMsg::Start)),
+                event(RecordedMessage::Input(SumMsg::Add(1))),
+                event(RecordedMessage::Command(5)),
+                event(RecordedMessage::Output(())),
+            ],
+        };
+
+        let mut builder = Sum::builder();
+        builder
+            .hooks
+            .replace_sources(move |input, command| replay(recording, input, command));
+        let harness = ComponentHarness::launch_with(builder, ());
+
+        assert!(harness.settle_until(Duration::from_secs(5), |h| h.model().0 == 6));
+        assert_eq!(harness.take_command_outputs(), vec!["5".to_owned()]);
+
+        // The input the component sent to itself was discarded.
+        harness.settle();
+        assert_eq!(harness.model().0, 6);
+    }
+}
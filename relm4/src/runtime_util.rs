@@ -1,11 +1,18 @@
+use std::fmt;
+use std::pin::Pin;
 use std::task::Poll;
+use std::time::Duration;
 
 use flume::r#async::RecvStream;
-use futures::{future::FusedFuture, pin_mut, Future, Stream};
+use futures::future::{Fuse, FusedFuture};
+use futures::{pin_mut, Future, FutureExt, Stream};
+use gtk::glib;
 use once_cell::sync::Lazy;
 use std::sync::Mutex;
 use tokio::sync::mpsc;
 
+use crate::channel::ReceiverToken;
+use crate::inspector::Registration;
 use crate::{
     shutdown::{self, ShutdownSender},
     Receiver, Sender, ShutdownReceiver,
@@ -25,12 +32,36 @@ pub(crate) fn shutdown_all() {
     }
 }
 
+/// Keeps the main context running until all shutdown handlers of
+/// commands completed or the grace period elapsed.
+pub(crate) fn wait_for_shutdown(grace_period: Duration) {
+    if grace_period.is_zero() {
+        return;
+    }
+
+    let context = glib::MainContext::ref_thread_default();
+    let start = std::time::Instant::now();
+
+    while shutdown::pending() > 0 {
+        if start.elapsed() >= grace_period {
+            tracing::warn!(
+                "{} shutdown handler(s) didn't complete within the grace period",
+                shutdown::pending()
+            );
+            break;
+        }
+
+        while context.iteration(false) {}
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
+
 /// A type that destroys an [`AsyncComponent`](crate::async_component::AsyncComponent)
 /// as soon as it is dropped.
 #[derive(Debug)]
 pub(super) struct ShutdownOnDrop {
-    /// Sender used to indicate that the async component should shut down.
-    shutdown_event_sender: Option<mpsc::Sender<()>>,
+    /// Senders used to indicate that the async components should shut down.
+    shutdown_event_senders: Vec<mpsc::Sender<()>>,
 }
 
 impl ShutdownOnDrop {
@@ -39,18 +70,30 @@ impl ShutdownOnDrop {
     /// When this type is dropped, a message will be sent through the channel.
     pub(crate) fn new(shutdown_event_sender: mpsc::Sender<()>) -> Self {
         Self {
-            shutdown_event_sender: Some(shutdown_event_sender),
+            shutdown_event_senders: vec![shutdown_event_sender],
+        }
+    }
+
+    /// Combines multiple instances into one that shuts
+    /// down all runtimes at once when dropped.
+    pub(crate) fn combine<I: IntoIterator<Item = Self>>(iter: I) -> Self {
+        let shutdown_event_senders = iter
+            .into_iter()
+            .flat_map(|mut shutdown| std::mem::take(&mut shutdown.shutdown_event_senders))
+            .collect();
+        Self {
+            shutdown_event_senders,
         }
     }
 
     pub(crate) fn deactivate(&mut self) {
-        self.shutdown_event_sender = None;
+        self.shutdown_event_senders.clear();
     }
 }
 
 impl Drop for ShutdownOnDrop {
     fn drop(&mut self) {
-        if let Some(sender) = self.shutdown_event_sender.take() {
+        for sender in self.shutdown_event_senders.drain(..) {
             sender.blocking_send(()).ok();
         }
     }
@@ -65,10 +108,13 @@ pub(super) struct RuntimeSenders<Output, Command> {
     pub(super) shutdown_recipient: ShutdownReceiver,
     pub(super) shutdown_on_drop: ShutdownOnDrop,
     pub(super) shutdown_event: ShutdownEvent,
+    pub(super) registration: Registration,
 }
 
 impl<Output, Command> RuntimeSenders<Output, Command> {
-    pub(super) fn new() -> Self {
+    /// Creates the senders of a new component and registers
+    /// it in the [`inspector`](crate::inspector).
+    pub(super) fn new(type_name: &'static str) -> Self {
         // Used by this component to send events to be handled externally by the caller.
         let (output_sender, output_receiver) = crate::channel::<Output>();
 
@@ -100,10 +146,156 @@ impl<Output, Command> RuntimeSenders<Output, Command> {
             shutdown_recipient,
             shutdown_on_drop,
             shutdown_event,
+            registration: Registration::new(type_name),
         }
     }
 }
 
+type Hook<T> = Box<dyn Fn(&T)>;
+
+/// Callbacks that are invoked by the runtime of a component
+/// for every message it handles.
+///
+/// This is used internally to observe the message traffic of a
+/// component, for example by the [`test`](crate::test) utilities.
+pub(crate) struct RuntimeHooks<Input, Command, Output> {
+    input: Vec<Hook<Input>>,
+    command: Vec<Hook<Command>>,
+    output: Vec<Hook<Output>>,
+    sources: Option<Box<dyn FnOnce(Sender<Input>, Sender<Command>)>>,
+}
+
+impl<Input, Command, Output> Default for RuntimeHooks<Input, Command, Output> {
+    fn default() -> Self {
+        Self {
+            input: Vec::new(),
+            command: Vec::new(),
+            output: Vec::new(),
+            sources: None,
+        }
+    }
+}
+
+impl<Input, Command, Output> RuntimeHooks<Input, Command, Output> {
+    /// Registers a callback that is called for every input message
+    /// before it's passed to the update function.
+    pub(crate) fn on_input<F: Fn(&Input) + 'static>(&mut self, func: F) {
+        self.input.push(Box::new(func));
+    }
+
+    /// Registers a callback that is called for every command output
+    /// before it's passed to the update function.
+    pub(crate) fn on_command<F: Fn(&Command) + 'static>(&mut self, func: F) {
+        self.command.push(Box::new(func));
+    }
+
+    /// Registers a callback that is called for every output message
+    /// before it's passed to the receiver of the component.
+    pub(crate) fn on_output<F: Fn(&Output) + 'static>(&mut self, func: F) {
+        self.output.push(Box::new(func));
+    }
+
+    /// Replaces the inputs the component sends to itself and the outputs
+    /// of its commands with the messages that `func` sends to the runtime.
+    ///
+    /// This is used to replay recordings without handling
+    /// messages produced by the component a second time.
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub(crate) fn replace_sources<F>(&mut self, func: F)
+    where
+        F: FnOnce(Sender<Input>, Sender<Command>) + 'static,
+    {
+        self.sources = Some(Box::new(func));
+    }
+
+    /// Returns the input and command senders that are passed to the component.
+    ///
+    /// If the sources were replaced, the runtime senders are passed to the
+    /// replacement and the component gets senders whose messages are discarded.
+    pub(crate) fn sources(
+        &mut self,
+        input: &Sender<Input>,
+        command: Sender<Command>,
+    ) -> (Sender<Input>, Sender<Command>)
+    where
+        Input: 'static,
+        Command: 'static,
+    {
+        if let Some(func) = self.sources.take() {
+            func(input.clone(), command);
+            (discard(), discard())
+        } else {
+            (input.clone(), command)
+        }
+    }
+
+    pub(crate) fn input(&self, message: &Input) {
+        for hook in &self.input {
+            hook(message);
+        }
+    }
+
+    pub(crate) fn command(&self, message: &Command) {
+        for hook in &self.command {
+            hook(message);
+        }
+    }
+
+    pub(crate) fn queued(&self, message: &Queued<Input, Command>) {
+        match message {
+            Queued::Input(message) => self.input(message),
+            Queued::Command(message) => self.command(message),
+        }
+    }
+
+    /// Takes the output hooks and inserts them between the output
+    /// sender of the runtime and the returned receiver.
+    ///
+    /// If no output hooks are registered, the receiver is returned unchanged.
+    pub(crate) fn observe_output(&mut self, receiver: Receiver<Output>) -> Receiver<Output>
+    where
+        Output: 'static,
+    {
+        if self.output.is_empty() {
+            return receiver;
+        }
+
+        let hooks = std::mem::take(&mut self.output);
+        let (sender, observed_receiver) = crate::channel();
+
+        crate::spawn_local(async move {
+            while let Some(message) = receiver.recv().await {
+                for hook in &hooks {
+                    hook(&message);
+                }
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        observed_receiver
+    }
+}
+
+impl<Input, Command, Output> fmt::Debug for RuntimeHooks<Input, Command, Output> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RuntimeHooks")
+            .field("input", &self.input.len())
+            .field("command", &self.command.len())
+            .field("output", &self.output.len())
+            .field("sources_replaced", &self.sources.is_some())
+            .finish()
+    }
+}
+
+/// Returns a sender whose messages are dropped.
+fn discard<T: 'static>() -> Sender<T> {
+    let (sender, receiver) = crate::channel();
+    crate::spawn_local(async move { while receiver.recv().await.is_some() {} });
+    sender
+}
+
 /// A type that wraps around a shutdown event receiver.
 ///
 /// This type is a future that will only resolve if the
@@ -163,22 +355,88 @@ impl FusedFuture for ShutdownEvent {
 ///
 /// This type is a stream that will only yield items
 /// until the sender is dropped.
+///
+/// Items of the optional priority stream are always yielded first.
+/// If a coalesce function is set, consecutive items that are
+/// already queued are merged into the newest one.
+/// Without it, the receiver never takes more items from the channel than it yields.
 pub(super) struct GuardedReceiver<'a, T>
 where
     T: 'static,
 {
     receive_stream: RecvStream<'a, T>,
+    priority_stream: Option<RecvStream<'a, T>>,
+    coalesce: Option<fn(&T, &T) -> bool>,
+    lookahead: Option<T>,
     sender_dropped: bool,
+    /// Keeps the channel open for senders while the runtime is running.
+    _token: ReceiverToken,
 }
 
+// The items are never pinned, so this is fine.
+impl<'a, T> Unpin for GuardedReceiver<'a, T> {}
+
 impl<'a, T> GuardedReceiver<'a, T>
 where
     T: 'static,
 {
     pub(super) fn new(receiver: Receiver<T>) -> Self {
+        Self::with_priority(receiver, None)
+    }
+
+    pub(super) fn with_priority(receiver: Receiver<T>, priority: Option<Receiver<T>>) -> Self {
+        let Receiver(receiver, token) = receiver;
         Self {
             receive_stream: receiver.into_stream(),
+            priority_stream: priority.map(Receiver::into_stream),
+            coalesce: None,
+            lookahead: None,
             sender_dropped: false,
+            _token: token,
+        }
+    }
+
+    /// Merge queued items for which `coalesce` returns `true`.
+    pub(super) fn coalesce(mut self, coalesce: fn(&T, &T) -> bool) -> Self {
+        self.coalesce = Some(coalesce);
+        self
+    }
+
+    /// Polls the priority stream, if there is one.
+    fn poll_priority(&mut self, cx: &mut std::task::Context<'_>) -> Option<T> {
+        let stream = self.priority_stream.as_mut()?;
+        pin_mut!(stream);
+
+        match stream.poll_next(cx) {
+            Poll::Ready(Some(value)) => Some(value),
+            Poll::Ready(None) => {
+                self.priority_stream = None;
+                None
+            }
+            Poll::Pending => None,
+        }
+    }
+
+    /// Polls the streams without looking at the lookahead.
+    fn poll_streams(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Option<T>> {
+        if self.sender_dropped {
+            return Poll::Ready(None);
+        }
+
+        if let Some(value) = self.poll_priority(cx) {
+            return Poll::Ready(Some(value));
+        }
+
+        let stream = &mut self.receive_stream;
+        pin_mut!(stream);
+
+        match stream.poll_next(cx) {
+            Poll::Ready(Some(value)) => Poll::Ready(Some(value)),
+            Poll::Ready(None) => {
+                self.sender_dropped = true;
+                Poll::Ready(None)
+            }
+            Poll::Pending => Poll::Pending,
         }
     }
 }
@@ -193,26 +451,344 @@ where
         mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> Poll<Self::Output> {
-        if self.sender_dropped {
-            Poll::Pending
+        let mut value = if let Some(lookahead) = self.lookahead.take() {
+            // Priority items that arrived later still overtake the lookahead.
+            if let Some(value) = self.poll_priority(cx) {
+                self.lookahead = Some(lookahead);
+                return Poll::Ready(value);
+            }
+            lookahead
         } else {
-            let stream = &mut self.receive_stream;
-            pin_mut!(stream);
-
-            match stream.poll_next(cx) {
-                Poll::Ready(Some(value)) => Poll::Ready(value),
-                Poll::Ready(None) => {
-                    self.sender_dropped = true;
-                    Poll::Pending
+            match self.poll_streams(cx) {
+                Poll::Ready(Some(value)) => value,
+                Poll::Ready(None) | Poll::Pending => return Poll::Pending,
+            }
+        };
+
+        if let Some(coalesce) = self.coalesce {
+            while let Poll::Ready(Some(next)) = self.poll_streams(cx) {
+                if coalesce(&value, &next) {
+                    value = next;
+                } else {
+                    self.lookahead = Some(next);
+                    break;
                 }
-                Poll::Pending => Poll::Pending,
             }
         }
+
+        Poll::Ready(value)
     }
 }
 
 impl<'a, T> FusedFuture for GuardedReceiver<'a, T> {
     fn is_terminated(&self) -> bool {
-        self.sender_dropped
+        self.sender_dropped && self.lookahead.is_none()
+    }
+}
+
+impl<'a, T> GuardedReceiver<'a, T>
+where
+    T: 'static,
+{
+    /// Returns the next item if it's available without waiting.
+    pub(super) fn try_next(&mut self) -> Option<T> {
+        self.now_or_never()
+    }
+
+    /// Returns the number of items that are already queued.
+    fn len(&self) -> usize {
+        usize::from(self.lookahead.is_some())
+            + self.priority_stream.as_ref().map_or(0, RecvStream::len)
+            + self.receive_stream.len()
+    }
+}
+
+/// A message that is handled as part of a batched update.
+#[derive(Debug)]
+pub(super) enum Queued<Input, Command> {
+    /// An input message.
+    Input(Input),
+    /// The output of a command.
+    Command(Command),
+}
+
+impl<Input, Command> Queued<Input, Command>
+where
+    Input: fmt::Debug,
+    Command: fmt::Debug,
+{
+    /// Creates the span in which the model of `C` handles this message.
+    pub(super) fn span<C>(&self, id: &str) -> tracing::Span {
+        let component = std::any::type_name::<C>();
+        match self {
+            Self::Input(message) => tracing::info_span!("update", input=?message, component, id),
+            Self::Command(message) => {
+                tracing::info_span!("update_cmd", cmd_output=?message, component, id)
+            }
+        }
+    }
+}
+
+/// Returns an iterator over the messages of a batched update.
+///
+/// The iterator yields `first` followed by the messages that were already
+/// queued when the batch started. Messages sent while the batch is handled
+/// are left for the next batch, so a component that sends itself a message
+/// on every update still gets its view updated.
+pub(super) fn batch<'r, 'a, Input, Command>(
+    first: Queued<Input, Command>,
+    input: &'r mut GuardedReceiver<'a, Input>,
+    cmd: &'r mut GuardedReceiver<'a, Command>,
+) -> Batch<'r, 'a, Input, Command> {
+    let remaining = input.len() + cmd.len();
+    Batch {
+        first: Some(first),
+        input,
+        cmd,
+        remaining,
+        prefer_cmd: true,
+    }
+}
+
+/// The iterator returned by [`batch`].
+pub(super) struct Batch<'r, 'a, Input: 'static, Command: 'static> {
+    first: Option<Queued<Input, Command>>,
+    input: &'r mut GuardedReceiver<'a, Input>,
+    cmd: &'r mut GuardedReceiver<'a, Command>,
+    remaining: usize,
+    /// Alternates between both receivers so neither of them is starved.
+    prefer_cmd: bool,
+}
+
+impl<'r, 'a, Input, Command> Iterator for Batch<'r, 'a, Input, Command> {
+    type Item = Queued<Input, Command>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(first) = self.first.take() {
+            return Some(first);
+        }
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let input = &mut *self.input;
+        let cmd = &mut *self.cmd;
+        let message = if self.prefer_cmd {
+            cmd.try_next()
+                .map(Queued::Command)
+                .or_else(|| input.try_next().map(Queued::Input))
+        } else {
+            input
+                .try_next()
+                .map(Queued::Input)
+                .or_else(|| cmd.try_next().map(Queued::Command))
+        };
+
+        self.prefer_cmd = !self.prefer_cmd;
+        self.remaining -= 1;
+        message
+    }
+}
+
+/// A future that resolves when the view of a component
+/// should be updated in batched mode.
+///
+/// The view is updated from a high priority idle callback.
+/// This way, all pending messages are handled first, but the
+/// view is still updated before GTK draws the next frame.
+pub(super) struct ViewUpdate {
+    batched: bool,
+    pending: Fuse<Pin<Box<dyn Future<Output = ()> + Send + 'static>>>,
+}
+
+impl ViewUpdate {
+    pub(super) fn new(batched: bool) -> Self {
+        Self {
+            batched,
+            pending: Fuse::terminated(),
+        }
+    }
+
+    /// Whether the view should only be updated by this type.
+    pub(super) const fn is_batched(&self) -> bool {
+        self.batched
+    }
+
+    /// Schedules a view update unless one is pending already.
+    pub(super) fn schedule(&mut self) {
+        if self.pending.is_terminated() {
+            self.pending =
+                glib::timeout_future_with_priority(glib::PRIORITY_HIGH_IDLE, Duration::ZERO).fuse();
+        }
+    }
+}
+
+impl Future for ViewUpdate {
+    type Output = ();
+
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Self::Output> {
+        self.pending.poll_unpin(cx)
+    }
+}
+
+impl FusedFuture for ViewUpdate {
+    fn is_terminated(&self) -> bool {
+        self.pending.is_terminated()
+    }
+}
+
+impl fmt::Debug for ViewUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ViewUpdate")
+            .field("batched", &self.batched)
+            .field("pending", &!self.pending.is_terminated())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{batch, GuardedReceiver, Queued};
+    use crate::channel::ChannelOptions;
+    use crate::test::ComponentHarness;
+    use crate::{Component, ComponentParts, ComponentSender};
+
+    #[derive(Debug)]
+    struct Sum(u8);
+
+    /// Counts the calls of the view methods.
+    #[derive(Debug, Default)]
+    struct ViewCalls {
+        update_view: usize,
+        update_with_view: usize,
+    }
+
+    impl Component for Sum {
+        type CommandOutput = ();
+        type Input = u8;
+        type Output = ();
+        type Init = ();
+        type Root = ();
+        type Widgets = ViewCalls;
+
+        fn init_root() -> Self::Root {}
+
+        fn init(_: (), _root: &(), _sender: ComponentSender<Self>) -> ComponentParts<Self> {
+            ComponentParts {
+                model: Sum(0),
+                widgets: ViewCalls::default(),
+            }
+        }
+
+        fn update(&mut self, value: u8, _sender: ComponentSender<Self>, _root: &()) {
+            self.0 += value;
+        }
+
+        fn update_with_view(
+            &mut self,
+            widgets: &mut ViewCalls,
+            value: u8,
+            sender: ComponentSender<Self>,
+            root: &(),
+        ) {
+            widgets.update_with_view += 1;
+            self.update(value, sender.clone(), root);
+            self.update_view(widgets, sender);
+        }
+
+        fn update_view(&self, widgets: &mut ViewCalls, _sender: ComponentSender<Self>) {
+            widgets.update_view += 1;
+        }
+    }
+
+    #[test]
+    fn batch_stops_at_queued_messages() {
+        let (input_sender, input_receiver) = crate::channel::<u8>();
+        let (cmd_sender, cmd_receiver) = crate::channel::<u8>();
+        let mut input = GuardedReceiver::new(input_receiver);
+        let mut cmd = GuardedReceiver::new(cmd_receiver);
+
+        input_sender.send(1).unwrap();
+        input_sender.send(2).unwrap();
+        cmd_sender.send(10).unwrap();
+
+        let mut handled = Vec::new();
+        for message in batch(Queued::Input(0), &mut input, &mut cmd) {
+            // Sending a message on every update must not extend the batch.
+            input_sender.send(100).unwrap();
+            handled.push(match message {
+                Queued::Input(value) | Queued::Command(value) => value,
+            });
+        }
+
+        assert_eq!(handled, vec![0, 10, 1, 2]);
+        assert_eq!(input.len(), 4);
+    }
+
+    #[test]
+    fn priority_overtakes_queued_messages() {
+        let options = ChannelOptions::default().prioritize(|msg: &u8| *msg >= 10);
+        let (sender, receiver, priority) = options.channel();
+        let mut input = GuardedReceiver::with_priority(receiver, priority);
+
+        sender.send(1).unwrap();
+        sender.send(2).unwrap();
+        assert_eq!(input.try_next(), Some(1));
+
+        // Without coalescing, the receiver didn't take the queued message,
+        // so it's still subject to the overflow policy and behind priority messages.
+        assert_eq!(input.receive_stream.len(), 1);
+        sender.send(10).unwrap();
+        assert_eq!(input.try_next(), Some(10));
+        assert_eq!(input.try_next(), Some(2));
+        assert_eq!(input.try_next(), None);
+    }
+
+    #[test]
+    fn priority_overtakes_lookahead() {
+        let options = ChannelOptions::default().prioritize(|msg: &u8| *msg >= 10);
+        let (sender, receiver, priority) = options.channel();
+        let mut input = GuardedReceiver::with_priority(receiver, priority).coalesce(|a, b| a == b);
+
+        for msg in [1, 1, 2] {
+            sender.send(msg).unwrap();
+        }
+        assert_eq!(input.try_next(), Some(1));
+        assert!(input.lookahead.is_some());
+
+        sender.send(10).unwrap();
+        assert_eq!(input.try_next(), Some(10));
+        assert_eq!(input.try_next(), Some(2));
+    }
+
+    #[gtk::test]
+    fn batched_view_updates() {
+        let harness = ComponentHarness::launch_with(Sum::builder().batch_view_updates(), ());
+
+        for value in 1..=3 {
+            harness.emit(value);
+        }
+        harness.settle();
+
+        assert_eq!(harness.model().0, 6);
+        assert_eq!(harness.widgets().update_view, 1);
+        assert_eq!(harness.widgets().update_with_view, 0);
+    }
+
+    #[gtk::test]
+    fn unbatched_view_updates() {
+        let harness = ComponentHarness::<Sum>::launch(());
+
+        for value in 1..=3 {
+            harness.emit(value);
+        }
+        harness.settle();
+
+        assert_eq!(harness.model().0, 6);
+        assert_eq!(harness.widgets().update_with_view, 3);
+        assert_eq!(harness.widgets().update_view, 3);
     }
 }
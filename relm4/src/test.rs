@@ -0,0 +1,317 @@
+//! Utilities for testing components without a running [`RelmApp`](crate::RelmApp).
+//!
+//! A [`ComponentHarness`] launches a component inside its own
+//! [`glib::MainContext`] and lets you drive it step by step:
+//! send inputs, let the runtime settle and check the resulting state.
+//!
+//! ```no_run
+//! # use relm4::prelude::*;
+//! use relm4::test::ComponentHarness;
+//! # struct Counter(u8);
+//! # #[derive(Debug)]
+//! # enum CounterMsg { Increment }
+//! # impl SimpleComponent for Counter {
+//! #     type Input = CounterMsg;
+//! #     type Output = u8;
+//! #     type Init = u8;
+//! #     type Root = ();
+//! #     type Widgets = ();
+//! #     fn init_root() -> Self::Root {}
+//! #     fn init(init: u8, _: &(), _: ComponentSender<Self>) -> ComponentParts<Self> {
+//! #         ComponentParts { model: Counter(init), widgets: () }
+//! #     }
+//! #     fn update(&mut self, _: CounterMsg, sender: ComponentSender<Self>) {
+//! #         self.0 += 1;
+//! #         sender.output(self.0).unwrap();
+//! #     }
+//! # }
+//!
+//! #[gtk::test]
+//! fn increment() {
+//!     let harness = ComponentHarness::<Counter>::launch(0);
+//!
+//!     harness.send(CounterMsg::Increment);
+//!
+//!     assert_eq!(harness.model().0, 1);
+//!     assert_eq!(harness.take_outputs(), vec![1]);
+//! }
+//! ```
+
+use std::cell::{Ref, RefCell};
+use std::collections::VecDeque;
+use std::fmt::{self, Debug};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use gtk::glib;
+
+use crate::{Component, ComponentBuilder, ComponentController, Controller};
+
+/// Runs a [`Component`] in a private [`glib::MainContext`] for testing.
+///
+/// All futures spawned by the component through [`spawn_local`](crate::spawn_local)
+/// run on the private context of the harness, so they only make progress
+/// while the harness is [settling](ComponentHarness::settle).
+///
+/// Outputs of the component and command outputs received by its runtime
+/// are collected and can be inspected with [`take_outputs`](ComponentHarness::take_outputs)
+/// and [`take_command_outputs`](ComponentHarness::take_command_outputs).
+///
+/// GTK must be initialized on the current thread, which is done by [`gtk::test`]
+/// or automatically when the harness is launched.
+pub struct ComponentHarness<C: Component> {
+    context: glib::MainContext,
+    controller: Controller<C>,
+    outputs: Rc<RefCell<VecDeque<C::Output>>>,
+    command_outputs: Rc<RefCell<Vec<String>>>,
+}
+
+impl<C: Component> ComponentHarness<C> {
+    /// Launches the component with its default builder and
+    /// settles the runtime afterwards.
+    #[must_use]
+    pub fn launch(payload: C::Init) -> Self {
+        if !gtk::is_initialized() {
+            crate::init();
+        }
+        Self::launch_with(C::builder(), payload)
+    }
+
+    /// Launches the component from a pre-configured builder and
+    /// settles the runtime afterwards.
+    #[must_use]
+    pub fn launch_with(mut builder: ComponentBuilder<C>, payload: C::Init) -> Self {
+        let context = glib::MainContext::new();
+        let outputs = Rc::new(RefCell::new(VecDeque::new()));
+        let command_outputs = Rc::new(RefCell::new(Vec::new()));
+
+        {
+            let command_outputs = command_outputs.clone();
+            builder.hooks.on_command(move |message| {
+                command_outputs.borrow_mut().push(format!("{:?}", message));
+            });
+        }
+
+        let controller = context
+            .with_thread_default(|| {
+                let outputs = outputs.clone();
+                let controller = builder.launch(payload).connect_receiver(move |_, message| {
+                    outputs.borrow_mut().push_back(message);
+                });
+                crate::late_initialization::run_late_init();
+                controller
+            })
+            .expect("Couldn't acquire the main context of the test harness");
+
+        let harness = Self {
+            context,
+            controller,
+            outputs,
+            command_outputs,
+        };
+        harness.settle();
+        harness
+    }
+
+    /// Queues an input message without processing it.
+    ///
+    /// Use this to send several messages at once before
+    /// calling [`settle`](Self::settle).
+    pub fn emit(&self, message: C::Input) {
+        self.controller.emit(message);
+    }
+
+    /// Sends an input message and settles the runtime afterwards.
+    pub fn send(&self, message: C::Input) {
+        self.emit(message);
+        self.settle();
+    }
+
+    /// Runs the private main context until no more events are pending.
+    ///
+    /// Note that commands running in the background might still deliver
+    /// their results later. Use [`settle_until`](Self::settle_until) to wait for them.
+    pub fn settle(&self) {
+        self.context
+            .with_thread_default(|| while self.context.iteration(false) {})
+            .expect("Couldn't acquire the main context of the test harness");
+    }
+
+    /// Settles the runtime repeatedly until `condition` returns `true`
+    /// or the `timeout` expired.
+    ///
+    /// Returns `false` if the timeout expired.
+    pub fn settle_until<F>(&self, timeout: Duration, mut condition: F) -> bool
+    where
+        F: FnMut(&Self) -> bool,
+    {
+        let start = Instant::now();
+        loop {
+            self.settle();
+            if condition(self) {
+                return true;
+            } else if start.elapsed() >= timeout {
+                return false;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Returns a reference to the model of the component.
+    pub fn model(&self) -> Ref<'_, C> {
+        self.controller.model()
+    }
+
+    /// Returns a reference to the widgets of the component.
+    pub fn widgets(&self) -> Ref<'_, C::Widgets> {
+        self.controller.widgets()
+    }
+
+    /// Returns the root widget of the component.
+    pub fn widget(&self) -> &C::Root {
+        self.controller.widget()
+    }
+
+    /// Provides access to the controller of the component.
+    pub const fn controller(&self) -> &Controller<C> {
+        &self.controller
+    }
+
+    /// Removes and returns the oldest collected output message.
+    #[must_use]
+    pub fn pop_output(&self) -> Option<C::Output> {
+        self.outputs.borrow_mut().pop_front()
+    }
+
+    /// Removes and returns all collected output messages.
+    #[must_use]
+    pub fn take_outputs(&self) -> Vec<C::Output> {
+        self.outputs.borrow_mut().drain(..).collect()
+    }
+
+    /// Removes and returns the [`Debug`] representation of all
+    /// command outputs that were handled by the runtime.
+    #[must_use]
+    pub fn take_command_outputs(&self) -> Vec<String> {
+        self.command_outputs.borrow_mut().drain(..).collect()
+    }
+
+    /// Shuts down the component and returns all remaining output messages,
+    /// including those sent by [`Component::shutdown`].
+    pub fn shutdown(self) -> Vec<C::Output> {
+        let Self {
+            context,
+            controller,
+            outputs,
+            ..
+        } = self;
+
+        drop(controller);
+        context
+            .with_thread_default(|| while context.iteration(false) {})
+            .expect("Couldn't acquire the main context of the test harness");
+
+        Vec::from(outputs.take())
+    }
+}
+
+impl<C> Debug for ComponentHarness<C>
+where
+    C: Component + Debug,
+    C::Widgets: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ComponentHarness")
+            .field("context", &self.context)
+            .field("controller", &self.controller)
+            .field("outputs", &self.outputs)
+            .field("command_outputs", &self.command_outputs)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::{Component, ComponentParts, ComponentSender, Sender};
+
+    use super::ComponentHarness;
+
+    #[derive(Debug)]
+    struct Counter {
+        value: u8,
+    }
+
+    #[derive(Debug)]
+    enum CounterMsg {
+        Increment,
+        Fetch,
+    }
+
+    impl Component for Counter {
+        type CommandOutput = u8;
+        type Input = CounterMsg;
+        type Output = u8;
+        type Init = u8;
+        type Root = ();
+        type Widgets = ();
+
+        fn init_root() -> Self::Root {}
+
+        fn init(
+            value: Self::Init,
+            _root: &Self::Root,
+            _sender: ComponentSender<Self>,
+        ) -> ComponentParts<Self> {
+            ComponentParts {
+                model: Counter { value },
+                widgets: (),
+            }
+        }
+
+        fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, _: &()) {
+            match message {
+                CounterMsg::Increment => {
+                    self.value += 1;
+                    sender.output(self.value).unwrap();
+                }
+                CounterMsg::Fetch => sender.oneshot_command(async { 42 }),
+            }
+        }
+
+        fn update_cmd(&mut self, value: Self::CommandOutput, _: ComponentSender<Self>, _: &()) {
+            self.value = value;
+        }
+
+        fn shutdown(&mut self, _widgets: &mut Self::Widgets, output: Sender<Self::Output>) {
+            output.emit(0);
+        }
+    }
+
+    #[gtk::test]
+    fn send_settle_assert() {
+        let harness = ComponentHarness::<Counter>::launch(1);
+        assert_eq!(harness.model().value, 1);
+
+        harness.emit(CounterMsg::Increment);
+        harness.emit(CounterMsg::Increment);
+        assert_eq!(harness.model().value, 1);
+
+        harness.settle();
+        assert_eq!(harness.model().value, 3);
+        assert_eq!(harness.take_outputs(), vec![2, 3]);
+        assert!(harness.take_outputs().is_empty());
+
+        assert_eq!(harness.shutdown(), vec![0]);
+    }
+
+    #[gtk::test]
+    fn command_outputs() {
+        let harness = ComponentHarness::<Counter>::launch(0);
+
+        harness.send(CounterMsg::Fetch);
+        assert!(harness.settle_until(Duration::from_secs(5), |h| h.model().value == 42));
+        assert_eq!(harness.take_command_outputs(), vec!["42".to_owned()]);
+    }
+}
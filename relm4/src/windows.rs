@@ -0,0 +1,307 @@
+//! Open components as additional top-level windows of the application.
+//!
+//! Windows opened through this module are attached to the [`main_application`](crate::main_application)
+//! and tracked until they are destroyed.
+//! Closing a window shuts down its component, unless the window
+//! [hides on close](gtk::prelude::GtkWindowExt::set_hide_on_close).
+//! Once the last tracked window was destroyed, the application quits,
+//! unless the [`before_shutdown`](crate::RelmApp::before_shutdown) hook vetoes.
+//!
+//! The main window launched by [`RelmApp`](crate::RelmApp) is tracked as well.
+//!
+//! ```no_run
+//! # use relm4::prelude::*;
+//! # type Document = ();
+//! # let path = ();
+//! // For example in the handler of a "New Window" action:
+//! let handle = relm4::windows::open::<Document>(path);
+//! ```
+
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use gtk::prelude::{ApplicationExt, Cast, GtkApplicationExt, GtkWindowExt, IsA, WidgetExt};
+
+use crate::{Component, ComponentBuilder, ComponentController, Sender};
+
+thread_local! {
+    static WINDOWS: RefCell<BTreeMap<WindowId, TrackedWindow>> = RefCell::default();
+    static NEXT_ID: Cell<u64> = Cell::new(0);
+}
+
+/// Identifies a tracked window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WindowId(u64);
+
+struct TrackedWindow {
+    window: gtk::Window,
+    /// Keeps the component alive while the window is open.
+    _controller: Box<dyn Any>,
+}
+
+/// A handle to a component that was opened as a window.
+pub struct WindowHandle<C: Component> {
+    id: WindowId,
+    window: gtk::Window,
+    sender: Sender<C::Input>,
+}
+
+impl<C: Component> WindowHandle<C> {
+    /// The identifier of the window.
+    #[must_use]
+    pub const fn id(&self) -> WindowId {
+        self.id
+    }
+
+    /// The window of the component.
+    #[must_use]
+    pub const fn window(&self) -> &gtk::Window {
+        &self.window
+    }
+
+    /// The input sender of the component.
+    #[must_use]
+    pub const fn sender(&self) -> &Sender<C::Input> {
+        &self.sender
+    }
+
+    /// Emits an input to the component.
+    pub fn emit(&self, message: C::Input) {
+        self.sender.emit(message);
+    }
+
+    /// Closes the window, which also shuts down the component
+    /// unless the window hides on close.
+    pub fn close(&self) {
+        self.window.close();
+    }
+}
+
+impl<C: Component> Clone for WindowHandle<C> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            window: self.window.clone(),
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<C: Component> fmt::Debug for WindowHandle<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WindowHandle")
+            .field("id", &self.id)
+            .field("window", &self.window)
+            .field("sender", &self.sender)
+            .finish()
+    }
+}
+
+/// Launches a component and opens its root widget as a new window.
+pub fn open<C>(payload: C::Init) -> WindowHandle<C>
+where
+    C: Component,
+    C::Root: IsA<gtk::Window>,
+{
+    open_with(C::builder(), payload)
+}
+
+/// Launches a component from a pre-configured builder
+/// and opens its root widget as a new window.
+pub fn open_with<C>(builder: ComponentBuilder<C>, payload: C::Init) -> WindowHandle<C>
+where
+    C: Component,
+    C::Root: IsA<gtk::Window>,
+{
+    let controller = builder.launch(payload).detach();
+
+    // Run late initialization for transient windows for example.
+    crate::late_initialization::run_late_init();
+
+    let window: gtk::Window = controller.widget().clone().upcast();
+    let sender = controller.sender().clone();
+    let id = track(&window, Box::new(controller));
+
+    window.set_visible(true);
+
+    WindowHandle { id, window, sender }
+}
+
+/// Adds a window to the main application and tracks it until it's destroyed.
+///
+/// `controller` is dropped once the window was destroyed.
+pub(crate) fn track(window: &gtk::Window, controller: Box<dyn Any>) -> WindowId {
+    crate::main_application().add_window(window);
+    watch(window, controller)
+}
+
+/// Tracks a window until it's destroyed.
+fn watch(window: &gtk::Window, controller: Box<dyn Any>) -> WindowId {
+    let id = WindowId(NEXT_ID.with(|next| next.replace(next.get() + 1)));
+
+    window.connect_close_request(move |window| {
+        // Destroying the last window quits the application, so ask first.
+        if count() == 1 && !window.hides_on_close() && crate::app::has_before_shutdown() {
+            let window = window.clone();
+            crate::app::quit_after_check(move || window.destroy());
+            gtk::Inhibit(true)
+        } else {
+            gtk::Inhibit(false)
+        }
+    });
+
+    // Also covers windows that are destroyed without being closed.
+    window.connect_destroy(move |_| untrack(id));
+
+    WINDOWS.with(|windows| {
+        windows.borrow_mut().insert(
+            id,
+            TrackedWindow {
+                window: window.clone(),
+                _controller: controller,
+            },
+        )
+    });
+
+    id
+}
+
+fn untrack(id: WindowId) {
+    let removed = WINDOWS.with(|windows| windows.borrow_mut().remove(&id));
+
+    // Drop the component outside of the borrow.
+    if removed.is_some() {
+        drop(removed);
+
+        if count() == 0 {
+            crate::main_application().quit();
+        }
+    }
+}
+
+/// Closes a tracked window, which also shuts down its component
+/// unless the window hides on close.
+///
+/// Returns `false` if no window with this identifier is open.
+pub fn close(id: WindowId) -> bool {
+    if let Some(window) = window(id) {
+        window.close();
+        true
+    } else {
+        false
+    }
+}
+
+/// Returns a tracked window.
+#[must_use]
+pub fn window(id: WindowId) -> Option<gtk::Window> {
+    WINDOWS.with(|windows| {
+        windows
+            .borrow()
+            .get(&id)
+            .map(|tracked| tracked.window.clone())
+    })
+}
+
+/// Returns the identifiers of all tracked windows in the order they were opened.
+#[must_use]
+pub fn ids() -> Vec<WindowId> {
+    WINDOWS.with(|windows| windows.borrow().keys().copied().collect())
+}
+
+/// Returns the number of tracked windows.
+#[must_use]
+pub fn count() -> usize {
+    WINDOWS.with(|windows| windows.borrow().len())
+}
+
+/// Presents the most recently opened window that is still visible.
+///
+/// Returns `false` if no tracked window is visible.
+pub fn present_latest() -> bool {
+    let latest = WINDOWS.with(|windows| {
+        windows
+            .borrow()
+            .values()
+            .rev()
+            .map(|tracked| tracked.window.clone())
+            .find(WidgetExt::is_visible)
+    });
+
+    if let Some(window) = latest {
+        window.present();
+        true
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+
+    use futures::FutureExt;
+    use gtk::prelude::{GtkWindowExt, WidgetExt};
+
+    use super::{close, watch, window, WindowId};
+
+    fn open_window(hide_on_close: bool) -> (gtk::Window, WindowId) {
+        let window = gtk::Window::new();
+        window.set_hide_on_close(hide_on_close);
+        // Only realized windows can be closed.
+        window.present();
+        let id = watch(&window, Box::new(()));
+        (window, id)
+    }
+
+    #[gtk::test]
+    fn closing_untracks() {
+        let (_window, id) = open_window(false);
+        assert!(window(id).is_some());
+
+        assert!(close(id));
+        assert!(window(id).is_none());
+        assert!(!close(id));
+    }
+
+    #[gtk::test]
+    fn hidden_windows_stay_tracked() {
+        let (hidden, id) = open_window(true);
+
+        assert!(close(id));
+        assert!(!hidden.is_visible());
+        assert_eq!(window(id), Some(hidden.clone()));
+
+        hidden.destroy();
+        assert!(window(id).is_none());
+    }
+
+    #[gtk::test]
+    fn destroying_untracks_and_drops_controller() {
+        let controller = Rc::new(());
+        let window = gtk::Window::new();
+        let id = watch(&window, Box::new(controller.clone()));
+        assert_eq!(Rc::strong_count(&controller), 2);
+
+        window.destroy();
+        assert!(super::window(id).is_none());
+        assert_eq!(Rc::strong_count(&controller), 1);
+    }
+
+    #[gtk::test]
+    fn before_shutdown_vetoes_closing_last_window() {
+        let (last, id) = open_window(false);
+        crate::app::set_before_shutdown(Rc::new(|| async { false }.boxed_local()));
+
+        assert!(close(id));
+        let context = gtk::glib::MainContext::default();
+        while context.iteration(false) {}
+        assert_eq!(window(id), Some(last.clone()));
+
+        crate::app::clear_before_shutdown();
+        last.destroy();
+        assert!(window(id).is_none());
+    }
+}